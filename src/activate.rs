@@ -0,0 +1,134 @@
+use crate::environment::environment_root;
+use std::path::Path;
+
+/// How to activate an environment in one shell.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Activation {
+    pub(crate) shell: &'static str,
+    pub(crate) command: String,
+}
+
+/// Quotes `path` for a shell command line when it contains spaces.
+fn quoted(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    if path.contains(' ') {
+        format!("\"{}\"", path)
+    } else {
+        path.into_owned()
+    }
+}
+
+/// The commands that activate the environment of `python_path` in each shell
+/// it has an activation script for. Conda-style environments are activated
+/// by prefix with their manager; interpreters without activation scripts
+/// (system or pyenv installs) get their directory prepended to `PATH`.
+pub(crate) fn activation_commands(python_path: &Path, provider: &str) -> Vec<Activation> {
+    let Some(env_root) = environment_root(python_path) else {
+        return Vec::new();
+    };
+    let activation = |shell, command| Activation { shell, command };
+
+    match provider {
+        "conda" => {
+            return vec![activation(
+                "conda",
+                format!("conda activate {}", quoted(env_root)),
+            )]
+        }
+        "mamba" => {
+            return vec![activation(
+                "micromamba",
+                format!("micromamba activate {}", quoted(env_root)),
+            )]
+        }
+        _ => {}
+    }
+
+    let mut activations = Vec::new();
+    for scripts_dir in ["bin", "Scripts"] {
+        let scripts = env_root.join(scripts_dir);
+        let script = |name: &str| Some(scripts.join(name)).filter(|path| path.is_file());
+        if let Some(script) = script("activate") {
+            activations.push(activation(
+                "bash/zsh",
+                format!("source {}", quoted(&script)),
+            ));
+        }
+        if let Some(script) = script("activate.fish") {
+            activations.push(activation("fish", format!("source {}", quoted(&script))));
+        }
+        if let Some(script) = script("activate.csh") {
+            activations.push(activation(
+                "csh/tcsh",
+                format!("source {}", quoted(&script)),
+            ));
+        }
+        if let Some(script) = script("activate.nu") {
+            activations.push(activation(
+                "nushell",
+                format!("overlay use {}", quoted(&script)),
+            ));
+        }
+        if let Some(script) = script("Activate.ps1") {
+            activations.push(activation("PowerShell", format!("& {}", quoted(&script))));
+        }
+        if let Some(script) = script("activate.bat") {
+            activations.push(activation("cmd.exe", quoted(&script)));
+        }
+    }
+
+    if activations.is_empty() {
+        let bin_dir = python_path.parent().unwrap_or(env_root);
+        activations.push(activation(
+            "POSIX shells",
+            format!("export PATH=\"{}:$PATH\"", bin_dir.display()),
+        ));
+        activations.push(activation(
+            "PowerShell",
+            format!("$env:PATH = \"{};$env:PATH\"", bin_dir.display()),
+        ));
+    }
+    activations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+
+    #[test]
+    fn sources_venv_scripts() {
+        let fixture = Fixture::new();
+        let python = fixture.venv("my env");
+        fixture.touch("my env/bin/activate");
+        fixture.touch("my env/bin/activate.fish");
+
+        let root = fixture.path().join("my env");
+        assert_eq!(
+            activation_commands(&python, "venv"),
+            [
+                Activation {
+                    shell: "bash/zsh",
+                    command: format!("source \"{}\"", root.join("bin/activate").display()),
+                },
+                Activation {
+                    shell: "fish",
+                    command: format!("source \"{}\"", root.join("bin/activate.fish").display()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn activates_conda_by_prefix() {
+        let activations = activation_commands(Path::new("/opt/conda/envs/ml/bin/python"), "conda");
+        assert_eq!(activations.len(), 1);
+        assert_eq!(activations[0].command, "conda activate /opt/conda/envs/ml");
+    }
+
+    #[test]
+    fn prepends_path_without_scripts() {
+        let activations = activation_commands(Path::new("/usr/bin/python3"), "system");
+        assert_eq!(activations[0].command, "export PATH=\"/usr/bin:$PATH\"");
+    }
+}
@@ -0,0 +1,1611 @@
+use crate::activate::activation_commands;
+use crate::environment::{
+    diff_packages, environment_executable, environment_root, find_on_path, find_python_executable,
+    has_package, is_venv, list_installed_packages, list_outdated_packages, python_command,
+    Installer, PythonEnvironment,
+};
+use crate::error::PyEnvError;
+use crate::kernels::{install_kernel, kernel_name};
+use crate::logging::{self, Level};
+use crate::process::{
+    command_stdout, command_stdout_with_timeout, output_with_timeout, parallel_map,
+    NETWORK_COMMAND_TIMEOUT,
+};
+use crate::project::{diff_requirement_lines, read_requires_python, version_satisfies};
+use crate::pytest::run_pytest;
+use crate::selection::{project_scope, write_pin_file, SelectedEnvironment};
+use crate::sync::SyncManager;
+use crate::tasks::{debug_configurations, merge_generated, python_tasks, TASK_LABEL_PREFIX};
+use crate::which::locate_modules;
+use crate::{interpreter_setting_path, set_json_path, PythonEnvironmentSelectExtension};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use zed_extension_api::{
+    self as zed, serde_json::json, SlashCommand, SlashCommandArgumentCompletion,
+    SlashCommandOutput, SlashCommandOutputSection, Worktree,
+};
+
+/// How many log entries `/pyenvlog` shows by default.
+const DEFAULT_LOG_LINES: usize = 100;
+
+/// Directories Zed's terminal checks for a venv to activate when
+/// `terminal.detect_venv` isn't configured.
+const TERMINAL_VENV_DIRECTORIES: &[&str] = &[".env", "env", ".venv", "venv"];
+
+/// Reads `<worktree>/.zed/<file_name>`, treating a missing or empty file as
+/// `empty` and rejecting contents of a different JSON type.
+fn read_zed_file(
+    root_path: &Path,
+    file_name: &str,
+    empty: zed::serde_json::Value,
+) -> Result<zed::serde_json::Value, PyEnvError> {
+    let path = root_path.join(".zed").join(file_name);
+    let value = match fs::read_to_string(&path) {
+        Ok(contents) if !contents.trim().is_empty() => {
+            serde_json::from_str::<zed::serde_json::Value>(&contents).map_err(|e| {
+                PyEnvError::Parse {
+                    context: path.display().to_string(),
+                    message: format!("{}; comments and trailing commas are not supported", e),
+                }
+            })?
+        }
+        _ => return Ok(empty),
+    };
+    if value.is_object() != empty.is_object() || value.is_array() != empty.is_array() {
+        return Err(PyEnvError::Parse {
+            context: path.display().to_string(),
+            message: format!(
+                "not a JSON {}",
+                if empty.is_array() { "array" } else { "object" }
+            ),
+        });
+    }
+    Ok(value)
+}
+
+fn read_project_settings(root_path: &Path) -> Result<zed::serde_json::Value, PyEnvError> {
+    read_zed_file(root_path, "settings.json", json!({}))
+}
+
+/// Writes `value` to `<worktree>/.zed/<file_name>`, returning its path.
+fn write_zed_file(
+    root_path: &Path,
+    file_name: &str,
+    value: &zed::serde_json::Value,
+) -> Result<PathBuf, PyEnvError> {
+    let path = root_path.join(".zed").join(file_name);
+    let contents = serde_json::to_string_pretty(value).map_err(|e| PyEnvError::Io {
+        context: format!("Failed to serialize {}", file_name),
+        source: e.into(),
+    })?;
+    fs::create_dir_all(root_path.join(".zed"))
+        .map_err(PyEnvError::io("Failed to create .zed directory"))?;
+    fs::write(&path, contents + "\n").map_err(PyEnvError::io(format!(
+        "Failed to write {}",
+        path.display()
+    )))?;
+    Ok(path)
+}
+
+/// Resolves a worktree-relative file, refusing paths that lead outside the
+/// worktree.
+fn worktree_file(root_path: &Path, relative: &str) -> Result<PathBuf, PyEnvError> {
+    let path = root_path.join(relative);
+    let canonical = fs::canonicalize(&path).map_err(PyEnvError::io(format!(
+        "Failed to find {} in the worktree",
+        relative
+    )))?;
+    let canonical_root = fs::canonicalize(root_path).unwrap_or_else(|_| root_path.to_path_buf());
+    if !canonical.starts_with(&canonical_root) || !canonical.is_file() {
+        return Err(PyEnvError::InvalidSelection(format!(
+            "{} is not a file inside the worktree",
+            relative
+        )));
+    }
+    Ok(path)
+}
+
+/// Resolves a worktree-relative path for a file the extension writes,
+/// refusing absolute paths and `..`.
+fn worktree_output_path(root_path: &Path, relative: &str) -> Result<PathBuf, PyEnvError> {
+    let relative_path = Path::new(relative);
+    let stays_inside = relative_path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if !stays_inside || relative.is_empty() {
+        return Err(PyEnvError::InvalidSelection(format!(
+            "{} must be a path inside the worktree",
+            relative
+        )));
+    }
+    Ok(root_path.join(relative_path))
+}
+
+/// Where `/pyenvcreate` puts an environment named `env_dir`: a directory
+/// inside the worktree that doesn't exist yet.
+fn new_environment_path(root_path: &Path, env_dir: &str) -> Result<PathBuf, PyEnvError> {
+    let env_path = worktree_output_path(root_path, env_dir)?;
+    if env_path.exists() {
+        return Err(PyEnvError::InvalidSelection(format!(
+            "{} already exists",
+            env_path.display()
+        )));
+    }
+    Ok(env_path)
+}
+
+/// Removes every occurrence of `flag` from `args`, returning whether it was present.
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let len = args.len();
+    args.retain(|arg| arg != flag);
+    args.len() != len
+}
+
+/// Removes `option <value>` from `args`, returning the value if present.
+fn take_option(args: &mut Vec<String>, option: &str) -> Result<Option<String>, PyEnvError> {
+    let Some(index) = args.iter().position(|arg| arg == option) else {
+        return Ok(None);
+    };
+    if index + 1 >= args.len() {
+        return Err(PyEnvError::InvalidSelection(format!(
+            "{} expects a value",
+            option
+        )));
+    }
+    let value = args.remove(index + 1);
+    args.remove(index);
+    Ok(Some(value))
+}
+
+impl PythonEnvironmentSelectExtension {
+    /// The environment a per-environment command operates on: the one named
+    /// by `args`, or the current selection when `args` is empty. A
+    /// `--path <dir>` argument picks the selection for that project directory.
+    /// Commands that run the interpreter themselves need it on the host, so
+    /// a guest's environment is refused.
+    fn target_environment(
+        &self,
+        worktree: Option<&Worktree>,
+        args: &[String],
+    ) -> Result<SelectedEnvironment, PyEnvError> {
+        let selected = self.target_any_environment(worktree, args)?;
+        match &selected.guest {
+            Some(guest) => Err(PyEnvError::InvalidSelection(format!(
+                "{} runs inside {}; this command needs an interpreter on the host, \
+                 so pass one by name or use /pyenvtasks to run Python there",
+                selected.name, guest
+            ))),
+            None => Ok(selected),
+        }
+    }
+
+    /// Like [`Self::target_environment`], but also accepts an environment
+    /// inside a container or WSL distro. Names and positions resolve against
+    /// the environments `/pyenvlst` shows, other worktrees' included.
+    fn target_any_environment(
+        &self,
+        worktree: Option<&Worktree>,
+        args: &[String],
+    ) -> Result<SelectedEnvironment, PyEnvError> {
+        let mut args = args.to_vec();
+        if let Some(path) = take_option(&mut args, "--path")? {
+            let worktree = worktree.ok_or_else(|| {
+                PyEnvError::InvalidSelection("--path requires a worktree".to_string())
+            })?;
+            let scope = project_scope(Path::new(&worktree.root_path()), &path)?;
+            if args.is_empty() {
+                return self
+                    .current_selection(worktree, Some(&scope))
+                    .filter(|selected| !selected.is_missing())
+                    .or_else(|| self.effective_selection(worktree))
+                    .ok_or_else(|| {
+                        PyEnvError::InvalidSelection(format!(
+                            "no environment selected for {}; run /pyenvselect <name> --path {} first",
+                            path, path
+                        ))
+                    });
+            }
+        }
+
+        if !args.is_empty() {
+            let all_envs = self.workspace_discovery(worktree).environments;
+            let env = self.resolve_environment(&all_envs, &args.join(" "))?;
+            return Ok(SelectedEnvironment::from_environment(env));
+        }
+
+        worktree
+            .and_then(|worktree| self.effective_selection(worktree))
+            .ok_or_else(|| {
+                PyEnvError::InvalidSelection(
+                    "no environment selected; pass an environment name or run /pyenvselect first"
+                        .to_string(),
+                )
+            })
+    }
+
+    /// Deletes the worktree virtual environment named `query`. Only venvs living
+    /// inside the worktree are eligible; conda, pyenv and system interpreters
+    /// are never touched. Without `confirm` this only reports what would go.
+    fn delete_environment(
+        &self,
+        worktree: &Worktree,
+        query: &str,
+        confirm: bool,
+    ) -> Result<String, PyEnvError> {
+        let root_path = PathBuf::from(worktree.root_path());
+        let all_envs = self.workspace_discovery(Some(worktree)).environments;
+        let env = self.resolve_environment(&all_envs, query)?;
+
+        let env_root = environment_root(&env.python_path)
+            .filter(|env_root| env_root.starts_with(&root_path) && *env_root != root_path)
+            .filter(|env_root| is_venv(env_root))
+            .ok_or_else(|| {
+                PyEnvError::InvalidSelection(format!(
+                    "refusing to delete {} ({}): only virtual environments inside the worktree can be deleted",
+                    env.name, env.provider
+                ))
+            })?
+            .to_path_buf();
+
+        if !confirm {
+            return Ok(format!(
+                "This will permanently delete {}.\nRun /pyenvdelete {} --confirm to proceed.",
+                env_root.display(),
+                query
+            ));
+        }
+
+        fs::remove_dir_all(&env_root).map_err(PyEnvError::io(format!(
+            "Failed to delete {}",
+            env_root.display()
+        )))?;
+        self.invalidate_cache(Some(&root_path));
+
+        let mut text = format!("Deleted {}", env_root.display());
+        if let Ok(mut state) = self.state.lock() {
+            let was_selected = state.forget_environment(&env_root);
+            state.save()?;
+            if was_selected {
+                text.push_str(
+                    "\nIt was the selected environment; run /pyenvselect to pick another.",
+                );
+            }
+        }
+        self.export_selection(worktree);
+        Ok(text)
+    }
+
+    /// Creates a virtual environment in the worktree from `base_python`, with
+    /// `uv venv` when `use_uv` is set and `python -m venv` otherwise, then
+    /// selects it.
+    fn create_environment(
+        &self,
+        worktree: &Worktree,
+        base_python: &Path,
+        env_dir: &str,
+        use_uv: bool,
+    ) -> Result<SelectedEnvironment, PyEnvError> {
+        let root_path = PathBuf::from(worktree.root_path());
+        let env_path = new_environment_path(&root_path, env_dir)?;
+
+        let mut command = if use_uv {
+            let mut command = Command::new("uv");
+            command
+                .arg("venv")
+                .arg(&env_path)
+                .arg("--python")
+                .arg(base_python);
+            command
+        } else {
+            let mut command = Command::new(base_python);
+            command.args(["-m", "venv"]).arg(&env_path);
+            command
+        };
+        command_stdout_with_timeout(command.current_dir(&root_path), NETWORK_COMMAND_TIMEOUT)?;
+
+        let python_path =
+            find_python_executable(&env_path).ok_or_else(|| PyEnvError::DiscoveryFailed {
+                context: env_path.display().to_string(),
+                message: "no interpreter found in the new environment".to_string(),
+            })?;
+        self.invalidate_cache(Some(&root_path));
+
+        let selected = SelectedEnvironment::from_environment(&PythonEnvironment {
+            name: env_dir.to_string(),
+            python_path,
+            provider: if use_uv { "uv" } else { "venv" }.to_string(),
+            ..Default::default()
+        });
+        self.store_selection(&root_path, selected.clone())?;
+        self.export_selection(worktree);
+        Ok(selected)
+    }
+
+    /// Registers `env` as a Jupyter kernel named `name` (derived from the
+    /// environment name by default). ipykernel must be installed in the
+    /// environment; with `install_ipykernel` it is installed first if missing.
+    fn register_kernel(
+        &self,
+        worktree: Option<&Worktree>,
+        env: &SelectedEnvironment,
+        name: Option<String>,
+        install_ipykernel: bool,
+    ) -> Result<String, PyEnvError> {
+        let mut text = String::new();
+        if !has_package(&env.python_path, "ipykernel")? {
+            if !install_ipykernel {
+                return Err(PyEnvError::InvalidSelection(format!(
+                    "ipykernel is not installed in {}; run /pyenvkernel {} --install-ipykernel to install it first",
+                    env.name, env.name
+                )));
+            }
+            Installer::for_environment(&env.python_path, false).install(
+                &env.python_path,
+                None,
+                &["ipykernel"],
+            )?;
+            text.push_str(&format!("Installed ipykernel in {}\n", env.name));
+        }
+
+        let name = name.unwrap_or_else(|| kernel_name(&env.name));
+        let report = install_kernel(&env.python_path, &name, &format!("Python ({})", env.name))?;
+        text.push_str(&format!(
+            "Registered kernel {} for {}\nInterpreter: {}\n{}",
+            name,
+            env.name,
+            env.python_path.display(),
+            report
+        ));
+
+        // The kernel shows up in /pyenvlst once the environments are rescanned.
+        let root_path = worktree.map(|worktree| PathBuf::from(worktree.root_path()));
+        self.invalidate_cache(root_path.as_deref());
+        Ok(text)
+    }
+
+    /// The `requires-python` constraint of the `pyproject.toml` in `scope` (the
+    /// worktree root when `None`), with the file's worktree-relative path.
+    fn requires_python(worktree: &Worktree, scope: Option<&Path>) -> Option<(String, PathBuf)> {
+        let root_path = PathBuf::from(worktree.root_path());
+        let pyproject = scope
+            .and_then(|scope| scope.strip_prefix(&root_path).ok())
+            .unwrap_or(Path::new(""))
+            .join("pyproject.toml");
+        let contents = worktree.read_text_file(&pyproject.to_string_lossy()).ok()?;
+        Some((read_requires_python(&contents)?, pyproject))
+    }
+
+    /// Writes the interpreter into `<worktree>/.zed/settings.json` under
+    /// `lsp.<language_server>.settings` (e.g. `python.pythonPath`) so it is shared with
+    /// the team and with Zed's own Python support. When the environment has its
+    /// own ruff, `lsp.ruff.binary` is pointed at it too. Returns the path that was written.
+    fn persist_to_project_settings(
+        root_path: &Path,
+        selected: &SelectedEnvironment,
+        language_server: &str,
+    ) -> Result<PathBuf, PyEnvError> {
+        let mut settings = read_project_settings(root_path)?;
+
+        // Keep in-worktree interpreters relative so the file works on every checkout.
+        let python_path = selected
+            .python_path
+            .strip_prefix(root_path)
+            .unwrap_or(&selected.python_path);
+
+        let keys: Vec<&str> = ["lsp", language_server, "settings"]
+            .into_iter()
+            .chain(interpreter_setting_path(language_server).iter().copied())
+            .collect();
+        set_json_path(&mut settings, &keys, json!(python_path.to_string_lossy()));
+
+        // Point Zed's Ruff integration at the project's pinned ruff, if installed.
+        if let Some(ruff_path) = environment_executable(&selected.python_path, "ruff") {
+            let ruff_path = ruff_path.strip_prefix(root_path).unwrap_or(&ruff_path);
+            set_json_path(
+                &mut settings,
+                &["lsp", "ruff", "binary"],
+                json!({
+                    "path": ruff_path.to_string_lossy(),
+                    "arguments": ["server"],
+                }),
+            );
+        }
+
+        write_zed_file(root_path, "settings.json", &settings)
+    }
+
+    /// Adds the worktree venv at `env_root` to `terminal.detect_venv` in
+    /// `<worktree>/.zed/settings.json`, so Zed activates it in new terminals.
+    /// Returns the path that was written.
+    fn persist_terminal_activation(
+        root_path: &Path,
+        env_root: &Path,
+    ) -> Result<PathBuf, PyEnvError> {
+        let env_dir = env_root
+            .strip_prefix(root_path)
+            .ok()
+            .filter(|env_dir| !env_dir.as_os_str().is_empty() && is_venv(env_root))
+            .ok_or_else(|| {
+                PyEnvError::InvalidSelection(format!(
+                    "{} is not a virtual environment inside the worktree; Zed's terminal can only activate those",
+                    env_root.display()
+                ))
+            })?
+            .to_string_lossy()
+            .into_owned();
+
+        let mut settings = read_project_settings(root_path)?;
+        let keys = ["terminal", "detect_venv", "on", "directories"];
+        let mut directories: Vec<zed::serde_json::Value> = settings
+            .pointer(&format!("/{}", keys.join("/")))
+            .and_then(|directories| directories.as_array().cloned())
+            .unwrap_or_else(|| {
+                TERMINAL_VENV_DIRECTORIES
+                    .iter()
+                    .map(|dir| json!(dir))
+                    .collect()
+            });
+        if !directories.contains(&json!(env_dir)) {
+            directories.insert(0, json!(env_dir));
+        }
+        set_json_path(&mut settings, &keys, json!(directories));
+        write_zed_file(root_path, "settings.json", &settings)
+    }
+
+    fn complete_environment_names(&self, query: &str) -> Vec<SlashCommandArgumentCompletion> {
+        let root_path = self
+            .state
+            .lock()
+            .ok()
+            .and_then(|state| state.last_worktree_root.clone());
+        let query = query.to_lowercase();
+
+        let mut environments = self
+            .discover_environments(root_path.as_deref())
+            .environments;
+        // Offer what the project's own files name (e.g. `environment.yml` or
+        // `.python-version`) first.
+        environments.sort_by_key(|env| !(env.suggested || env.pinned));
+        environments
+            .iter()
+            .filter(|env| {
+                env.name.to_lowercase().contains(&query) || env.id.to_lowercase().contains(&query)
+            })
+            .map(|env| {
+                // A shared name would be ambiguous, so insert the identifier instead.
+                let shared = environments
+                    .iter()
+                    .filter(|other| other.name == env.name)
+                    .count()
+                    > 1;
+                let new_text = if shared { &env.id } else { &env.name };
+                let label = match &env.version {
+                    Some(version) => format!("{} ({})", new_text, version),
+                    None => new_text.clone(),
+                };
+                SlashCommandArgumentCompletion {
+                    label,
+                    new_text: new_text.clone(),
+                    run_command: true,
+                }
+            })
+            .collect()
+    }
+
+    /// Offers the entries of the last worktree's selection history by position.
+    fn complete_recent_selections(&self) -> Vec<SlashCommandArgumentCompletion> {
+        let Ok(state) = self.state.lock() else {
+            return Vec::new();
+        };
+        let history = state
+            .last_worktree_root
+            .as_ref()
+            .and_then(|root| state.history.get(root.to_string_lossy().as_ref()));
+        history
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(index, selected)| SlashCommandArgumentCompletion {
+                label: format!(
+                    "{}: {} ({})",
+                    index + 1,
+                    selected.name,
+                    selected.version.as_deref().unwrap_or("unknown")
+                ),
+                new_text: (index + 1).to_string(),
+                run_command: true,
+            })
+            .collect()
+    }
+
+    /// Completes the argument of one of the `extension.toml` slash commands.
+    pub(crate) fn complete_command_argument(
+        &self,
+        command: SlashCommand,
+        args: Vec<String>,
+    ) -> Result<Vec<SlashCommandArgumentCompletion>, String> {
+        match command.name.as_str() {
+            "pyenvcur" => Ok(vec![]),
+            "pyenvlst" => Ok(vec![]),
+            "pyenvrefresh" => Ok(vec![]),
+            "pyenvpkgs" => Ok(self.complete_environment_names(&args.join(" "))),
+            "pyenvinfo" => Ok(self.complete_environment_names(&args.join(" "))),
+            "pyenvcreate" => Ok(self.complete_environment_names(&args.join(" "))),
+            "pyenvdelete" => Ok(self.complete_environment_names(&args.join(" "))),
+            "pyenvdoctor" => Ok(self.complete_environment_names(&args.join(" "))),
+            "pyenvkernel" => Ok(self.complete_environment_names(&args.join(" "))),
+            "pyenvactivate" => Ok(self.complete_environment_names(&args.join(" "))),
+            "pyenvtasks" => Ok(self.complete_environment_names(&args.join(" "))),
+            "pyenvdebug" => Ok(self.complete_environment_names(&args.join(" "))),
+            "pyenvtest" => Ok(vec![]),
+            "pyenvrun" => Ok(vec![]),
+            "pyenvwhich" => Ok(vec![]),
+            "pyenvinstall" => Ok(vec![]),
+            "pyenvfreeze" => Ok(self.complete_environment_names(&args.join(" "))),
+            "pyenvsync" => Ok(vec![]),
+            "pyenvlog" => Ok(vec![]),
+            "pyenvrecent" => Ok(self.complete_recent_selections()),
+            "pyenvdiff" => Ok(self
+                .complete_environment_names(args.last().map(String::as_str).unwrap_or_default())),
+            "pyenvoutdated" => Ok(self.complete_environment_names(&args.join(" "))),
+            "pyenvreqs" => Ok(self.complete_environment_names(&args.join(" "))),
+            "pyenvselect" => Ok(self.complete_environment_names(&args.join(" "))),
+            command => Err(format!("unknown slash command: \"{command}\"")),
+        }
+    }
+
+    /// Runs one of the `extension.toml` slash commands.
+    pub(crate) fn execute_command(
+        &self,
+        command: SlashCommand,
+        args: Vec<String>,
+        _worktree: Option<&Worktree>,
+    ) -> Result<SlashCommandOutput, String> {
+        self.remember_worktree(_worktree);
+
+        match command.name.as_str() {
+            "pyenvcur" => {
+                let mut args = args;
+                let as_json = take_flag(&mut args, "--json");
+                let scope = match (take_option(&mut args, "--path")?, _worktree) {
+                    (Some(path), Some(worktree)) => {
+                        Some(project_scope(Path::new(&worktree.root_path()), &path)?)
+                    }
+                    (Some(_), None) => return Err("--path requires a worktree".to_string()),
+                    (None, _) => None,
+                };
+                if !args.is_empty() {
+                    return Err("/pyenvcur only accepts --json and --path <dir>".to_string());
+                }
+                if as_json {
+                    return Ok(self.render_current_selection_json(_worktree, scope.as_deref())?);
+                }
+
+                let text = self.describe_current_selection(_worktree, scope.as_deref());
+
+                Ok(SlashCommandOutput {
+                    sections: vec![SlashCommandOutputSection {
+                        range: (0..text.len()).into(),
+                        label: "Current Python Environment".to_string(),
+                    }],
+                    text,
+                })
+            }
+            "pyenvlst" => {
+                let discovery = self.workspace_discovery(_worktree);
+                let mut args = args;
+                let as_json = take_flag(&mut args, "--json");
+                let by_size = take_flag(&mut args, "--by-size");
+                let verbose = take_flag(&mut args, "--verbose");
+                if !args.is_empty() {
+                    return Err(
+                        "/pyenvlst only accepts --json, --by-size and --verbose".to_string()
+                    );
+                }
+                if let Ok(mut listing) = self.last_listing.lock() {
+                    *listing = discovery
+                        .environments
+                        .iter()
+                        .map(|env| env.python_path.clone())
+                        .collect();
+                }
+                if by_size {
+                    let sizes = parallel_map(&discovery.environments, |env| {
+                        self.environment_size(&env.python_path, &env.provider)
+                    });
+                    let mut environments: Vec<_> =
+                        discovery.environments.iter().zip(sizes).collect();
+                    environments.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+                    if let Ok(mut listing) = self.last_listing.lock() {
+                        *listing = environments
+                            .iter()
+                            .map(|(env, _)| env.python_path.clone())
+                            .collect();
+                    }
+                    return Ok(Self::render_size_list(&environments));
+                }
+                let selected = _worktree.and_then(|worktree| self.effective_selection(worktree));
+                if as_json {
+                    return Ok(Self::render_environment_json(
+                        &discovery,
+                        selected.as_ref(),
+                    )?);
+                }
+
+                let mut output = self.render_environment_list(&discovery, selected.as_ref());
+                if verbose {
+                    Self::append_diagnostics(&mut output, &discovery);
+                }
+                Ok(output)
+            }
+            "pyenvpkgs" => {
+                let mut args = args;
+                let as_json = take_flag(&mut args, "--json");
+                let env = self.target_environment(_worktree, &args)?;
+                let packages = list_installed_packages(&env.python_path)?;
+                if as_json {
+                    return Ok(Self::render_packages_json(&env, &packages)?);
+                }
+
+                let max_name_length = packages
+                    .iter()
+                    .map(|package| package.name.len())
+                    .max()
+                    .unwrap_or(0);
+                let mut text = format!("Packages in {} ({}):\n", env.name, packages.len());
+                for package in &packages {
+                    text.push_str(&format!(
+                        "{:<width$}    {}\n",
+                        package.name,
+                        package.version,
+                        width = max_name_length
+                    ));
+                }
+
+                Ok(SlashCommandOutput {
+                    sections: vec![SlashCommandOutputSection {
+                        range: (0..text.len()).into(),
+                        label: format!("Packages: {}", env.name),
+                    }],
+                    text,
+                })
+            }
+            "pyenvoutdated" => {
+                let env = self.target_environment(_worktree, &args)?;
+                let packages = list_outdated_packages(&env.python_path)?;
+
+                let text = if packages.is_empty() {
+                    format!("All packages in {} are up to date.\n", env.name)
+                } else {
+                    let name_width = packages
+                        .iter()
+                        .map(|package| package.name.len())
+                        .chain(["Package".len()])
+                        .max()
+                        .unwrap_or(0);
+                    let version_width = packages
+                        .iter()
+                        .map(|package| package.version.len())
+                        .chain(["Current".len()])
+                        .max()
+                        .unwrap_or(0);
+                    let mut text = format!(
+                        "Outdated packages in {} ({}):\n{:<name_width$}    {:<version_width$}    Latest\n",
+                        env.name,
+                        packages.len(),
+                        "Package",
+                        "Current",
+                    );
+                    for package in &packages {
+                        text.push_str(&format!(
+                            "{:<name_width$}    {:<version_width$}    {}\n",
+                            package.name, package.version, package.latest_version,
+                        ));
+                    }
+                    text
+                };
+
+                Ok(SlashCommandOutput {
+                    sections: vec![SlashCommandOutputSection {
+                        range: (0..text.len()).into(),
+                        label: format!("Outdated: {}", env.name),
+                    }],
+                    text,
+                })
+            }
+            "pyenvinfo" => {
+                let mut args = args;
+                let measure_size = take_flag(&mut args, "--size");
+                let env = self.target_environment(_worktree, &args)?;
+                let size = measure_size
+                    .then(|| self.environment_size(&env.python_path, &env.provider))
+                    .flatten();
+                Ok(Self::render_environment_info(&env, size))
+            }
+            "pyenvcreate" => {
+                let worktree = _worktree
+                    .ok_or_else(|| "creating an environment requires a worktree".to_string())?;
+
+                // /pyenvcreate [base interpreter] [--name <dir>] [--uv]
+                let mut env_dir = self
+                    .settings
+                    .lock()
+                    .ok()
+                    .and_then(|settings| {
+                        settings
+                            .project_env_dirs
+                            .iter()
+                            .find(|dir| !dir.contains('*'))
+                            .cloned()
+                    })
+                    .unwrap_or_else(|| ".venv".to_string());
+                let mut use_uv = false;
+                let mut base = Vec::new();
+                let mut args = args.into_iter();
+                while let Some(arg) = args.next() {
+                    match arg.as_str() {
+                        "--uv" => use_uv = true,
+                        "--name" => {
+                            env_dir = args
+                                .next()
+                                .ok_or_else(|| "--name expects a directory name".to_string())?
+                        }
+                        _ => base.push(arg),
+                    }
+                }
+
+                let base_python = if base.is_empty() {
+                    find_on_path("python3")
+                        .or_else(|| find_on_path("python"))
+                        .ok_or_else(|| {
+                            "no python found on PATH; pass a base interpreter".to_string()
+                        })?
+                } else {
+                    let query = base.join(" ");
+                    let all_envs = self.workspace_discovery(Some(worktree)).environments;
+                    match self.resolve_environment(&all_envs, &query) {
+                        Ok(env) => env.python_path.clone(),
+                        Err(error) if !Path::new(&query).is_file() => return Err(error.into()),
+                        Err(_) => PathBuf::from(query),
+                    }
+                };
+
+                let selected = self.create_environment(worktree, &base_python, &env_dir, use_uv)?;
+                let text = format!(
+                    "Created {} from {}\nSelected {}\nInterpreter: {}\nVersion: {}",
+                    env_dir,
+                    base_python.display(),
+                    selected.name,
+                    selected.python_path.display(),
+                    selected.version.as_deref().unwrap_or("unknown")
+                );
+
+                Ok(SlashCommandOutput {
+                    sections: vec![SlashCommandOutputSection {
+                        range: (0..text.len()).into(),
+                        label: format!("Created Python Environment: {}", env_dir),
+                    }],
+                    text,
+                })
+            }
+            "pyenvdelete" => {
+                let worktree = _worktree
+                    .ok_or_else(|| "deleting an environment requires a worktree".to_string())?;
+                let mut args = args;
+                let confirm = take_flag(&mut args, "--confirm");
+                let query = args.join(" ");
+                if query.is_empty() {
+                    return Err("expected an environment name or index".to_string());
+                }
+
+                let text = self.delete_environment(worktree, &query, confirm)?;
+
+                Ok(SlashCommandOutput {
+                    sections: vec![SlashCommandOutputSection {
+                        range: (0..text.len()).into(),
+                        label: "Delete Python Environment".to_string(),
+                    }],
+                    text,
+                })
+            }
+            "pyenvdoctor" => {
+                let worktree = _worktree
+                    .ok_or_else(|| "checking an environment requires a worktree".to_string())?;
+                let env = self.target_environment(_worktree, &args)?;
+                let checks = self.check_environment_health(worktree, &env);
+                Ok(Self::render_health_report(&env, &checks))
+            }
+            "pyenvkernel" => {
+                let mut args = args;
+                let install_ipykernel = take_flag(&mut args, "--install-ipykernel");
+                let name = take_option(&mut args, "--name")?;
+                let env = self.target_environment(_worktree, &args)?;
+                let text = self.register_kernel(_worktree, &env, name, install_ipykernel)?;
+
+                Ok(SlashCommandOutput {
+                    sections: vec![SlashCommandOutputSection {
+                        range: (0..text.len()).into(),
+                        label: format!("Jupyter Kernel: {}", env.name),
+                    }],
+                    text,
+                })
+            }
+            "pyenvactivate" => {
+                let mut args = args;
+                let persist = take_flag(&mut args, "--persist");
+                let env = self.target_environment(_worktree, &args)?;
+                let env_root = environment_root(&env.python_path)
+                    .unwrap_or(&env.python_path)
+                    .to_path_buf();
+
+                let mut text = format!("Activate {} in a terminal:\n", env.name);
+                for activation in activation_commands(&env.python_path, &env.provider) {
+                    text.push_str(&format!("{}: {}\n", activation.shell, activation.command));
+                }
+                if is_venv(&env_root) {
+                    text.push_str(&format!(
+                        "\nOr set these variables (e.g. under `terminal.env` in Zed's settings):\nVIRTUAL_ENV={}\nPATH={}{}$PATH\n",
+                        env_root.display(),
+                        env.python_path.parent().unwrap_or(&env_root).display(),
+                        if cfg!(windows) { ";" } else { ":" }
+                    ));
+                }
+
+                // Extensions can't set the environment of Zed's terminals, but
+                // Zed activates the venvs listed in `terminal.detect_venv` itself.
+                if persist {
+                    let worktree =
+                        _worktree.ok_or_else(|| "--persist requires a worktree".to_string())?;
+                    let root_path = PathBuf::from(worktree.root_path());
+                    let settings_path = Self::persist_terminal_activation(&root_path, &env_root)?;
+                    text.push_str(&format!(
+                        "\nAdded {} to terminal.detect_venv in {}; new terminals will activate it.\n",
+                        env_root.strip_prefix(&root_path).unwrap_or(&env_root).display(),
+                        settings_path.display()
+                    ));
+                }
+
+                Ok(SlashCommandOutput {
+                    sections: vec![SlashCommandOutputSection {
+                        range: (0..text.len()).into(),
+                        label: format!("Activate: {}", env.name),
+                    }],
+                    text,
+                })
+            }
+            "pyenvtasks" => {
+                let mut args = args;
+                let persist = take_flag(&mut args, "--persist");
+                let env = self.target_any_environment(_worktree, &args)?;
+                let root_path = _worktree.map(|worktree| PathBuf::from(worktree.root_path()));
+                let launcher = match (&env.guest, &root_path) {
+                    (Some(guest), Some(root_path)) => Some(guest.launcher(root_path)?),
+                    (Some(guest), None) => {
+                        return Err(format!("tasks running in {} require a worktree", guest))
+                    }
+                    (None, _) => None,
+                };
+                let tasks =
+                    python_tasks(&env.python_path, root_path.as_deref(), launcher.as_deref());
+
+                let text = if persist {
+                    let root_path =
+                        root_path.ok_or_else(|| "--persist requires a worktree".to_string())?;
+                    let existing = read_zed_file(&root_path, "tasks.json", json!([]))?;
+                    let tasks_path = write_zed_file(
+                        &root_path,
+                        "tasks.json",
+                        &merge_generated(existing, tasks),
+                    )?;
+                    format!(
+                        "Wrote tasks running {} to {}.\nRun them with `task: spawn`; they start with \"{}\".",
+                        env.name,
+                        tasks_path.display(),
+                        TASK_LABEL_PREFIX.trim()
+                    )
+                } else {
+                    let tasks =
+                        serde_json::to_string_pretty(&tasks).map_err(|e| PyEnvError::Io {
+                            context: "Failed to serialize tasks".to_string(),
+                            source: e.into(),
+                        })?;
+                    format!(
+                        "Tasks running {} (add them to .zed/tasks.json, or run /pyenvtasks --persist):\n{}",
+                        env.name, tasks
+                    )
+                };
+
+                Ok(SlashCommandOutput {
+                    sections: vec![SlashCommandOutputSection {
+                        range: (0..text.len()).into(),
+                        label: format!("Tasks: {}", env.name),
+                    }],
+                    text,
+                })
+            }
+            "pyenvdebug" => {
+                let mut args = args;
+                let persist = take_flag(&mut args, "--persist");
+                let install_debugpy = take_flag(&mut args, "--install-debugpy");
+                let env = self.target_environment(_worktree, &args)?;
+                let root_path = _worktree.map(|worktree| PathBuf::from(worktree.root_path()));
+                let configurations = debug_configurations(&env.python_path, root_path.as_deref());
+
+                let mut text = String::new();
+                if !has_package(&env.python_path, "debugpy")? {
+                    if install_debugpy {
+                        Installer::for_environment(&env.python_path, false).install(
+                            &env.python_path,
+                            None,
+                            &["debugpy"],
+                        )?;
+                        text.push_str(&format!("Installed debugpy in {}\n", env.name));
+                    } else {
+                        text.push_str(&format!(
+                            "Warning: debugpy is not installed in {}; run /pyenvdebug {} --install-debugpy to install it.\n",
+                            env.name, env.name
+                        ));
+                    }
+                }
+
+                if persist {
+                    let root_path =
+                        root_path.ok_or_else(|| "--persist requires a worktree".to_string())?;
+                    let existing = read_zed_file(&root_path, "debug.json", json!([]))?;
+                    let debug_path = write_zed_file(
+                        &root_path,
+                        "debug.json",
+                        &merge_generated(existing, configurations),
+                    )?;
+                    text.push_str(&format!(
+                        "Wrote debug configurations using {} to {}.\nStart them with `debugger: start`; they start with \"{}\".",
+                        env.name,
+                        debug_path.display(),
+                        TASK_LABEL_PREFIX.trim()
+                    ));
+                } else {
+                    let configurations =
+                        serde_json::to_string_pretty(&configurations).map_err(|e| {
+                            PyEnvError::Io {
+                                context: "Failed to serialize debug configurations".to_string(),
+                                source: e.into(),
+                            }
+                        })?;
+                    text.push_str(&format!(
+                        "Debug configurations using {} (add them to .zed/debug.json, or run /pyenvdebug --persist):\n{}",
+                        env.name, configurations
+                    ));
+                }
+
+                Ok(SlashCommandOutput {
+                    sections: vec![SlashCommandOutputSection {
+                        range: (0..text.len()).into(),
+                        label: format!("Debug: {}", env.name),
+                    }],
+                    text,
+                })
+            }
+            "pyenvtest" => {
+                let worktree =
+                    _worktree.ok_or_else(|| "running tests requires a worktree".to_string())?;
+                // /pyenvtest [node ids...] [--collect] [--env <name>]
+                let mut args = args;
+                let collect_only = take_flag(&mut args, "--collect");
+                let env_query: Vec<String> = take_option(&mut args, "--env")?.into_iter().collect();
+                let env = self.target_environment(_worktree, &env_query)?;
+
+                let report = run_pytest(
+                    &env.python_path,
+                    Path::new(&worktree.root_path()),
+                    &args,
+                    collect_only,
+                )?;
+                Ok(Self::render_pytest_report(&env, &report))
+            }
+            "pyenvrun" => {
+                let worktree =
+                    _worktree.ok_or_else(|| "running code requires a worktree".to_string())?;
+                // /pyenvrun [--env <name>] -c <code...> | <script> [args...]
+                let mut args = args;
+                let env_query: Vec<String> = take_option(&mut args, "--env")?.into_iter().collect();
+                let env = self.target_environment(_worktree, &env_query)?;
+                let root_path = PathBuf::from(worktree.root_path());
+
+                let mut command = python_command(&env.python_path);
+                command.current_dir(&root_path);
+                let invocation = match args.split_first() {
+                    Some((flag, code)) if flag == "-c" && !code.is_empty() => {
+                        // Slash command arguments arrive split on whitespace.
+                        let code = code.join(" ");
+                        command.arg("-c").arg(&code);
+                        format!("-c {:?}", code)
+                    }
+                    Some((script, script_args)) if script != "-c" => {
+                        command
+                            .arg(worktree_file(&root_path, script)?)
+                            .args(script_args);
+                        args.join(" ")
+                    }
+                    _ => return Err("expected -c <code> or a worktree-relative script".to_string()),
+                };
+
+                let output = output_with_timeout(&mut command, NETWORK_COMMAND_TIMEOUT)?;
+                Ok(Self::render_run_output(&env, &invocation, &output))
+            }
+            "pyenvwhich" => {
+                let worktree =
+                    _worktree.ok_or_else(|| "locating a module requires a worktree".to_string())?;
+                // /pyenvwhich [--env <name>] <module or executable>
+                let mut args = args;
+                let env_query: Vec<String> = take_option(&mut args, "--env")?.into_iter().collect();
+                let [name] = args.as_slice() else {
+                    return Err("expected a module or executable name".to_string());
+                };
+                let env = self.target_environment(_worktree, &env_query)?;
+                let root_path = PathBuf::from(worktree.root_path());
+
+                let location = locate_modules(&env.python_path, &root_path, &[name])?
+                    .pop()
+                    .ok_or_else(|| format!("{} didn't report on {}", env.name, name))?;
+                let script = environment_executable(&env.python_path, name);
+                if location.resolved.is_none() && script.is_none() {
+                    return Err(format!(
+                        "no module or executable named {} in {}",
+                        name, env.name
+                    ));
+                }
+                Ok(Self::render_which(
+                    &env,
+                    &location,
+                    script.as_deref(),
+                    &root_path,
+                ))
+            }
+            "pyenvdiff" => {
+                let [first, second] = args.as_slice() else {
+                    return Err("expected two environment names or indexes".to_string());
+                };
+                let first = self.target_environment(_worktree, std::slice::from_ref(first))?;
+                let second = self.target_environment(_worktree, std::slice::from_ref(second))?;
+                let [first_packages, second_packages] =
+                    [&first, &second].map(|env| list_installed_packages(&env.python_path));
+                let diff = diff_packages(&first_packages?, &second_packages?);
+                Ok(Self::render_environment_diff(&first, &second, &diff))
+            }
+            "pyenvinstall" => {
+                let worktree = _worktree
+                    .ok_or_else(|| "installing packages requires a worktree".to_string())?;
+                // /pyenvinstall [--env <name>] [--uv] <packages...> | -r <file> | --extras <a,b>
+                let mut args = args;
+                let env_query: Vec<String> = take_option(&mut args, "--env")?.into_iter().collect();
+                let prefer_uv = take_flag(&mut args, "--uv");
+                let requirements = take_option(&mut args, "-r")?;
+                let extras = take_option(&mut args, "--extras")?;
+                let env = self.target_environment(_worktree, &env_query)?;
+                let root_path = PathBuf::from(worktree.root_path());
+
+                let mut install_args = args.clone();
+                if let Some(requirements) = &requirements {
+                    worktree_file(&root_path, requirements)?;
+                    install_args.extend(["-r".to_string(), requirements.clone()]);
+                }
+                if let Some(extras) = &extras {
+                    install_args.extend(["-e".to_string(), format!(".[{}]", extras)]);
+                }
+                if install_args.is_empty() {
+                    return Err(
+                        "expected package names, -r <requirements file> or --extras <names>"
+                            .to_string(),
+                    );
+                }
+
+                let installer = Installer::for_environment(&env.python_path, prefer_uv);
+                let install_args: Vec<&str> = install_args.iter().map(String::as_str).collect();
+                let installed =
+                    installer.install(&env.python_path, Some(&root_path), &install_args)?;
+
+                let mut text = format!(
+                    "$ {} install {}\nEnvironment: {} ({})\n",
+                    installer.name(),
+                    install_args.join(" "),
+                    env.name,
+                    env.python_path.display()
+                );
+                if installed.is_empty() {
+                    text.push_str("Nothing to install; all requirements were already satisfied.\n");
+                } else {
+                    text.push_str(&format!("Installed {} packages:\n", installed.len()));
+                    for package in &installed {
+                        text.push_str(&format!("+ {}\n", package));
+                    }
+                }
+
+                Ok(SlashCommandOutput {
+                    sections: vec![SlashCommandOutputSection {
+                        range: (0..text.len()).into(),
+                        label: format!("Install: {}", env.name),
+                    }],
+                    text,
+                })
+            }
+            "pyenvfreeze" => {
+                let worktree = _worktree
+                    .ok_or_else(|| "freezing an environment requires a worktree".to_string())?;
+                // /pyenvfreeze [env] [--output <file>] [--uv]
+                let mut args = args;
+                let output = take_option(&mut args, "--output")?;
+                let prefer_uv = take_flag(&mut args, "--uv");
+                let env = self.target_environment(_worktree, &args)?;
+                let root_path = PathBuf::from(worktree.root_path());
+
+                // Conda environments are exported with their conda packages,
+                // which pip freeze would miss.
+                let conda = ["conda", "mamba", "micromamba"]
+                    .into_iter()
+                    .find_map(find_on_path)
+                    .filter(|_| env.provider == "conda" || env.provider == "mamba");
+                let (contents, default_output) = match (&conda, environment_root(&env.python_path))
+                {
+                    (Some(conda), Some(env_root)) => (
+                        command_stdout(
+                            Command::new(conda)
+                                .args(["env", "export", "--no-builds", "--prefix"])
+                                .arg(env_root),
+                        )?,
+                        "environment.yml",
+                    ),
+                    _ => (
+                        Installer::for_environment(&env.python_path, prefer_uv)
+                            .freeze(&env.python_path)?,
+                        "requirements.txt",
+                    ),
+                };
+                let output = output.unwrap_or(default_output.to_string());
+                let output_path = worktree_output_path(&root_path, &output)?;
+
+                let previous = fs::read_to_string(&output_path).ok();
+                if let Some(parent) = output_path.parent() {
+                    fs::create_dir_all(parent).map_err(PyEnvError::io(format!(
+                        "Failed to create {}",
+                        parent.display()
+                    )))?;
+                }
+                fs::write(&output_path, &contents).map_err(PyEnvError::io(format!(
+                    "Failed to write {}",
+                    output_path.display()
+                )))?;
+
+                let mut text = format!(
+                    "Froze {} into {} ({} lines)\n",
+                    env.name,
+                    output,
+                    contents
+                        .lines()
+                        .filter(|line| !line.trim().is_empty())
+                        .count()
+                );
+                match previous {
+                    None => text.push_str("Created a new file.\n"),
+                    Some(previous) => {
+                        let (added, removed) = diff_requirement_lines(&previous, &contents);
+                        if added.is_empty() && removed.is_empty() {
+                            text.push_str("No changes from the previous file.\n");
+                        }
+                        for line in &added {
+                            text.push_str(&format!("+ {}\n", line));
+                        }
+                        for line in &removed {
+                            text.push_str(&format!("- {}\n", line));
+                        }
+                    }
+                }
+
+                Ok(SlashCommandOutput {
+                    sections: vec![SlashCommandOutputSection {
+                        range: (0..text.len()).into(),
+                        label: format!("Freeze: {}", env.name),
+                    }],
+                    text,
+                })
+            }
+            "pyenvsync" => {
+                let worktree = _worktree
+                    .ok_or_else(|| "syncing an environment requires a worktree".to_string())?;
+                // /pyenvsync [--env <name>]
+                let mut args = args;
+                let env_query: Vec<String> = take_option(&mut args, "--env")?.into_iter().collect();
+                if !args.is_empty() {
+                    return Err("/pyenvsync only accepts --env <name>".to_string());
+                }
+                let root_path = PathBuf::from(worktree.root_path());
+                let manager = SyncManager::detect(&root_path).ok_or_else(|| {
+                    "found no uv.lock, poetry.lock, environment.yml or requirements.txt to sync from"
+                        .to_string()
+                })?;
+                let env = match self.target_environment(_worktree, &env_query) {
+                    Ok(env) => Some(env),
+                    // uv, Poetry and conda create the project environment.
+                    Err(_) if env_query.is_empty() && manager.creates_environment() => None,
+                    Err(error) => return Err(error.into()),
+                };
+
+                // A fresh `uv venv` has neither pip nor any packages to list.
+                let before = env
+                    .as_ref()
+                    .and_then(|env| list_installed_packages(&env.python_path).ok())
+                    .unwrap_or_default();
+                let invocation = manager.sync(
+                    &root_path,
+                    env.as_ref().map(|env| env.python_path.as_path()),
+                )?;
+                self.invalidate_cache(Some(&root_path));
+                let env = match env {
+                    Some(env) => env,
+                    None => self.effective_selection(worktree).ok_or_else(|| {
+                        format!(
+                            "{} finished, but no project environment was found",
+                            invocation
+                        )
+                    })?,
+                };
+                let after = list_installed_packages(&env.python_path)?;
+                Ok(Self::render_sync_report(
+                    &env,
+                    manager.manifest(),
+                    &invocation,
+                    &diff_packages(&before, &after),
+                ))
+            }
+            "pyenvreqs" => {
+                let worktree = _worktree
+                    .ok_or_else(|| "checking requirements requires a worktree".to_string())?;
+                let env = self.target_environment(_worktree, &args)?;
+                Ok(Self::render_requirements_check(worktree, &env)?)
+            }
+            "pyenvlog" => {
+                // /pyenvlog [count] [--level <debug|info|warn|error>] [--tag <tag>]
+                let mut args = args;
+                let level = match take_option(&mut args, "--level")? {
+                    Some(level) => Level::parse(&level).ok_or_else(|| {
+                        format!(
+                            "unknown log level \"{}\"; use debug, info, warn or error",
+                            level
+                        )
+                    })?,
+                    None => Level::Debug,
+                };
+                let tag = take_option(&mut args, "--tag")?;
+                let limit = match args.as_slice() {
+                    [] => DEFAULT_LOG_LINES,
+                    [count] => count
+                        .parse()
+                        .map_err(|_| format!("expected a number of lines, got \"{}\"", count))?,
+                    _ => {
+                        return Err("/pyenvlog accepts a line count, --level and --tag".to_string())
+                    }
+                };
+
+                let entries = logging::recent(limit, level, tag.as_deref());
+                let mut text = format!(
+                    "Last {} log entries (times in UTC, level {} and above{})\n",
+                    entries.len(),
+                    level,
+                    tag.as_deref()
+                        .map(|tag| format!(", tagged {}", tag))
+                        .unwrap_or_default()
+                );
+                if entries.is_empty() {
+                    text.push_str("Nothing logged yet.\n");
+                } else {
+                    text.push_str("```\n");
+                    for entry in &entries {
+                        text.push_str(&format!("{}\n", entry));
+                    }
+                    text.push_str("```\n");
+                }
+
+                Ok(SlashCommandOutput {
+                    sections: vec![SlashCommandOutputSection {
+                        range: (0..text.len()).into(),
+                        label: "pyenvselect log".to_string(),
+                    }],
+                    text,
+                })
+            }
+            "pyenvrecent" => {
+                // /pyenvrecent [#]
+                let worktree =
+                    _worktree.ok_or_else(|| "selection history requires a worktree".to_string())?;
+                let history = self.selection_history(worktree);
+                let all_envs = self.get_all_python_environments(Some(worktree));
+                let index = match args.as_slice() {
+                    [] => return Ok(Self::render_selection_history(&history, &all_envs)),
+                    [index] => index.parse::<usize>().map_err(|_| {
+                        format!("expected a position from /pyenvrecent, got \"{}\"", index)
+                    })?,
+                    _ => return Err("/pyenvrecent accepts a single position".to_string()),
+                };
+                let previous = index
+                    .checked_sub(1)
+                    .and_then(|index| history.get(index))
+                    .ok_or_else(|| {
+                        format!(
+                            "position {} is out of range ({} recent selections)",
+                            index,
+                            history.len()
+                        )
+                    })?;
+                let env = all_envs
+                    .iter()
+                    .find(|env| env.python_path == previous.python_path)
+                    .ok_or_else(|| {
+                        format!(
+                            "{} ({}) no longer exists; run /pyenvlst to pick another",
+                            previous.name,
+                            previous.python_path.display()
+                        )
+                    })?;
+
+                let selected = self.select_environment(Some(worktree), &env.id, None, None)?;
+                let mut text = format!(
+                    "Selected {} again\nInterpreter: {}\nVersion: {}",
+                    selected.name,
+                    selected.python_path.display(),
+                    selected.version.as_deref().unwrap_or("unknown")
+                );
+                let settings = self
+                    .settings
+                    .lock()
+                    .map(|settings| settings.clone())
+                    .unwrap_or_default();
+                if settings.update_project_settings {
+                    let settings_path = Self::persist_to_project_settings(
+                        Path::new(&worktree.root_path()),
+                        &selected,
+                        &settings.language_server,
+                    )?;
+                    text.push_str(&format!("\nSaved to {}", settings_path.display()));
+                    text.push_str("\nThe Python language server will pick up the new interpreter.");
+                } else {
+                    text.push_str(
+                        "\nRun `editor: restart language server` to apply it to the Python \
+                         language server.",
+                    );
+                }
+
+                Ok(SlashCommandOutput {
+                    sections: vec![SlashCommandOutputSection {
+                        range: (0..text.len()).into(),
+                        label: format!("Python Environment: {}", selected.name),
+                    }],
+                    text,
+                })
+            }
+            "pyenvrefresh" => {
+                let root_path = _worktree.map(|worktree| PathBuf::from(worktree.root_path()));
+                self.invalidate_cache(root_path.as_deref());
+                let discovery = self.discover_environments(root_path.as_deref());
+
+                let mut text = format!(
+                    "Refreshed environment cache: found {} environments.",
+                    discovery.environments.len()
+                );
+                if !discovery.warnings.is_empty() {
+                    text.push_str(&format!(
+                        " {} warnings; run /pyenvlst to see them.",
+                        discovery.warnings.len()
+                    ));
+                }
+
+                Ok(SlashCommandOutput {
+                    sections: vec![SlashCommandOutputSection {
+                        range: (0..text.len()).into(),
+                        label: "Python Environments Refreshed".to_string(),
+                    }],
+                    text,
+                })
+            }
+            "pyenvselect" => {
+                let settings = self
+                    .settings
+                    .lock()
+                    .map(|settings| settings.clone())
+                    .unwrap_or_default();
+                let mut args = args;
+                let persist_flag = take_flag(&mut args, "--persist");
+                let pin = take_flag(&mut args, "--pin");
+                let in_worktree = take_option(&mut args, "--worktree")?;
+                let scope = match (take_option(&mut args, "--path")?, _worktree) {
+                    (Some(path), Some(worktree)) => {
+                        Some(project_scope(Path::new(&worktree.root_path()), &path)?)
+                    }
+                    (Some(_), None) => return Err("--path requires a worktree".to_string()),
+                    (None, _) => None,
+                };
+                let query = args.join(" ");
+                if query.is_empty() {
+                    return Err("expected an environment name or index".to_string());
+                }
+
+                // Project settings hold a single interpreter per worktree, so
+                // only worktree-wide selections are written there.
+                let root_scope = scope
+                    .as_deref()
+                    .zip(_worktree)
+                    .is_none_or(|(scope, worktree)| scope == Path::new(&worktree.root_path()));
+                let persist = root_scope && (settings.update_project_settings || persist_flag);
+                if pin && !root_scope {
+                    return Err(
+                        "--pin applies to the whole worktree and can't be combined with --path"
+                            .to_string(),
+                    );
+                }
+
+                let selected = self.select_environment(
+                    _worktree,
+                    &query,
+                    scope.as_deref(),
+                    in_worktree.as_deref(),
+                )?;
+                let mut text = String::new();
+                if let (Some(scope), Some(worktree), false) = (&scope, _worktree, root_scope) {
+                    let root_path = PathBuf::from(worktree.root_path());
+                    text.push_str(&format!(
+                        "Project directory: {}\n",
+                        scope.strip_prefix(&root_path).unwrap_or(scope).display()
+                    ));
+                }
+                text.push_str(&format!(
+                    "Selected {}\nInterpreter: {}\nVersion: {}",
+                    selected.name,
+                    selected.python_path.display(),
+                    selected.version.as_deref().unwrap_or("unknown")
+                ));
+                if let Some(guest) = &selected.guest {
+                    text.push_str(&format!("\nRuns in {}", guest));
+                } else if let Some(ruff_path) =
+                    environment_executable(&selected.python_path, "ruff")
+                {
+                    text.push_str(&format!("\nRuff: {}", ruff_path.display()));
+                }
+                if let Some((requires, pyproject)) =
+                    _worktree.and_then(|worktree| Self::requires_python(worktree, scope.as_deref()))
+                {
+                    if let Some(version) = selected
+                        .version
+                        .as_deref()
+                        .filter(|version| !version_satisfies(version, &requires))
+                    {
+                        text.push_str(&format!(
+                            "\nWarning: Python {} doesn't satisfy requires-python {} from {}",
+                            version,
+                            requires,
+                            pyproject.display()
+                        ));
+                    }
+                }
+                if let Some(warning) =
+                    _worktree.and_then(|worktree| Self::version_pin_mismatch(worktree, &selected))
+                {
+                    text.push_str(&format!("\nWarning: {}", warning));
+                }
+                if let Some(warning) =
+                    _worktree.and_then(|worktree| self.architecture_mismatch(worktree, &selected))
+                {
+                    text.push_str(&format!("\nWarning: {}", warning));
+                }
+
+                // Language servers run on the host, where a guest's
+                // interpreter path means nothing.
+                let persist = persist && selected.guest.is_none();
+                if persist {
+                    if let Some(worktree) = _worktree {
+                        let root_path = PathBuf::from(worktree.root_path());
+                        let settings_path = Self::persist_to_project_settings(
+                            &root_path,
+                            &selected,
+                            &settings.language_server,
+                        )?;
+                        text.push_str(&format!("\nSaved to {}", settings_path.display()));
+                    }
+                }
+                if pin {
+                    let worktree =
+                        _worktree.ok_or_else(|| "--pin requires a worktree".to_string())?;
+                    let pin_path = write_pin_file(Path::new(&worktree.root_path()), &selected)?;
+                    text.push_str(&format!(
+                        "\nPinned in {}; commit it so teammates get the same environment.",
+                        pin_path.display()
+                    ));
+                }
+
+                // The extension API cannot notify running language servers, but
+                // a settings change makes Zed re-request their configuration.
+                if let Some(guest) = &selected.guest {
+                    text.push_str(&format!(
+                        "\nLanguage servers run on the host and keep their interpreter; \
+                         /pyenvtasks generates tasks that run Python in {}.",
+                        guest
+                    ));
+                } else if !root_scope {
+                    text.push_str(
+                        "\nThe language server keeps the worktree-wide interpreter; pass \
+                         --path to /pyenvcur, /pyenvpkgs and friends to use this one.",
+                    );
+                } else if persist {
+                    text.push_str("\nThe Python language server will pick up the new interpreter.");
+                } else {
+                    text.push_str(
+                        "\nRun `editor: restart language server` to apply it to the Python \
+                         language server, or pass --persist to apply it automatically.",
+                    );
+                }
+
+                Ok(SlashCommandOutput {
+                    sections: vec![SlashCommandOutputSection {
+                        range: (0..text.len()).into(),
+                        label: format!("Python Environment: {}", selected.name),
+                    }],
+                    text,
+                })
+            }
+            command => Err(format!("unknown slash command: \"{command}\"")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+
+    #[test]
+    fn resolves_only_files_inside_the_worktree() {
+        let fixture = Fixture::new();
+        let script = fixture.touch("project/scripts/probe.py");
+        fixture.touch("secret.py");
+        let root_path = fixture.path().join("project");
+
+        assert_eq!(
+            worktree_file(&root_path, "scripts/probe.py").unwrap(),
+            script
+        );
+        assert!(worktree_file(&root_path, "../secret.py").is_err());
+        assert!(worktree_file(&root_path, "scripts").is_err());
+        assert!(worktree_file(&root_path, "missing.py").is_err());
+    }
+
+    #[test]
+    fn keeps_output_paths_inside_the_worktree() {
+        let root_path = Path::new("/work/app");
+        assert_eq!(
+            worktree_output_path(root_path, "requirements/lock.txt").unwrap(),
+            root_path.join("requirements/lock.txt")
+        );
+        assert!(worktree_output_path(root_path, "../requirements.txt").is_err());
+        assert!(worktree_output_path(root_path, "/etc/requirements.txt").is_err());
+    }
+
+    #[test]
+    fn creates_environments_only_inside_the_worktree() {
+        let fixture = Fixture::new();
+        fixture.venv("app/.venv");
+        let root_path = fixture.path().join("app");
+
+        assert_eq!(
+            new_environment_path(&root_path, "envs/py312").unwrap(),
+            root_path.join("envs/py312")
+        );
+        assert!(new_environment_path(&root_path, ".venv").is_err());
+        assert!(matches!(
+            new_environment_path(&root_path, "../elsewhere"),
+            Err(PyEnvError::InvalidSelection(_))
+        ));
+        let absolute = fixture.path().join("elsewhere");
+        assert!(matches!(
+            new_environment_path(&root_path, &absolute.to_string_lossy()),
+            Err(PyEnvError::InvalidSelection(_))
+        ));
+    }
+}
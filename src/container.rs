@@ -0,0 +1,134 @@
+use crate::environment::find_on_path;
+use crate::process::command_stdout;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+/// Label marking a docker-compose service as the project's Python runtime
+/// when there is no `devcontainer.json` naming it.
+pub(crate) const PYTHON_RUNTIME_LABEL: &str = "pyenvselect.python";
+
+/// A running container, as listed by
+/// `docker ps --format '{{.Names}}\t{{.Labels}}'`.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct RunningContainer {
+    pub(crate) name: String,
+    pub(crate) labels: HashMap<String, String>,
+}
+
+impl RunningContainer {
+    /// Whether the container runs the Python of the worktree at `root_path`:
+    /// a dev container opened from it, or a compose service of its project
+    /// that `devcontainer.json` names as its `service` or that carries the
+    /// [`PYTHON_RUNTIME_LABEL`].
+    pub(crate) fn runs_python_for(&self, root_path: &Path, service: Option<&str>) -> bool {
+        let label_is_root = |label: &str| {
+            self.labels
+                .get(label)
+                .is_some_and(|folder| Path::new(folder) == root_path)
+        };
+        if label_is_root("devcontainer.local_folder") {
+            return true;
+        }
+        label_is_root("com.docker.compose.project.working_dir")
+            && (self.labels.contains_key(PYTHON_RUNTIME_LABEL)
+                || service.is_some_and(|service| {
+                    self.labels
+                        .get("com.docker.compose.service")
+                        .map(String::as_str)
+                        == Some(service)
+                }))
+    }
+}
+
+/// Parses the `docker ps` listing described on [`RunningContainer`].
+pub(crate) fn parse_docker_ps(output: &str) -> Vec<RunningContainer> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (name, labels) = line.split_once('\t').unwrap_or((line, ""));
+            let name = name.trim();
+            (!name.is_empty()).then(|| RunningContainer {
+                name: name.to_string(),
+                labels: labels
+                    .split(',')
+                    .filter_map(|label| label.split_once('='))
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+/// The compose `service` a `devcontainer.json` runs in. The file is JSON
+/// with comments, so the key is looked up line by line rather than parsed.
+pub(crate) fn read_devcontainer_service(contents: &str) -> Option<String> {
+    contents.lines().find_map(|line| {
+        let value = line.trim().strip_prefix("\"service\"")?;
+        let value = value.trim_start().strip_prefix(':')?.trim();
+        let value = value.strip_prefix('"')?;
+        Some(value.split('"').next()?.to_string()).filter(|service| !service.is_empty())
+    })
+}
+
+/// Where the container mounts the host directory `root_path`, from
+/// `docker inspect`, so tasks can run inside it on the worktree's files.
+pub(crate) fn mount_point(container: &str, root_path: &Path) -> Option<String> {
+    let docker = find_on_path("docker")?;
+    let output = command_stdout(Command::new(docker).args([
+        "inspect",
+        "--format",
+        "{{range .Mounts}}{{.Source}}\t{{.Destination}}\n{{end}}",
+        container,
+    ]))
+    .ok()?;
+    output.lines().find_map(|line| {
+        let (source, destination) = line.split_once('\t')?;
+        (Path::new(source) == root_path).then(|| destination.trim().to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_worktrees_containers() {
+        let output = "\
+app-devcontainer\tdevcontainer.local_folder=/work/app,devcontainer.config_file=/work/app/.devcontainer/devcontainer.json
+app-web-1\tcom.docker.compose.project.working_dir=/work/app,com.docker.compose.service=web
+app-db-1\tcom.docker.compose.project.working_dir=/work/app,com.docker.compose.service=db
+worker-1\tcom.docker.compose.project.working_dir=/work/app,com.docker.compose.service=worker,pyenvselect.python=true
+other-web-1\tcom.docker.compose.project.working_dir=/work/other,com.docker.compose.service=web
+";
+        let root = Path::new("/work/app");
+        let python_runtimes: Vec<String> = parse_docker_ps(output)
+            .into_iter()
+            .filter(|container| container.runs_python_for(root, Some("web")))
+            .map(|container| container.name)
+            .collect();
+        assert_eq!(
+            python_runtimes,
+            ["app-devcontainer", "app-web-1", "worker-1"]
+        );
+    }
+
+    #[test]
+    fn reads_the_devcontainer_service() {
+        let devcontainer = r#"{
+    // Runs in the compose project's web service.
+    "name": "App",
+    "dockerComposeFile": ["../docker-compose.yml"],
+    "service": "web",
+    "workspaceFolder": "/workspaces/app"
+}"#;
+        assert_eq!(
+            read_devcontainer_service(devcontainer).as_deref(),
+            Some("web")
+        );
+        assert_eq!(
+            read_devcontainer_service(r#"{ "image": "python:3.12" }"#),
+            None
+        );
+    }
+}
@@ -0,0 +1,674 @@
+use crate::environment::{environment_executable, list_installed_packages, probe_interpreter};
+use crate::project::{
+    check_requirements, normalize_package_name, read_project_requirements, read_requires_python,
+    read_toml_string, version_satisfies, VersionPin,
+};
+use crate::providers::EnvironmentProvider;
+use crate::selection::SelectedEnvironment;
+use crate::which::{locate_modules, ModuleLocation};
+use crate::{
+    ExtensionSettings, PythonEnvironmentSelectExtension, NPM_LANGUAGE_SERVERS,
+    PYLSP_LANGUAGE_SERVER,
+};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use zed_extension_api::Worktree;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    /// The check didn't apply, e.g. the project declares no requirements.
+    Skip,
+}
+
+/// One line of the `/pyenvdoctor` report.
+#[derive(Debug)]
+pub(crate) struct HealthCheck {
+    pub(crate) name: &'static str,
+    pub(crate) status: CheckStatus,
+    pub(crate) detail: String,
+    /// What to do about a warning or failure.
+    pub(crate) fix: Option<String>,
+}
+
+impl HealthCheck {
+    fn new(name: &'static str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn with_fix(mut self, fix: impl Into<String>) -> Self {
+        self.fix = Some(fix.into());
+        self
+    }
+}
+
+/// Checks `version` against the project's version pin (`.python-version`
+/// or `.tool-versions`) and the `requires-python` of its `pyproject.toml`.
+fn check_version_pins(
+    env_name: &str,
+    version: &str,
+    pin: Option<&VersionPin>,
+    pyproject: Option<&str>,
+) -> Vec<HealthCheck> {
+    let mut checks = Vec::new();
+
+    match pin {
+        None => checks.push(HealthCheck::new(
+            ".python-version",
+            CheckStatus::Skip,
+            "no .python-version or .tool-versions file",
+        )),
+        Some(pin) if pin.allows(env_name, version) => checks.push(HealthCheck::new(
+            pin.source,
+            CheckStatus::Pass,
+            format!("Python {} matches {}", version, pin.versions.join(", ")),
+        )),
+        Some(pin) => checks.push(
+            HealthCheck::new(
+                pin.source,
+                CheckStatus::Warn,
+                format!(
+                    "Python {} doesn't match {}",
+                    version,
+                    pin.versions.join(", ")
+                ),
+            )
+            .with_fix(format!(
+                "Select an environment running Python {}, or update {}",
+                pin.versions[0], pin.source
+            )),
+        ),
+    }
+
+    match pyproject.and_then(read_requires_python) {
+        None => checks.push(HealthCheck::new(
+            "requires-python",
+            CheckStatus::Skip,
+            "pyproject.toml declares no requires-python",
+        )),
+        Some(requires) if version_satisfies(version, &requires) => checks.push(HealthCheck::new(
+            "requires-python",
+            CheckStatus::Pass,
+            format!("Python {} satisfies {}", version, requires),
+        )),
+        Some(requires) => checks.push(
+            HealthCheck::new(
+                "requires-python",
+                CheckStatus::Fail,
+                format!("Python {} doesn't satisfy {}", version, requires),
+            )
+            .with_fix(format!(
+                "Select or create (/pyenvcreate) an environment with Python {}",
+                requires
+            )),
+        ),
+    }
+
+    checks
+}
+
+/// Top-level `.py` files and directories of the worktree at `root_path`
+/// whose names are importable, so could shadow an installed module when
+/// code runs from the root. Hidden directories are left out.
+fn shadow_candidates(root_path: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(root_path) else {
+        return Vec::new();
+    };
+    let is_identifier = |name: &str| {
+        name.chars()
+            .next()
+            .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let name = if path.is_dir() {
+                entry.file_name().into_string().ok()?
+            } else {
+                path.file_stem()?.to_str()?.to_string()
+            };
+            let importable = path.is_dir() || path.extension().is_some_and(|ext| ext == "py");
+            (importable && is_identifier(&name) && name != "__pycache__").then_some(name)
+        })
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Describes a worktree module shadowing an installed one, e.g.
+/// `requests/ shadows requests 2.32.3`.
+fn describe_shadowing(location: &ModuleLocation, root_path: &Path) -> String {
+    let resolved = location.resolved.as_deref().unwrap_or_default();
+    let relative = Path::new(resolved)
+        .strip_prefix(root_path)
+        .unwrap_or(Path::new(resolved));
+    let mut components = relative.components();
+    let entry = match components.next() {
+        Some(Component::Normal(entry)) if components.next().is_some() => {
+            format!("{}/", entry.to_string_lossy())
+        }
+        _ => relative.display().to_string(),
+    };
+    if location.standard_library {
+        return format!("{} shadows the standard library's {}", entry, location.name);
+    }
+    match (&location.distribution, &location.version) {
+        (Some(distribution), Some(version)) => {
+            format!("{} shadows {} {}", entry, distribution, version)
+        }
+        _ => format!(
+            "{} shadows the installed {} ({})",
+            entry,
+            location.name,
+            location.installed.as_deref().unwrap_or_default()
+        ),
+    }
+}
+
+/// Echoes which providers `settings` leaves running, flagging names in
+/// `enabled_providers` or `disabled_providers` that match no provider.
+fn check_providers(
+    providers: &[Box<dyn EnvironmentProvider>],
+    settings: &ExtensionSettings,
+) -> HealthCheck {
+    const NAME: &str = "Providers";
+    let names = |filter: &dyn Fn(&dyn EnvironmentProvider) -> bool| -> Vec<&str> {
+        providers
+            .iter()
+            .map(Box::as_ref)
+            .filter(|provider| filter(*provider))
+            .map(|provider| provider.name())
+            .collect()
+    };
+    let running = names(&|provider| settings.provider_enabled(provider));
+    let disabled = names(&|provider| {
+        settings
+            .disabled_providers
+            .iter()
+            .any(|name| name == provider.name())
+    });
+    let not_enabled = names(&|provider| {
+        provider.opt_in()
+            && !settings.provider_enabled(provider)
+            && !disabled.contains(&provider.name())
+    });
+
+    let mut detail = format!("running {}", running.join(", "));
+    if !disabled.is_empty() {
+        detail.push_str(&format!("; disabled {}", disabled.join(", ")));
+    }
+    if !not_enabled.is_empty() {
+        detail.push_str(&format!("; opt-in, not enabled {}", not_enabled.join(", ")));
+    }
+
+    let unknown: Vec<&str> = settings
+        .enabled_providers
+        .iter()
+        .chain(&settings.disabled_providers)
+        .map(String::as_str)
+        .filter(|name| !providers.iter().any(|provider| provider.name() == *name))
+        .collect();
+    if unknown.is_empty() {
+        return HealthCheck::new(NAME, CheckStatus::Pass, detail);
+    }
+    HealthCheck::new(
+        NAME,
+        CheckStatus::Warn,
+        format!("{}; unknown provider names {}", detail, unknown.join(", ")),
+    )
+    .with_fix(format!(
+        "Use provider names from: {}",
+        names(&|_| true).join(", ")
+    ))
+}
+
+impl PythonEnvironmentSelectExtension {
+    /// Runs the `/pyenvdoctor` checks against `env`, in report order.
+    pub(crate) fn check_environment_health(
+        &self,
+        worktree: &Worktree,
+        env: &SelectedEnvironment,
+    ) -> Vec<HealthCheck> {
+        let python = env.python_path.display();
+        let mut checks = Vec::new();
+
+        let info = match probe_interpreter(&env.python_path) {
+            Ok(info) => {
+                checks.push(HealthCheck::new(
+                    "Interpreter",
+                    CheckStatus::Pass,
+                    format!("{} {} runs", info.implementation, info.version),
+                ));
+                Some(info)
+            }
+            Err(error) => {
+                checks.push(
+                    HealthCheck::new("Interpreter", CheckStatus::Fail, error.to_string()).with_fix(
+                        format!(
+                            "Recreate the environment at {}, or select another with /pyenvselect",
+                            python
+                        ),
+                    ),
+                );
+                None
+            }
+        };
+
+        match &info {
+            Some(info) => match &info.pip_version {
+                Some(pip_version) => checks.push(HealthCheck::new(
+                    "pip",
+                    CheckStatus::Pass,
+                    format!("pip {}", pip_version),
+                )),
+                None => checks.push(
+                    HealthCheck::new("pip", CheckStatus::Fail, "pip is not importable")
+                        .with_fix(format!("Run `{} -m ensurepip --upgrade`", python)),
+                ),
+            },
+            None => checks.push(HealthCheck::new(
+                "pip",
+                CheckStatus::Skip,
+                "the interpreter doesn't run",
+            )),
+        }
+
+        checks.push(Self::check_requirements_installed(
+            worktree,
+            env,
+            info.is_some(),
+        ));
+        checks.push(self.check_language_server(worktree, env));
+        checks.push(Self::check_shadowed_imports(worktree, env, info.is_some()));
+        let settings = self
+            .settings
+            .lock()
+            .map(|settings| settings.clone())
+            .unwrap_or_default();
+        checks.push(check_providers(&self.providers, &settings));
+
+        match &info {
+            Some(info) => checks.extend(check_version_pins(
+                &env.name,
+                &info.version,
+                VersionPin::read(|file| worktree.read_text_file(file).ok()).as_ref(),
+                worktree.read_text_file("pyproject.toml").ok().as_deref(),
+            )),
+            None => checks.push(HealthCheck::new(
+                "Python version",
+                CheckStatus::Skip,
+                "the interpreter doesn't run",
+            )),
+        }
+
+        checks
+    }
+
+    fn check_requirements_installed(
+        worktree: &Worktree,
+        env: &SelectedEnvironment,
+        interpreter_runs: bool,
+    ) -> HealthCheck {
+        const NAME: &str = "Requirements";
+        let requirements = read_project_requirements(|path| worktree.read_text_file(path).ok());
+        if requirements.is_empty() {
+            return HealthCheck::new(
+                NAME,
+                CheckStatus::Skip,
+                "no requirements.txt or [project] dependencies",
+            );
+        }
+        if !interpreter_runs {
+            return HealthCheck::new(NAME, CheckStatus::Skip, "the interpreter doesn't run");
+        }
+
+        let installed = match list_installed_packages(&env.python_path) {
+            Ok(installed) => installed,
+            Err(error) => return HealthCheck::new(NAME, CheckStatus::Fail, error.to_string()),
+        };
+        let check = check_requirements(
+            &requirements,
+            installed
+                .into_iter()
+                .map(|package| (package.name, package.version)),
+        );
+        if check.missing.is_empty() && check.mismatched.is_empty() {
+            return HealthCheck::new(
+                NAME,
+                CheckStatus::Pass,
+                format!("all {} requirements satisfied", requirements.len()),
+            );
+        }
+
+        let mut problems = Vec::new();
+        if !check.missing.is_empty() {
+            let names: Vec<&str> = check
+                .missing
+                .iter()
+                .map(|(_, requirement)| requirement.name.as_str())
+                .collect();
+            problems.push(format!("missing {}", names.join(", ")));
+        }
+        if !check.mismatched.is_empty() {
+            let names: Vec<&str> = check
+                .mismatched
+                .iter()
+                .map(|((_, requirement), _)| requirement.name.as_str())
+                .collect();
+            problems.push(format!("wrong version of {}", names.join(", ")));
+        }
+        let from_requirements_txt = check
+            .missing
+            .iter()
+            .map(|(source, _)| source)
+            .chain(check.mismatched.iter().map(|((source, _), _)| source))
+            .any(|source| source == "requirements.txt");
+        let install = if from_requirements_txt {
+            "-r requirements.txt"
+        } else {
+            "-e ."
+        };
+        HealthCheck::new(NAME, CheckStatus::Fail, problems.join("; ")).with_fix(format!(
+            "Run `{} -m pip install {}`; /pyenvreqs lists the details",
+            env.python_path.display(),
+            install
+        ))
+    }
+
+    /// Whether files or directories at the worktree root are imported in
+    /// place of installed packages of the same name, e.g. a local
+    /// `typing.py` or a `requests/` folder. The project's own package,
+    /// installed into `env` from the worktree, doesn't count.
+    fn check_shadowed_imports(
+        worktree: &Worktree,
+        env: &SelectedEnvironment,
+        interpreter_runs: bool,
+    ) -> HealthCheck {
+        const NAME: &str = "Shadowed imports";
+        if !interpreter_runs {
+            return HealthCheck::new(NAME, CheckStatus::Skip, "the interpreter doesn't run");
+        }
+        let root_path = PathBuf::from(worktree.root_path());
+        let candidates = shadow_candidates(&root_path);
+        if candidates.is_empty() {
+            return HealthCheck::new(
+                NAME,
+                CheckStatus::Skip,
+                "no Python modules at the worktree root",
+            );
+        }
+
+        let names: Vec<&str> = candidates.iter().map(String::as_str).collect();
+        let locations = match locate_modules(&env.python_path, &root_path, &names) {
+            Ok(locations) => locations,
+            Err(error) => return HealthCheck::new(NAME, CheckStatus::Fail, error.to_string()),
+        };
+        let project = worktree
+            .read_text_file("pyproject.toml")
+            .ok()
+            .and_then(|pyproject| read_toml_string(&pyproject, "project", "name"))
+            .map(|name| normalize_package_name(&name));
+        let shadowing: Vec<String> = locations
+            .iter()
+            .filter(|location| location.shadows_installed(&root_path))
+            .filter(|location| {
+                project.is_none()
+                    || location.distribution.as_deref().map(normalize_package_name) != project
+            })
+            .map(|location| describe_shadowing(location, &root_path))
+            .collect();
+        if shadowing.is_empty() {
+            return HealthCheck::new(
+                NAME,
+                CheckStatus::Pass,
+                "no worktree module shadows an installed one",
+            );
+        }
+        HealthCheck::new(NAME, CheckStatus::Warn, shadowing.join("; "))
+            .with_fix("Rename the local modules; /pyenvwhich <name> shows what an import loads")
+    }
+
+    /// Whether the configured language server will be given `env`.
+    fn check_language_server(&self, worktree: &Worktree, env: &SelectedEnvironment) -> HealthCheck {
+        const NAME: &str = "Language server";
+        let server = self
+            .settings
+            .lock()
+            .map(|settings| settings.language_server.clone())
+            .unwrap_or_default();
+
+        let known = server == PYLSP_LANGUAGE_SERVER
+            || NPM_LANGUAGE_SERVERS.iter().any(|npm| npm.id == server);
+        if !known {
+            return HealthCheck::new(
+                NAME,
+                CheckStatus::Fail,
+                format!(
+                    "the `language_server` setting names an unknown server: {}",
+                    server
+                ),
+            )
+            .with_fix("Set `language_server` to \"pyright\", \"basedpyright\" or \"pylsp\"");
+        }
+
+        if server == PYLSP_LANGUAGE_SERVER
+            && environment_executable(&env.python_path, "pylsp").is_none()
+            && worktree.which("pylsp").is_none()
+        {
+            return HealthCheck::new(NAME, CheckStatus::Fail, "pylsp is not installed").with_fix(
+                format!(
+                    "Run `{} -m pip install python-lsp-server`",
+                    env.python_path.display()
+                ),
+            );
+        }
+
+        match self.effective_selection(worktree) {
+            Some(selected) if selected.python_path == env.python_path => HealthCheck::new(
+                NAME,
+                CheckStatus::Pass,
+                format!("{} is configured with this interpreter", server),
+            ),
+            Some(selected) => HealthCheck::new(
+                NAME,
+                CheckStatus::Warn,
+                format!(
+                    "{} is configured with {} instead",
+                    server,
+                    selected.python_path.display()
+                ),
+            )
+            .with_fix(format!("Run /pyenvselect {}", env.name)),
+            None => HealthCheck::new(
+                NAME,
+                CheckStatus::Warn,
+                format!("{} has no interpreter configured", server),
+            )
+            .with_fix(format!("Run /pyenvselect {}", env.name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+
+    /// The version pin of a worktree holding only `file`.
+    fn pin(file: &'static str, contents: &'static str) -> Option<VersionPin> {
+        VersionPin::read(|path| (path == file).then(|| contents.to_string()))
+    }
+
+    fn statuses(checks: &[HealthCheck]) -> Vec<(&str, CheckStatus)> {
+        checks
+            .iter()
+            .map(|check| (check.name, check.status))
+            .collect()
+    }
+
+    #[test]
+    fn echoes_provider_settings() {
+        let providers = crate::providers::providers();
+        let check = check_providers(&providers, &ExtensionSettings::default());
+        assert_eq!(check.status, CheckStatus::Pass);
+        assert!(check
+            .detail
+            .contains("opt-in, not enabled docker, pipx, wsl"));
+
+        let settings = ExtensionSettings {
+            disabled_providers: vec!["conda".to_string(), "anaconda".to_string()],
+            ..Default::default()
+        };
+        let check = check_providers(&providers, &settings);
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert!(check.detail.contains("; disabled conda;"));
+        assert!(check.detail.ends_with("unknown provider names anaconda"));
+        assert!(check.detail.starts_with("running poetry, pipenv,"));
+        assert!(!check.detail.contains(", conda,"));
+    }
+
+    #[test]
+    fn lists_importable_root_entries() {
+        let fixture = Fixture::new();
+        fixture.touch("typing.py");
+        fixture.touch("requests/__init__.py");
+        fixture.touch("my-scripts/run.py");
+        fixture.touch(".cache/data.py");
+        fixture.touch("__pycache__/typing.cpython-312.pyc");
+        fixture.touch("README.md");
+        assert_eq!(shadow_candidates(fixture.path()), ["requests", "typing"]);
+
+        let location = |name: &str, resolved: &str, installed: &str| ModuleLocation {
+            name: name.to_string(),
+            resolved: Some(format!("/work/app/{}", resolved)),
+            installed: Some(installed.to_string()),
+            distribution: None,
+            version: None,
+            standard_library: false,
+        };
+        let root = Path::new("/work/app");
+        let typing = ModuleLocation {
+            standard_library: true,
+            ..location("typing", "typing.py", "/usr/lib/python3.12/typing.py")
+        };
+        assert_eq!(
+            describe_shadowing(&typing, root),
+            "typing.py shadows the standard library's typing"
+        );
+        let requests = ModuleLocation {
+            distribution: Some("requests".to_string()),
+            version: Some("2.32.3".to_string()),
+            ..location(
+                "requests",
+                "requests/__init__.py",
+                "/site/requests/__init__.py",
+            )
+        };
+        assert_eq!(
+            describe_shadowing(&requests, root),
+            "requests/ shadows requests 2.32.3"
+        );
+        // No distribution found, e.g. a module on PYTHONPATH.
+        let yaml = location("yaml", "yaml/__init__.py", "/opt/lib/yaml/__init__.py");
+        assert_eq!(
+            describe_shadowing(&yaml, root),
+            "yaml/ shadows the installed yaml (/opt/lib/yaml/__init__.py)"
+        );
+    }
+
+    #[test]
+    fn passes_matching_pins() {
+        let checks = check_version_pins(
+            ".venv",
+            "3.12.1",
+            pin(".python-version", "3.12\n").as_ref(),
+            Some("[project]\nname = \"app\"\nrequires-python = \">=3.11\"\n"),
+        );
+        assert_eq!(
+            statuses(&checks),
+            [
+                (".python-version", CheckStatus::Pass),
+                ("requires-python", CheckStatus::Pass),
+            ]
+        );
+    }
+
+    #[test]
+    fn flags_mismatched_pins() {
+        let checks = check_version_pins(
+            "legacy",
+            "3.8.18",
+            pin(".python-version", "3.12.1\n").as_ref(),
+            Some("[project]\nrequires-python = \">=3.11\"\n"),
+        );
+        assert_eq!(
+            statuses(&checks),
+            [
+                (".python-version", CheckStatus::Warn),
+                ("requires-python", CheckStatus::Fail),
+            ]
+        );
+        assert!(checks.iter().all(|check| check.fix.is_some()));
+        assert_eq!(checks[1].detail, "Python 3.8.18 doesn't satisfy >=3.11");
+    }
+
+    #[test]
+    fn accepts_pins_naming_the_environment() {
+        let checks = check_version_pins(
+            "ml-env",
+            "3.10.4",
+            pin(".python-version", "ml-env\n").as_ref(),
+            None,
+        );
+        assert_eq!(
+            statuses(&checks),
+            [
+                (".python-version", CheckStatus::Pass),
+                ("requires-python", CheckStatus::Skip),
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_tool_versions_pins() {
+        let checks = check_version_pins(
+            "3.11.9",
+            "3.11.9",
+            pin(".tool-versions", "nodejs 20.11.0\npython 3.12.1\n").as_ref(),
+            None,
+        );
+        assert_eq!(
+            statuses(&checks),
+            [
+                (".tool-versions", CheckStatus::Warn),
+                ("requires-python", CheckStatus::Skip),
+            ]
+        );
+        assert_eq!(
+            checks[0].fix.as_deref(),
+            Some("Select an environment running Python 3.12.1, or update .tool-versions")
+        );
+    }
+
+    #[test]
+    fn skips_missing_pins() {
+        let checks = check_version_pins(".venv", "3.12.1", None, Some("[tool.ruff]\n"));
+        assert_eq!(
+            statuses(&checks),
+            [
+                (".python-version", CheckStatus::Skip),
+                ("requires-python", CheckStatus::Skip),
+            ]
+        );
+    }
+}
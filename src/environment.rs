@@ -0,0 +1,1144 @@
+use crate::container::mount_point;
+use crate::error::PyEnvError;
+use crate::process::{
+    command_stdout, command_stdout_with_timeout, output_with_timeout, run_command,
+    NETWORK_COMMAND_TIMEOUT, VALIDATION_TIMEOUT,
+};
+use crate::project::normalize_package_name;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PythonEnvironment {
+    pub(crate) name: String,
+    /// Unique among the discovered environments, so `/pyenvselect` can address
+    /// environments sharing a name; see [`assign_identifiers`].
+    pub(crate) id: String,
+    pub(crate) python_path: PathBuf,
+    pub(crate) provider: String,
+    /// Name of the project the environment belongs to, for managers that
+    /// create one environment per project (e.g. Poetry).
+    pub(crate) project: Option<String>,
+    /// Set for the conda environment the `name:` of the worktree's
+    /// `environment.yml` declares. Version pins set [`Self::pinned`].
+    pub(crate) suggested: bool,
+    /// Set for the environment activated (via `VIRTUAL_ENV` or `CONDA_PREFIX`)
+    /// in the shell Zed was launched from.
+    pub(crate) active: bool,
+    pub(crate) version: Option<String>,
+    /// Why the interpreter failed to start, e.g. because its base interpreter
+    /// was removed. Broken environments are listed but can't be selected.
+    pub(crate) broken: Option<String>,
+    /// Other providers that reported the same interpreter.
+    pub(crate) also_found_by: Vec<String>,
+    /// Contents of `pyvenv.cfg`, for virtual environments.
+    pub(crate) pyvenv: Option<PyvenvConfig>,
+    /// Factors of a tox environment's name, e.g. `py311` and `django42`.
+    pub(crate) factors: Vec<String>,
+    /// Signature of the nox session that created the environment, e.g. `tests-3.11`.
+    pub(crate) session: Option<String>,
+    /// Display names of the Jupyter kernels that launch this interpreter.
+    pub(crate) kernels: Vec<String>,
+    /// The project lockfile or requirements file changed after packages were
+    /// last installed into this environment.
+    pub(crate) stale: Option<String>,
+    /// Lives in one of the worktree directories the `project_env_dirs`
+    /// setting names as the project's own environment.
+    pub(crate) project_env: bool,
+    /// Lives inside the worktree, as opposed to a global location such as
+    /// conda's `envs` directory, pyenv's versions or `WORKON_HOME`.
+    pub(crate) project_local: bool,
+    /// The interpreter the worktree's `.python-version` or `.tool-versions`
+    /// pins.
+    pub(crate) pinned: bool,
+    /// Set on environments of another open worktree listed alongside the
+    /// current one's: that worktree's name.
+    pub(crate) worktree: Option<String>,
+    /// Where the interpreter runs, for environments that only exist inside
+    /// a dev container or WSL distro; `python_path` is then a path in it.
+    pub(crate) guest: Option<Guest>,
+    /// Which Python it is, beyond its version; see [`Flavor`].
+    pub(crate) flavor: Option<Flavor>,
+}
+
+/// A system the host can run commands in but whose interpreters it can't
+/// start directly.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum Guest {
+    /// A running docker container, by name.
+    Container(String),
+    /// A WSL distribution, by name.
+    Wsl(String),
+}
+
+impl Guest {
+    /// The program and leading arguments that run a command in the guest
+    /// from the worktree at `root_path`, so relative file paths resolve.
+    pub(crate) fn launcher(&self, root_path: &Path) -> Result<Vec<String>, PyEnvError> {
+        match self {
+            Self::Container(name) => {
+                let workdir = mount_point(name, root_path).ok_or_else(|| {
+                    PyEnvError::InvalidSelection(format!(
+                        "couldn't find where the container {} mounts {}; is it still running?",
+                        name,
+                        root_path.display()
+                    ))
+                })?;
+                Ok(["docker", "exec", "-it", "-w", &workdir, name]
+                    .map(str::to_string)
+                    .to_vec())
+            }
+            // wsl translates a Windows working directory itself.
+            Self::Wsl(distro) => Ok(vec![
+                "wsl".to_string(),
+                "-d".to_string(),
+                distro.clone(),
+                "--cd".to_string(),
+                root_path.to_string_lossy().into_owned(),
+                "--".to_string(),
+            ]),
+        }
+    }
+}
+
+impl fmt::Display for Guest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Container(name) => write!(f, "the container {}", name),
+            Self::Wsl(distro) => write!(f, "the WSL distro {}", distro),
+        }
+    }
+}
+
+pub(crate) fn is_venv(path: &Path) -> bool {
+    let activate_scripts = [
+        path.join("bin").join("activate"),
+        path.join("Scripts").join("activate.bat"),
+        path.join("Scripts").join("Activate.ps1"),
+    ];
+    let pyvenv_cfg = path.join("pyvenv.cfg");
+
+    activate_scripts.iter().any(|script| script.exists()) || pyvenv_cfg.exists()
+}
+
+/// Finds the interpreter of an environment rooted at `venv_path`, checking
+/// the POSIX layout (`bin/python`) and the Windows layouts (`Scripts\python.exe`
+/// for venvs, `python.exe` at the root for conda and pyenv-win installs).
+pub(crate) fn find_python_executable(venv_path: &Path) -> Option<PathBuf> {
+    [
+        venv_path.join("bin").join("python"),
+        venv_path.join("bin").join("python3"),
+        venv_path.join("Scripts").join("python.exe"),
+        venv_path.join("python.exe"),
+    ]
+    .into_iter()
+    .find(|python_path| python_path.exists())
+}
+
+/// Inverse of [`find_python_executable`]: the environment directory that
+/// contains `python_path`.
+pub(crate) fn environment_root(python_path: &Path) -> Option<&Path> {
+    let parent = python_path.parent()?;
+    match parent.file_name().and_then(|name| name.to_str()) {
+        Some("bin") | Some("Scripts") => parent.parent(),
+        _ => Some(parent),
+    }
+}
+
+/// A command running the interpreter at `python_path` the way it runs once
+/// its environment is activated. Conda packages such as MKL or CUDA builds
+/// rely on activation: on the `PATH` entries of the environment (and its
+/// `Library` directories on Windows) and on the `etc/conda/activate.d`
+/// scripts some of them install. Those scripts only run under `conda run`,
+/// so it is used when there are any; otherwise the activation variables are
+/// set directly, sparing the second or so `conda run` takes to start.
+pub(crate) fn python_command(python_path: &Path) -> Command {
+    let Some(env_root) =
+        environment_root(python_path).filter(|env_root| env_root.join("conda-meta").is_dir())
+    else {
+        return Command::new(python_path);
+    };
+
+    let activation_scripts = env_root.join("etc").join("conda").join("activate.d");
+    if fs::read_dir(activation_scripts).is_ok_and(|mut scripts| scripts.next().is_some()) {
+        // micromamba doesn't capture output to begin with.
+        let runner = find_on_path("conda")
+            .map(|conda| (conda, true))
+            .or_else(|| find_on_path("micromamba").map(|micromamba| (micromamba, false)));
+        if let Some((runner, captures_output)) = runner {
+            let mut command = Command::new(runner);
+            command.arg("run");
+            if captures_output {
+                command.arg("--no-capture-output");
+            }
+            command.arg("--prefix").arg(env_root).arg(python_path);
+            return command;
+        }
+    }
+
+    let mut command = Command::new(python_path);
+    let mut path_dirs = conda_path_dirs(env_root);
+    if let Some(path) = std::env::var_os("PATH") {
+        path_dirs.extend(std::env::split_paths(&path));
+    }
+    if let Ok(path) = std::env::join_paths(path_dirs) {
+        command.env("PATH", path);
+    }
+    command.env("CONDA_PREFIX", env_root);
+    if let Some(name) = env_root.file_name() {
+        command.env("CONDA_DEFAULT_ENV", name);
+    }
+    command
+}
+
+/// The directories `conda activate` puts in front of `PATH` for the conda
+/// environment at `env_root`.
+fn conda_path_dirs(env_root: &Path) -> Vec<PathBuf> {
+    if !env_root.join("python.exe").exists() {
+        return vec![env_root.join("bin")];
+    }
+    let library = env_root.join("Library");
+    vec![
+        env_root.to_path_buf(),
+        library.join("mingw-w64").join("bin"),
+        library.join("usr").join("bin"),
+        library.join("bin"),
+        env_root.join("Scripts"),
+        env_root.join("bin"),
+    ]
+}
+
+/// Whether the worktree-relative directory `relative` matches `pattern`,
+/// whose components may end in `*` to match any suffix (`.direnv/python-*`).
+pub(crate) fn matches_dir_pattern(relative: &Path, pattern: &str) -> bool {
+    let components: Vec<_> = relative.components().collect();
+    let parts: Vec<&str> = pattern.split('/').filter(|part| !part.is_empty()).collect();
+    components.len() == parts.len()
+        && components.iter().zip(parts).all(|(component, part)| {
+            let component = component.as_os_str().to_string_lossy();
+            match part.strip_suffix('*') {
+                Some(prefix) => component.starts_with(prefix),
+                None => component == part,
+            }
+        })
+}
+
+pub(crate) fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// Lists every environment directly inside `dir`, named after its directory.
+pub(crate) fn environments_in(dir: &Path, provider: &str) -> Vec<PythonEnvironment> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut envs: Vec<PythonEnvironment> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            Some(PythonEnvironment {
+                python_path: find_python_executable(&entry.path())?,
+                name: entry.file_name().to_string_lossy().into_owned(),
+                provider: provider.to_string(),
+                ..Default::default()
+            })
+        })
+        .collect();
+    envs.sort_by(|a, b| a.name.cmp(&b.name));
+    envs
+}
+
+/// Finds an executable installed into the same environment as `python_path`,
+/// e.g. `.venv/bin/pylsp` or `.venv\Scripts\pylsp.exe`.
+pub(crate) fn environment_executable(python_path: &Path, name: &str) -> Option<PathBuf> {
+    let bin_dir = python_path.parent()?;
+    [
+        bin_dir.join(name),
+        bin_dir.join(format!("{}.exe", name)),
+        bin_dir.join("Scripts").join(format!("{}.exe", name)),
+    ]
+    .into_iter()
+    .find(|candidate| candidate.is_file())
+}
+
+/// Returns the first `PATH` entry containing an executable called `name`.
+pub(crate) fn find_on_path(name: &str) -> Option<PathBuf> {
+    let path_var = std::env::var_os("PATH")?;
+    std::env::split_paths(&path_var).find_map(|dir| {
+        [dir.join(name), dir.join(format!("{}.exe", name))]
+            .into_iter()
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+/// Checks that the interpreter at `python_path` actually starts.
+pub(crate) fn validate_interpreter(python_path: &Path) -> Result<(), PyEnvError> {
+    let output = output_with_timeout(
+        Command::new(python_path).args(["-c", "import sys; print(sys.version)"]),
+        VALIDATION_TIMEOUT,
+    )?;
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(PyEnvError::DiscoveryFailed {
+        context: python_path.display().to_string(),
+        message: stderr
+            .lines()
+            .rev()
+            .find(|line| !line.trim().is_empty())
+            .map_or_else(
+                || format!("exited with {}", output.status),
+                |line| line.trim().to_string(),
+            ),
+    })
+}
+
+pub(crate) fn query_python_version(python_path: &Path) -> Option<String> {
+    let output = run_command(Command::new(python_path).arg("--version")).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    // Python 2 prints its version to stderr, Python 3 to stdout.
+    let text = if output.stdout.is_empty() {
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    } else {
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    let version = text.trim().trim_start_matches("Python").trim();
+    if version.is_empty() {
+        None
+    } else {
+        Some(version.to_string())
+    }
+}
+
+/// The implementation of an interpreter and the distribution it came from,
+/// which tell apart installs of the same version.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Flavor {
+    /// `CPython`, `PyPy`, `GraalPy`, ... from `sys.implementation.name`.
+    pub(crate) implementation: String,
+    /// `Anaconda`, `conda-forge`, `python.org`, `Homebrew`, `Microsoft Store`
+    /// or `system`, when recognizable.
+    pub(crate) distribution: Option<String>,
+    /// The CPU architecture it was built for, `arm64` or `x86_64` (under
+    /// Rosetta or Windows' emulation on an ARM machine) or as reported.
+    pub(crate) machine: Option<String>,
+}
+
+impl Flavor {
+    /// Reads `sys.implementation.name`, `platform.machine()` and
+    /// `sys.version`. Conda builds say who packaged them in `sys.version`;
+    /// other distributions are told apart by where the (base) interpreter
+    /// lives in `base_dir`.
+    fn parse(implementation: &str, machine: &str, sys_version: &str, base_dir: &Path) -> Self {
+        let implementation = match implementation.trim() {
+            "cpython" => "CPython".to_string(),
+            "pypy" => "PyPy".to_string(),
+            "graalpy" | "graalpython" => "GraalPy".to_string(),
+            "ironpython" => "IronPython".to_string(),
+            "jython" => "Jython".to_string(),
+            other => other.to_string(),
+        };
+        let base_dir = base_dir.to_string_lossy();
+        let distribution = if sys_version.contains("Anaconda") || sys_version.contains("Continuum")
+        {
+            Some("Anaconda")
+        } else if sys_version.contains("conda-forge") {
+            Some("conda-forge")
+        } else if base_dir.contains("Python.framework") || base_dir.contains(r"\Programs\Python") {
+            Some("python.org")
+        } else if base_dir.contains("/Cellar/") || base_dir.starts_with("/opt/homebrew/") {
+            Some("Homebrew")
+        } else if base_dir.contains("WindowsApps") {
+            Some("Microsoft Store")
+        } else if ["/usr/bin", "/bin"].contains(&base_dir.as_ref()) {
+            Some("system")
+        } else {
+            None
+        };
+        // macOS and Linux say `arm64`/`aarch64`, Windows `ARM64`/`AMD64`.
+        let machine = match machine.trim().to_ascii_lowercase().as_str() {
+            "" => None,
+            "arm64" | "aarch64" => Some("arm64".to_string()),
+            "x86_64" | "amd64" | "x64" => Some("x86_64".to_string()),
+            other => Some(other.to_string()),
+        };
+        Self {
+            implementation,
+            distribution: distribution.map(str::to_string),
+            machine,
+        }
+    }
+
+    /// How listings show it, e.g. `CPython, Anaconda, arm64`.
+    pub(crate) fn label(&self) -> String {
+        std::iter::once(self.implementation.as_str())
+            .chain(self.distribution.as_deref())
+            .chain(self.machine.as_deref())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Asks the interpreter at `python_path` which [`Flavor`] it is. Virtual
+/// environments are placed by the base interpreter `pyvenv.cfg` names.
+pub(crate) fn query_flavor(python_path: &Path) -> Option<Flavor> {
+    let output = output_with_timeout(
+        Command::new(python_path).args([
+            "-c",
+            "import platform, sys; print(sys.implementation.name); print(platform.machine()); print(sys.version)",
+        ]),
+        VALIDATION_TIMEOUT,
+    )
+    .ok()
+    .filter(|output| output.status.success())?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (implementation, rest) = stdout.split_once('\n')?;
+    let (machine, sys_version) = rest.split_once('\n')?;
+    let base_dir = PyvenvConfig::for_interpreter(python_path)
+        .and_then(|cfg| cfg.home)
+        .or_else(|| python_path.parent().map(Path::to_path_buf))?;
+    Some(Flavor::parse(
+        implementation,
+        machine,
+        sys_version,
+        &base_dir,
+    ))
+}
+
+/// The parts of a virtual environment's `pyvenv.cfg` we care about.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PyvenvConfig {
+    /// Directory containing the base interpreter the venv was created from.
+    pub(crate) home: Option<PathBuf>,
+    pub(crate) version: Option<String>,
+    pub(crate) include_system_site_packages: bool,
+    /// Name given with `--prompt` (or by uv and Poetry), shown in the shell prompt.
+    pub(crate) prompt: Option<String>,
+}
+
+impl PyvenvConfig {
+    /// Reads the `pyvenv.cfg` of the environment `python_path` belongs to.
+    pub(crate) fn for_interpreter(python_path: &Path) -> Option<Self> {
+        let env_root = environment_root(python_path)?;
+        let contents = fs::read_to_string(env_root.join("pyvenv.cfg")).ok()?;
+        Some(Self::parse(&contents))
+    }
+
+    fn parse(contents: &str) -> Self {
+        let mut config = Self::default();
+        for line in contents.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "home" => config.home = Some(PathBuf::from(value)),
+                // venv writes `version`, virtualenv and uv `version_info` (e.g.
+                // `3.11.4.final.0`); keep the numeric part.
+                "version" | "version_info" if config.version.is_none() => {
+                    let version: Vec<&str> = value
+                        .split('.')
+                        .take_while(|part| part.chars().all(|c| c.is_ascii_digit()))
+                        .collect();
+                    config.version = Some(version.join("."));
+                }
+                "include-system-site-packages" => {
+                    config.include_system_site_packages = value.eq_ignore_ascii_case("true");
+                }
+                "prompt" => {
+                    let prompt = value.trim_matches(|c| c == '\'' || c == '"');
+                    if !prompt.is_empty() {
+                        config.prompt = Some(prompt.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+        config
+    }
+}
+
+/// Reads the Python version recorded in an environment's `pyvenv.cfg`.
+pub(crate) fn read_pyvenv_version(python_path: &Path) -> Option<String> {
+    PyvenvConfig::for_interpreter(python_path)?.version
+}
+
+/// Script run by [`probe_interpreter`]; prints a JSON [`InterpreterInfo`].
+const PROBE_SCRIPT: &str = r#"
+import json, platform, sys
+try:
+    from importlib.metadata import version
+    pip_version = version("pip")
+except Exception:
+    pip_version = None
+print(json.dumps({
+    "version": platform.python_version(),
+    "implementation": platform.python_implementation(),
+    "prefix": sys.prefix,
+    "base_prefix": getattr(sys, "base_prefix", sys.prefix),
+    "sys_path": sys.path,
+    "pip_version": pip_version,
+}))
+"#;
+
+/// Details an interpreter reports about itself.
+#[derive(Deserialize)]
+pub(crate) struct InterpreterInfo {
+    pub(crate) version: String,
+    pub(crate) implementation: String,
+    pub(crate) prefix: String,
+    pub(crate) base_prefix: String,
+    pub(crate) sys_path: Vec<String>,
+    pub(crate) pip_version: Option<String>,
+}
+
+pub(crate) fn probe_interpreter(python_path: &Path) -> Result<InterpreterInfo, PyEnvError> {
+    let output = command_stdout(python_command(python_path).args(["-c", PROBE_SCRIPT]))?;
+    serde_json::from_str(output.trim()).map_err(PyEnvError::parse("interpreter details"))
+}
+
+/// Total size of the files under `path`, without following symlinks.
+pub(crate) fn directory_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => directory_size(&entry.path()),
+            Ok(file_type) if file_type.is_file() => entry.metadata().map_or(0, |m| m.len()),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// The directory whose size is the size of the environment of
+/// `python_path`, or `None` when the interpreter lives in a shared prefix
+/// such as `/usr`, where the total would mostly be other software.
+pub(crate) fn environment_size_root<'a>(python_path: &'a Path, provider: &str) -> Option<&'a Path> {
+    let env_root = environment_root(python_path)?;
+    let standalone_install = matches!(provider, "pyenv" | "asdf");
+    (is_venv(env_root) || env_root.join("conda-meta").is_dir() || standalone_install)
+        .then_some(env_root)
+}
+
+/// A distribution installed in an environment, as reported by `pip list`.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct InstalledPackage {
+    pub(crate) name: String,
+    pub(crate) version: String,
+}
+
+/// Lists the packages installed in the environment of `python_path`, using
+/// `pip list` and falling back to reading `*.dist-info` directories when pip
+/// isn't installed there.
+pub(crate) fn list_installed_packages(
+    python_path: &Path,
+) -> Result<Vec<InstalledPackage>, PyEnvError> {
+    let mut packages = match command_stdout(python_command(python_path).args([
+        "-m",
+        "pip",
+        "list",
+        "--format=json",
+        "--disable-pip-version-check",
+    ])) {
+        Ok(output) => serde_json::from_str::<Vec<InstalledPackage>>(&output)
+            .map_err(PyEnvError::parse("pip output"))?,
+        Err(pip_error) => {
+            let packages = read_dist_info_packages(python_path);
+            if packages.is_empty() {
+                return Err(pip_error);
+            }
+            packages
+        }
+    };
+    packages.sort_by_key(|package| package.name.to_lowercase());
+    Ok(packages)
+}
+
+/// How the packages of one environment differ from another's.
+#[derive(Debug, Default)]
+pub(crate) struct PackageDiff {
+    /// Installed only in the second environment.
+    pub(crate) added: Vec<InstalledPackage>,
+    /// Installed only in the first environment.
+    pub(crate) removed: Vec<InstalledPackage>,
+    /// Name, first version and second version of packages in both.
+    pub(crate) changed: Vec<(String, String, String)>,
+}
+
+/// Compares two package lists by normalized name, sorted by name.
+pub(crate) fn diff_packages(
+    first: &[InstalledPackage],
+    second: &[InstalledPackage],
+) -> PackageDiff {
+    let find = |packages: &[InstalledPackage], name: &str| {
+        packages
+            .iter()
+            .find(|package| normalize_package_name(&package.name) == normalize_package_name(name))
+            .cloned()
+    };
+    let mut diff = PackageDiff::default();
+    for package in first {
+        match find(second, &package.name) {
+            None => diff.removed.push(package.clone()),
+            Some(other) if other.version != package.version => {
+                diff.changed
+                    .push((package.name.clone(), package.version.clone(), other.version))
+            }
+            Some(_) => {}
+        }
+    }
+    diff.added = second
+        .iter()
+        .filter(|package| find(first, &package.name).is_none())
+        .cloned()
+        .collect();
+
+    diff.added
+        .sort_by_key(|package| package.name.to_lowercase());
+    diff.removed
+        .sort_by_key(|package| package.name.to_lowercase());
+    diff.changed.sort_by_key(|(name, _, _)| name.to_lowercase());
+    diff
+}
+
+/// A package with a newer release available, as reported by
+/// `pip list --outdated`.
+#[derive(Deserialize)]
+pub(crate) struct OutdatedPackage {
+    pub(crate) name: String,
+    pub(crate) version: String,
+    pub(crate) latest_version: String,
+}
+
+/// Lists packages in the environment of `python_path` that have newer
+/// releases on the package index. Requires pip and network access.
+pub(crate) fn list_outdated_packages(
+    python_path: &Path,
+) -> Result<Vec<OutdatedPackage>, PyEnvError> {
+    let output = command_stdout_with_timeout(
+        python_command(python_path).args([
+            "-m",
+            "pip",
+            "list",
+            "--outdated",
+            "--format=json",
+            "--disable-pip-version-check",
+        ]),
+        NETWORK_COMMAND_TIMEOUT,
+    )?;
+    let mut packages = serde_json::from_str::<Vec<OutdatedPackage>>(&output)
+        .map_err(PyEnvError::parse("pip output"))?;
+    packages.sort_by_key(|package| package.name.to_lowercase());
+    Ok(packages)
+}
+
+/// Whether `package` is installed in the environment of `python_path`.
+pub(crate) fn has_package(python_path: &Path, package: &str) -> Result<bool, PyEnvError> {
+    let package = normalize_package_name(package);
+    Ok(list_installed_packages(python_path)?
+        .iter()
+        .any(|installed| normalize_package_name(&installed.name) == package))
+}
+
+/// The tool that installs packages into an environment.
+pub(crate) enum Installer {
+    Pip,
+    Uv(PathBuf),
+}
+
+impl Installer {
+    /// uv when `prefer_uv` is set or the environment has no pip (`uv venv`
+    /// creates environments without it) and uv is installed; pip otherwise.
+    pub(crate) fn for_environment(python_path: &Path, prefer_uv: bool) -> Self {
+        let wants_uv = prefer_uv || environment_executable(python_path, "pip").is_none();
+        match find_on_path("uv") {
+            Some(uv) if wants_uv => Self::Uv(uv),
+            _ => Self::Pip,
+        }
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Pip => "pip",
+            Self::Uv(_) => "uv",
+        }
+    }
+
+    /// Runs `pip install <args>` (or `uv pip install`) for the environment of
+    /// `python_path` from `cwd`, returning the `name-version` of each package
+    /// it installed.
+    pub(crate) fn install(
+        &self,
+        python_path: &Path,
+        cwd: Option<&Path>,
+        args: &[&str],
+    ) -> Result<Vec<String>, PyEnvError> {
+        let mut command = match self {
+            Self::Pip => {
+                let mut command = python_command(python_path);
+                command.args(["-m", "pip", "install", "--disable-pip-version-check"]);
+                command
+            }
+            Self::Uv(uv) => {
+                let mut command = Command::new(uv);
+                command
+                    .args(["pip", "install", "--python"])
+                    .arg(python_path);
+                command
+            }
+        };
+        command.args(args);
+        if let Some(cwd) = cwd {
+            command.current_dir(cwd);
+        }
+
+        let output = output_with_timeout(&mut command, NETWORK_COMMAND_TIMEOUT)?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() {
+            return Err(PyEnvError::DiscoveryFailed {
+                context: format!("{} install", self.name()),
+                message: format!("exited with {}: {}", output.status, stderr.trim()),
+            });
+        }
+        // pip reports on stdout and uv on stderr.
+        Ok(installed_packages(&format!("{}\n{}", stdout, stderr)))
+    }
+
+    /// Runs `pip freeze` (or `uv pip freeze`) for the environment of `python_path`.
+    pub(crate) fn freeze(&self, python_path: &Path) -> Result<String, PyEnvError> {
+        match self {
+            Self::Pip => command_stdout(python_command(python_path).args([
+                "-m",
+                "pip",
+                "freeze",
+                "--disable-pip-version-check",
+            ])),
+            Self::Uv(uv) => command_stdout(
+                Command::new(uv)
+                    .args(["pip", "freeze", "--python"])
+                    .arg(python_path),
+            ),
+        }
+    }
+}
+
+/// Reads the packages an install added from pip's `Successfully installed a-1
+/// b-2` line or uv's ` + a==1` lines.
+fn installed_packages(output: &str) -> Vec<String> {
+    let mut installed = Vec::new();
+    for line in output.lines() {
+        if let Some(packages) = line.strip_prefix("Successfully installed ") {
+            installed.extend(packages.split_whitespace().map(str::to_string));
+        } else if let Some(package) = line.trim_start().strip_prefix("+ ") {
+            installed.push(package.trim().replacen("==", "-", 1));
+        }
+    }
+    installed
+}
+
+/// FNV-1a hash of `path` as six hex digits. Unlike `DefaultHasher` it is
+/// stable across runs and Rust releases, so identifiers survive restarts.
+fn short_path_hash(path: &Path) -> String {
+    let hash = path
+        .to_string_lossy()
+        .bytes()
+        .fold(0x811c_9dc5_u32, |hash, byte| {
+            (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+        });
+    format!("{:06x}", hash & 0xff_ffff)
+}
+
+/// Gives every environment an `id` of `provider:name`, appending a hash of
+/// the interpreter path (`conda:ml@3fa2c1`) when two environments share both.
+pub(crate) fn assign_identifiers(environments: &mut [PythonEnvironment]) {
+    let bases: Vec<String> = environments
+        .iter()
+        .map(|env| format!("{}:{}", env.provider, env.name))
+        .collect();
+    for (env, base) in environments.iter_mut().zip(&bases) {
+        env.id = if bases.iter().filter(|other| *other == base).count() > 1 {
+            format!("{}@{}", base, short_path_hash(&env.python_path))
+        } else {
+            base.clone()
+        };
+    }
+}
+
+/// Identifies an interpreter independently of the path it was found under:
+/// the canonical environment root plus the canonical executable. The root
+/// is needed because a venv's `python` is a symlink to its base interpreter,
+/// and the executable because `/usr/bin/python2` and `/usr/bin/python3` share
+/// a root.
+pub(crate) fn interpreter_identity(python_path: &Path) -> (PathBuf, PathBuf) {
+    let canonical = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let root = environment_root(python_path)
+        .or_else(|| python_path.parent())
+        .map(canonical)
+        .unwrap_or_default();
+    (root, canonical(python_path))
+}
+
+/// Files declaring a project's dependencies, checked by [`stale_dependency_file`].
+const DEPENDENCY_FILES: &[&str] = &[
+    "uv.lock",
+    "poetry.lock",
+    "Pipfile.lock",
+    "pdm.lock",
+    "pixi.lock",
+    "requirements.txt",
+];
+
+/// When packages were last installed into or removed from the environment
+/// of `python_path`: the newest modification time of its `site-packages`.
+fn last_installed(python_path: &Path) -> Option<SystemTime> {
+    site_packages_dirs(python_path)
+        .iter()
+        .filter_map(|dir| fs::metadata(dir).and_then(|meta| meta.modified()).ok())
+        .max()
+}
+
+/// The most recently changed of the project's [`DEPENDENCY_FILES`] in
+/// `root_path`, if it changed after the environment was last synced.
+pub(crate) fn stale_dependency_file(python_path: &Path, root_path: &Path) -> Option<String> {
+    let installed = last_installed(python_path)?;
+    DEPENDENCY_FILES
+        .iter()
+        .filter_map(|name| {
+            let modified = fs::metadata(root_path.join(name))
+                .and_then(|meta| meta.modified())
+                .ok()?;
+            (modified > installed).then_some((modified, *name))
+        })
+        .max()
+        .map(|(_, name)| name.to_string())
+}
+
+/// Returns the `site-packages` directories of the environment of `python_path`
+/// (`lib/pythonX.Y/site-packages` on POSIX, `Lib\site-packages` on Windows).
+fn site_packages_dirs(python_path: &Path) -> Vec<PathBuf> {
+    let Some(env_root) = environment_root(python_path) else {
+        return Vec::new();
+    };
+    let mut dirs = vec![env_root.join("Lib").join("site-packages")];
+    if let Ok(entries) = fs::read_dir(env_root.join("lib")) {
+        dirs.extend(
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path().join("site-packages")),
+        );
+    }
+    dirs.retain(|dir| dir.is_dir());
+    dirs
+}
+
+/// Reads `<name>-<version>.dist-info` directory names from site-packages.
+fn read_dist_info_packages(python_path: &Path) -> Vec<InstalledPackage> {
+    site_packages_dirs(python_path)
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.filter_map(Result::ok))
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let stem = file_name.strip_suffix(".dist-info")?;
+            let (name, version) = stem.split_once('-')?;
+            Some(InstalledPackage {
+                name: name.to_string(),
+                version: version.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+    use std::collections::HashMap;
+    use std::ffi::OsStr;
+
+    fn packages(list: &[(&str, &str)]) -> Vec<InstalledPackage> {
+        list.iter()
+            .map(|(name, version)| InstalledPackage {
+                name: name.to_string(),
+                version: version.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn disambiguates_identifiers() {
+        let env = |name: &str, provider: &str, path: &str| PythonEnvironment {
+            name: name.to_string(),
+            provider: provider.to_string(),
+            python_path: PathBuf::from(path),
+            ..Default::default()
+        };
+        let mut envs = [
+            env("ml", "conda", "/opt/miniconda3/envs/ml/bin/python"),
+            env("ml", "conda", "/home/dev/.conda/envs/ml/bin/python"),
+            env("ml", "venv", "/work/ml/bin/python"),
+        ];
+        assign_identifiers(&mut envs);
+        assert!(envs[0].id.starts_with("conda:ml@"));
+        assert_eq!(envs[0].id.len(), "conda:ml@".len() + 6);
+        assert_ne!(envs[0].id, envs[1].id);
+        assert_eq!(envs[2].id, "venv:ml");
+
+        let first = envs[0].id.clone();
+        assign_identifiers(&mut envs);
+        assert_eq!(envs[0].id, first);
+    }
+
+    #[test]
+    fn matches_directory_patterns() {
+        assert!(matches_dir_pattern(Path::new(".venv"), ".venv"));
+        assert!(!matches_dir_pattern(Path::new("api/.venv"), ".venv"));
+        assert!(matches_dir_pattern(
+            Path::new(".direnv/python-3.12.1"),
+            ".direnv/python-*"
+        ));
+        assert!(!matches_dir_pattern(
+            Path::new(".direnv"),
+            ".direnv/python-*"
+        ));
+        assert!(matches_dir_pattern(Path::new("envs/dev"), "envs/*/"));
+    }
+
+    #[test]
+    fn reads_installed_packages_from_pip_and_uv() {
+        let pip = "Collecting rich\nRequirement already satisfied: pygments\nSuccessfully installed markdown-it-py-3.0.0 rich-13.7.0\n";
+        assert_eq!(
+            installed_packages(pip),
+            ["markdown-it-py-3.0.0", "rich-13.7.0"]
+        );
+        let uv = "Resolved 3 packages in 120ms\nInstalled 2 packages in 8ms\n + mdurl==0.1.2\n + rich==13.7.0\n";
+        assert_eq!(installed_packages(uv), ["mdurl-0.1.2", "rich-13.7.0"]);
+        assert!(installed_packages("Requirement already satisfied: rich\n").is_empty());
+    }
+
+    #[test]
+    fn measures_only_self_contained_environments() {
+        let fixture = Fixture::new();
+        let venv = fixture.venv("project/.venv");
+        let conda = fixture.touch("miniconda3/envs/ml/bin/python");
+        fixture.touch("miniconda3/envs/ml/conda-meta/history");
+        let system = fixture.touch("usr/bin/python3");
+
+        assert_eq!(
+            environment_size_root(&venv, "venv"),
+            Some(fixture.path().join("project/.venv").as_path())
+        );
+        assert!(environment_size_root(&conda, "conda").is_some());
+        assert_eq!(environment_size_root(&system, "system"), None);
+    }
+
+    #[test]
+    fn flags_environments_older_than_the_lockfile() {
+        let fixture = Fixture::new();
+        let python = fixture.venv(".venv");
+        let site_packages = fixture.path().join(".venv/lib/python3.12/site-packages");
+        fs::create_dir_all(&site_packages).unwrap();
+        let lockfile = fixture.write("uv.lock", "version = 1\n");
+        fixture.write("requirements.txt", "requests\n");
+
+        let set_modified = |path: &Path, secs_ago: u64| {
+            let time = SystemTime::now() - std::time::Duration::from_secs(secs_ago);
+            fs::File::options()
+                .write(true)
+                .open(path)
+                .or_else(|_| fs::File::open(path))
+                .unwrap()
+                .set_modified(time)
+                .unwrap();
+        };
+        set_modified(&site_packages, 60);
+        set_modified(&fixture.path().join("requirements.txt"), 120);
+        assert_eq!(
+            stale_dependency_file(&python, fixture.path()).as_deref(),
+            Some("uv.lock")
+        );
+
+        set_modified(&lockfile, 300);
+        assert_eq!(stale_dependency_file(&python, fixture.path()), None);
+    }
+
+    #[test]
+    fn diffs_package_lists() {
+        let first = packages(&[
+            ("requests", "2.31.0"),
+            ("Django", "4.2.7"),
+            ("black", "23.1"),
+        ]);
+        let second = packages(&[
+            ("requests", "2.31.0"),
+            ("django", "5.0"),
+            ("pytest", "8.0.0"),
+        ]);
+        let diff = diff_packages(&first, &second);
+
+        let names = |packages: &[InstalledPackage]| {
+            packages
+                .iter()
+                .map(|package| package.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&diff.added), ["pytest"]);
+        assert_eq!(names(&diff.removed), ["black"]);
+        assert_eq!(
+            diff.changed,
+            [("Django".to_string(), "4.2.7".to_string(), "5.0".to_string())]
+        );
+    }
+
+    #[test]
+    fn finds_posix_interpreter() {
+        let fixture = Fixture::new();
+        let python_path = fixture.venv("env");
+
+        assert!(is_venv(&fixture.path().join("env")));
+        assert_eq!(
+            find_python_executable(&fixture.path().join("env")),
+            Some(python_path.clone())
+        );
+        assert_eq!(
+            environment_root(&python_path),
+            Some(fixture.path().join("env").as_path())
+        );
+    }
+
+    #[test]
+    fn finds_windows_venv_interpreter() {
+        let fixture = Fixture::new();
+        fixture.touch("env/Scripts/Activate.ps1");
+        let python_path = fixture.touch("env/Scripts/python.exe");
+        let env_root = fixture.path().join("env");
+
+        assert!(is_venv(&env_root));
+        assert_eq!(find_python_executable(&env_root), Some(python_path.clone()));
+        assert_eq!(environment_root(&python_path), Some(env_root.as_path()));
+    }
+
+    #[test]
+    fn finds_windows_root_interpreter() {
+        let fixture = Fixture::new();
+        let python_path = fixture.touch("pyenv-win/versions/3.11.9/python.exe");
+        let env_root = fixture.path().join("pyenv-win/versions/3.11.9");
+
+        assert!(!is_venv(&env_root));
+        assert_eq!(find_python_executable(&env_root), Some(python_path.clone()));
+        assert_eq!(environment_root(&python_path), Some(env_root.as_path()));
+    }
+
+    #[test]
+    fn reads_packages_from_windows_site_packages() {
+        let fixture = Fixture::new();
+        let python_path = fixture.touch("env/Scripts/python.exe");
+        fs::create_dir_all(
+            fixture
+                .path()
+                .join("env/Lib/site-packages/requests-2.32.3.dist-info"),
+        )
+        .unwrap();
+        fs::create_dir_all(fixture.path().join("env/Lib/site-packages/requests")).unwrap();
+
+        let packages = read_dist_info_packages(&python_path);
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].name, "requests");
+        assert_eq!(packages[0].version, "2.32.3");
+    }
+
+    #[test]
+    fn parses_pyvenv_cfg() {
+        let config = PyvenvConfig::parse(
+            "home = /usr/local/bin\n\
+             include-system-site-packages = True\n\
+             version_info = 3.11.4.final.0\n\
+             prompt = 'my-project'\n",
+        );
+        assert_eq!(config.home, Some(PathBuf::from("/usr/local/bin")));
+        assert_eq!(config.version.as_deref(), Some("3.11.4"));
+        assert!(config.include_system_site_packages);
+        assert_eq!(config.prompt.as_deref(), Some("my-project"));
+
+        let config = PyvenvConfig::parse("version = 3.12.1\nprompt = ''\n");
+        assert_eq!(config.version.as_deref(), Some("3.12.1"));
+        assert!(!config.include_system_site_packages);
+        assert_eq!(config.prompt, None);
+    }
+
+    #[test]
+    fn activates_conda_environments() {
+        let fixture = Fixture::new();
+        let venv_python = fixture.venv(".venv");
+        assert!(python_command(&venv_python).get_envs().next().is_none());
+
+        fixture.touch("envs/ml/conda-meta/history");
+        fixture.touch("envs/ml/bin/python");
+        let command = python_command(&fixture.path().join("envs/ml/bin/python"));
+        let env: HashMap<_, _> = command
+            .get_envs()
+            .map(|(name, value)| (name.to_os_string(), value.map(PathBuf::from)))
+            .collect();
+        let env_root = fixture.path().join("envs").join("ml");
+        assert_eq!(env[OsStr::new("CONDA_PREFIX")], Some(env_root.clone()));
+        let path = env[OsStr::new("PATH")].clone().unwrap();
+        assert_eq!(
+            std::env::split_paths(&path).next(),
+            Some(env_root.join("bin"))
+        );
+    }
+
+    #[test]
+    fn tells_implementations_and_distributions_apart() {
+        let flavor = |implementation: &str, sys_version: &str, base_dir: &str| {
+            Flavor::parse(implementation, "", sys_version, Path::new(base_dir)).label()
+        };
+        assert_eq!(
+            flavor(
+                "cpython",
+                "3.11.5 | packaged by Anaconda, Inc. | (main, Sep 11 2023) [GCC 11.2.0]",
+                "/opt/anaconda3/bin"
+            ),
+            "CPython, Anaconda"
+        );
+        assert_eq!(
+            flavor(
+                "pypy",
+                "3.10.13 (fc4d5ef4, Sep 29 2023)\n[PyPy 7.3.13 with GCC 10.2.1]",
+                "/home/dev/.pyenv/versions/pypy3.10-7.3.13/bin"
+            ),
+            "PyPy"
+        );
+        assert_eq!(
+            flavor(
+                "cpython",
+                "3.12.1 (v3.12.1:2305ca5144, Dec  7 2023) [Clang 13.0.0]",
+                "/Library/Frameworks/Python.framework/Versions/3.12/bin"
+            ),
+            "CPython, python.org"
+        );
+        assert_eq!(
+            flavor("graalpy", "3.10.8 (Oracle GraalVM)", "/usr/bin"),
+            "GraalPy, system"
+        );
+        let machine = |machine: &str| {
+            Flavor::parse("cpython", machine, "", Path::new("/opt/python/bin")).machine
+        };
+        assert_eq!(machine("aarch64").as_deref(), Some("arm64"));
+        assert_eq!(machine("AMD64").as_deref(), Some("x86_64"));
+        assert_eq!(machine("riscv64").as_deref(), Some("riscv64"));
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt;
+use std::io;
+
+/// Errors raised while discovering, selecting and managing environments. The
+/// extension entry points report errors as strings, so this converts into one.
+#[derive(Debug)]
+pub(crate) enum PyEnvError {
+    /// A tool or interpreter ran but didn't produce what we needed.
+    DiscoveryFailed {
+        context: String,
+        message: String,
+    },
+    /// A provider's tool or data directory isn't present on this machine.
+    ProviderUnavailable {
+        provider: String,
+        reason: String,
+    },
+    /// The user's input doesn't identify a usable environment.
+    InvalidSelection(String),
+    Io {
+        context: String,
+        source: io::Error,
+    },
+    Parse {
+        context: String,
+        message: String,
+    },
+}
+
+impl PyEnvError {
+    /// For `map_err`: wraps an I/O error with what we were doing.
+    pub(crate) fn io(context: impl Into<String>) -> impl FnOnce(io::Error) -> Self {
+        move |source| Self::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    /// For `map_err`: wraps a deserialization error with what was being parsed.
+    pub(crate) fn parse<E: fmt::Display>(context: impl Into<String>) -> impl FnOnce(E) -> Self {
+        move |error| Self::Parse {
+            context: context.into(),
+            message: error.to_string(),
+        }
+    }
+
+    pub(crate) fn unavailable(provider: &str, reason: impl Into<String>) -> Self {
+        Self::ProviderUnavailable {
+            provider: provider.to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for PyEnvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DiscoveryFailed { context, message } => write!(f, "{}: {}", context, message),
+            Self::ProviderUnavailable { provider, reason } => {
+                write!(f, "{} is unavailable: {}", provider, reason)
+            }
+            Self::InvalidSelection(message) => f.write_str(message),
+            Self::Io { context, source } => write!(f, "{}: {}", context, source),
+            Self::Parse { context, message } => {
+                write!(f, "Failed to parse {}: {}", context, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PyEnvError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<PyEnvError> for String {
+    fn from(error: PyEnvError) -> Self {
+        error.to_string()
+    }
+}
@@ -0,0 +1,245 @@
+use crate::environment::{find_python_executable, home_dir, interpreter_identity, python_command};
+use crate::error::PyEnvError;
+use crate::process::command_stdout;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A Jupyter kernelspec: a `kernel.json` in one of Jupyter's `kernels`
+/// directories.
+#[derive(Debug)]
+pub(crate) struct Kernelspec {
+    /// Directory name, which is what `jupyter` and notebook metadata call it.
+    pub(crate) name: String,
+    pub(crate) display_name: String,
+    /// Interpreter the kernel launches, when it can be told from `argv`.
+    pub(crate) python_path: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+struct KernelJson {
+    #[serde(default)]
+    argv: Vec<String>,
+    #[serde(default)]
+    display_name: String,
+    #[serde(default)]
+    language: String,
+}
+
+/// Directories Jupyter looks for kernelspecs in, most specific first:
+/// `JUPYTER_PATH`, the user data directory, the `share/jupyter` of each
+/// environment in `env_roots`, and the system-wide locations.
+fn kernel_dirs(env_roots: &[PathBuf]) -> Vec<PathBuf> {
+    let mut data_dirs: Vec<PathBuf> = std::env::var_os("JUPYTER_PATH")
+        .map(|paths| std::env::split_paths(&paths).collect())
+        .unwrap_or_default();
+
+    if let Some(data_dir) = std::env::var_os("JUPYTER_DATA_DIR") {
+        data_dirs.push(PathBuf::from(data_dir));
+    } else {
+        if let Some(xdg_data_home) = std::env::var_os("XDG_DATA_HOME") {
+            data_dirs.push(Path::new(&xdg_data_home).join("jupyter"));
+        }
+        if let Some(home) = home_dir() {
+            data_dirs.push(home.join(".local").join("share").join("jupyter"));
+            data_dirs.push(home.join("Library").join("Jupyter"));
+        }
+        if let Some(appdata) = std::env::var_os("APPDATA") {
+            data_dirs.push(Path::new(&appdata).join("jupyter"));
+        }
+    }
+
+    data_dirs.extend(
+        env_roots
+            .iter()
+            .map(|root| root.join("share").join("jupyter")),
+    );
+    data_dirs.extend(
+        ["/usr/local/share/jupyter", "/usr/share/jupyter"]
+            .into_iter()
+            .map(PathBuf::from),
+    );
+    if let Some(program_data) = std::env::var_os("PROGRAMDATA") {
+        data_dirs.push(Path::new(&program_data).join("jupyter"));
+    }
+
+    let mut kernel_dirs: Vec<PathBuf> = data_dirs.iter().map(|dir| dir.join("kernels")).collect();
+    kernel_dirs.dedup();
+    kernel_dirs
+}
+
+/// Reads one kernelspec directory, skipping kernels for other languages.
+fn read_kernelspec(resource_dir: &Path) -> Option<Kernelspec> {
+    let contents = fs::read_to_string(resource_dir.join("kernel.json")).ok()?;
+    let kernel: KernelJson = serde_json::from_str(&contents).ok()?;
+    if !kernel.language.eq_ignore_ascii_case("python") {
+        return None;
+    }
+    let name = resource_dir.file_name()?.to_string_lossy().into_owned();
+    Some(Kernelspec {
+        display_name: if kernel.display_name.is_empty() {
+            name.clone()
+        } else {
+            kernel.display_name
+        },
+        name,
+        python_path: kernel
+            .argv
+            .first()
+            .and_then(|program| kernel_interpreter(program, resource_dir)),
+    })
+}
+
+/// Resolves the first `argv` entry of a kernel. `ipykernel install --sys-prefix`
+/// writes a bare `python`, meaning the interpreter of the environment whose
+/// `share/jupyter/kernels` holds the spec.
+fn kernel_interpreter(program: &str, resource_dir: &Path) -> Option<PathBuf> {
+    let program = Path::new(program);
+    if program.is_absolute() {
+        return Some(program.to_path_buf());
+    }
+    let mut ancestors = resource_dir.ancestors().skip(1);
+    let in_env_share = ["kernels", "jupyter", "share"]
+        .iter()
+        .all(|expected| ancestors.next().and_then(Path::file_name) == Some(expected.as_ref()));
+    if !in_env_share {
+        return None;
+    }
+    find_python_executable(ancestors.next()?)
+}
+
+/// Lists the Python kernelspecs installed for the user, system-wide, and in
+/// the environments rooted at `env_roots`. Like Jupyter, an earlier directory
+/// shadows a later kernel of the same name.
+pub(crate) fn list_kernelspecs(env_roots: &[PathBuf]) -> Vec<Kernelspec> {
+    let mut kernels: Vec<Kernelspec> = Vec::new();
+    for kernel_dir in kernel_dirs(env_roots) {
+        let Ok(entries) = fs::read_dir(&kernel_dir) else {
+            continue;
+        };
+        let mut found: Vec<Kernelspec> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| read_kernelspec(&entry.path()))
+            .filter(|kernel| !kernels.iter().any(|known| known.name == kernel.name))
+            .collect();
+        found.sort_by(|a, b| a.name.cmp(&b.name));
+        kernels.extend(found);
+    }
+    kernels
+}
+
+/// Display names of the kernels in `kernels` that launch `python_path`.
+pub(crate) fn kernels_for(python_path: &Path, kernels: &[Kernelspec]) -> Vec<String> {
+    let identity = interpreter_identity(python_path);
+    kernels
+        .iter()
+        .filter(|kernel| {
+            kernel
+                .python_path
+                .as_deref()
+                .is_some_and(|kernel_python| interpreter_identity(kernel_python) == identity)
+        })
+        .map(|kernel| kernel.display_name.clone())
+        .collect()
+}
+
+/// Turns an environment name into a valid kernel name, which Jupyter limits
+/// to ASCII letters, digits, `-`, `.` and `_`: `.venv` becomes `venv` and
+/// `ML env` becomes `ml-env`.
+pub(crate) fn kernel_name(env_name: &str) -> String {
+    let name: String = env_name
+        .trim_start_matches('.')
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' | '-' | '.' | '_' => c,
+            'A'..='Z' => c.to_ascii_lowercase(),
+            _ => '-',
+        })
+        .collect();
+    if name.is_empty() {
+        "python3".to_string()
+    } else {
+        name
+    }
+}
+
+/// Registers the interpreter at `python_path` as a kernel for the current
+/// user with `ipykernel install --user`, returning ipykernel's report.
+pub(crate) fn install_kernel(
+    python_path: &Path,
+    name: &str,
+    display_name: &str,
+) -> Result<String, PyEnvError> {
+    let output = command_stdout(
+        python_command(python_path)
+            .args(["-m", "ipykernel", "install", "--user", "--name", name])
+            .args(["--display-name", display_name]),
+    )?;
+    Ok(output.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+
+    fn kernel_json(argv0: &str, display_name: &str, language: &str) -> String {
+        serde_json::json!({
+            "argv": [argv0, "-m", "ipykernel_launcher", "-f", "{connection_file}"],
+            "display_name": display_name,
+            "language": language,
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn maps_kernels_to_interpreters() {
+        let fixture = Fixture::new();
+        let analysis = fixture.venv("envs/analysis");
+        let sys_prefix = fixture.venv("envs/notebooks");
+        let user_kernels = fixture.path().join("user/kernels");
+
+        fixture.write(
+            "user/kernels/analysis/kernel.json",
+            &kernel_json(&analysis.to_string_lossy(), "Python (analysis)", "python"),
+        );
+        fixture.write("user/kernels/ir/kernel.json", &kernel_json("R", "R", "R"));
+        fixture.write(
+            "envs/notebooks/share/jupyter/kernels/python3/kernel.json",
+            &kernel_json("python", "", "python"),
+        );
+
+        let kernel_dirs = [
+            user_kernels,
+            fixture.path().join("envs/notebooks/share/jupyter/kernels"),
+        ];
+        let kernels: Vec<Kernelspec> = kernel_dirs
+            .iter()
+            .flat_map(|dir| fs::read_dir(dir).unwrap())
+            .filter_map(|entry| read_kernelspec(&entry.unwrap().path()))
+            .collect();
+        assert_eq!(kernels.len(), 2);
+
+        assert_eq!(kernels_for(&analysis, &kernels), ["Python (analysis)"]);
+        assert_eq!(kernels_for(&sys_prefix, &kernels), ["python3"]);
+        assert!(kernels_for(Path::new("/usr/bin/python3"), &kernels).is_empty());
+    }
+
+    #[test]
+    fn sanitizes_kernel_names() {
+        assert_eq!(kernel_name(".venv"), "venv");
+        assert_eq!(kernel_name("ML env (3.11)"), "ml-env--3.11-");
+        assert_eq!(kernel_name("py311-django42"), "py311-django42");
+        assert_eq!(kernel_name("."), "python3");
+    }
+
+    #[test]
+    fn leaves_bare_user_kernels_unresolved() {
+        let resource_dir = Path::new("/Users/dev/Library/Jupyter/kernels/python3");
+        assert_eq!(kernel_interpreter("python3", resource_dir), None);
+        assert_eq!(
+            kernel_interpreter("/opt/py/bin/python", resource_dir),
+            Some(PathBuf::from("/opt/py/bin/python"))
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How many entries the log keeps; older ones are dropped first.
+const LOG_CAPACITY: usize = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    pub(crate) fn parse(level: &str) -> Option<Self> {
+        match level.to_ascii_lowercase().as_str() {
+            "debug" => Some(Self::Debug),
+            "info" => Some(Self::Info),
+            "warn" | "warning" => Some(Self::Warn),
+            "error" => Some(Self::Error),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::Debug => "DEBUG",
+            Self::Info => "INFO",
+            Self::Warn => "WARN",
+            Self::Error => "ERROR",
+        })
+    }
+}
+
+/// One line of the log.
+#[derive(Clone, Debug)]
+pub(crate) struct LogEntry {
+    /// Milliseconds since the Unix epoch.
+    pub(crate) timestamp_ms: u128,
+    pub(crate) level: Level,
+    /// What logged it: a provider name, `discovery`, `command`, ...
+    pub(crate) tag: String,
+    pub(crate) message: String,
+}
+
+impl fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = self.timestamp_ms % 1000;
+        let secs = (self.timestamp_ms / 1000) % 86_400;
+        write!(
+            f,
+            "{:02}:{:02}:{:02}.{:03} {:<5} [{}] {}",
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60,
+            ms,
+            self.level,
+            self.tag,
+            self.message
+        )
+    }
+}
+
+/// A bounded buffer of log entries.
+struct LogBuffer {
+    entries: VecDeque<LogEntry>,
+    capacity: usize,
+}
+
+impl LogBuffer {
+    const fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    fn push(&mut self, entry: LogEntry) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// The last `limit` entries at `min_level` or above, optionally only those
+    /// tagged `tag`, oldest first.
+    fn recent(&self, limit: usize, min_level: Level, tag: Option<&str>) -> Vec<LogEntry> {
+        let mut entries: Vec<LogEntry> = self
+            .entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.level >= min_level)
+            .filter(|entry| tag.is_none_or(|tag| entry.tag.eq_ignore_ascii_case(tag)))
+            .take(limit)
+            .cloned()
+            .collect();
+        entries.reverse();
+        entries
+    }
+}
+
+static LOG: Mutex<LogBuffer> = Mutex::new(LogBuffer::new(LOG_CAPACITY));
+
+/// Appends an entry to the extension's log, which `/pyenvlog` shows.
+pub(crate) fn log(level: Level, tag: &str, message: impl Into<String>) {
+    let timestamp_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or_default();
+    if let Ok(mut log) = LOG.lock() {
+        log.push(LogEntry {
+            timestamp_ms,
+            level,
+            tag: tag.to_string(),
+            message: message.into(),
+        });
+    }
+}
+
+pub(crate) fn debug(tag: &str, message: impl Into<String>) {
+    log(Level::Debug, tag, message);
+}
+
+pub(crate) fn info(tag: &str, message: impl Into<String>) {
+    log(Level::Info, tag, message);
+}
+
+pub(crate) fn warn(tag: &str, message: impl Into<String>) {
+    log(Level::Warn, tag, message);
+}
+
+pub(crate) fn error(tag: &str, message: impl Into<String>) {
+    log(Level::Error, tag, message);
+}
+
+/// The last `limit` log entries at `min_level` or above, oldest first.
+pub(crate) fn recent(limit: usize, min_level: Level, tag: Option<&str>) -> Vec<LogEntry> {
+    LOG.lock()
+        .map(|log| log.recent(limit, min_level, tag))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(level: Level, tag: &str, message: &str) -> LogEntry {
+        LogEntry {
+            timestamp_ms: 45_296_789,
+            level,
+            tag: tag.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn keeps_the_most_recent_entries() {
+        let mut log = LogBuffer::new(3);
+        for message in ["one", "two", "three", "four"] {
+            log.push(entry(Level::Info, "discovery", message));
+        }
+        let messages: Vec<String> = log
+            .recent(10, Level::Debug, None)
+            .into_iter()
+            .map(|entry| entry.message)
+            .collect();
+        assert_eq!(messages, ["two", "three", "four"]);
+        assert_eq!(log.recent(1, Level::Debug, None)[0].message, "four");
+    }
+
+    #[test]
+    fn filters_by_level_and_tag() {
+        let mut log = LogBuffer::new(10);
+        log.push(entry(Level::Debug, "conda", "ran conda env list"));
+        log.push(entry(Level::Warn, "conda", "conda timed out"));
+        log.push(entry(Level::Error, "command", "/pyenvselect failed"));
+
+        assert_eq!(log.recent(10, Level::Warn, None).len(), 2);
+        let conda = log.recent(10, Level::Debug, Some("Conda"));
+        assert_eq!(conda.len(), 2);
+        assert_eq!(
+            conda[1].to_string(),
+            "12:34:56.789 WARN  [conda] conda timed out"
+        );
+    }
+}
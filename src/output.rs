@@ -0,0 +1,964 @@
+use crate::doctor::{CheckStatus, HealthCheck};
+use crate::environment::{
+    environment_executable, list_installed_packages, probe_interpreter, InstalledPackage,
+    PackageDiff, PythonEnvironment, PyvenvConfig,
+};
+use crate::error::PyEnvError;
+use crate::project::{check_requirements, read_project_requirements};
+use crate::pytest::PytestReport;
+use crate::selection::SelectedEnvironment;
+use crate::which::ModuleLocation;
+use crate::{Discovery, PythonEnvironmentSelectExtension, ScanStep};
+use std::path::Path;
+use std::process::Output;
+use std::time::Duration;
+use zed_extension_api::{
+    serde_json::json, SlashCommandOutput, SlashCommandOutputSection, Worktree,
+};
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Escapes `|` so `text` can sit in a Markdown table cell.
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+impl PythonEnvironmentSelectExtension {
+    fn provider_label(&self, provider: &str) -> &'static str {
+        self.providers
+            .iter()
+            .find(|p| p.name() == provider)
+            .map_or("Other", |p| p.label())
+    }
+
+    /// The environment `/pyenvcur` reports for `worktree` (or the project
+    /// directory `dir` inside it), with why it was picked when nobody
+    /// selected it, and the selection it stands in for if that one is gone.
+    fn current_environment(
+        &self,
+        worktree: Option<&Worktree>,
+        dir: Option<&Path>,
+    ) -> (
+        Option<(SelectedEnvironment, Option<&'static str>)>,
+        Option<SelectedEnvironment>,
+    ) {
+        let mut missing = None;
+        let selected = worktree.and_then(|worktree| match self.current_selection(worktree, dir) {
+            Some(selected) if selected.is_missing() => {
+                let replacement = self.replacement_selection(worktree, &selected);
+                missing = Some(selected);
+                replacement.map(|(selected, reason)| (selected, Some(reason)))
+            }
+            Some(selected) => Some((selected, None)),
+            None => self
+                .default_selection(worktree)
+                .map(|(selected, reason)| (selected, Some(reason))),
+        });
+        (selected, missing)
+    }
+
+    /// Renders `/pyenvcur --json`: the current environment in the format of
+    /// `.zed/python-environment.json`, plus why it was picked when it wasn't
+    /// selected (`default_reason`) and the vanished selection it replaces.
+    pub(crate) fn render_current_selection_json(
+        &self,
+        worktree: Option<&Worktree>,
+        dir: Option<&Path>,
+    ) -> Result<SlashCommandOutput, PyEnvError> {
+        let (selected, missing) = self.current_environment(worktree, dir);
+        let selected = selected.map(|(selected, default_reason)| {
+            let mut value = selected.to_json();
+            value["default_reason"] = json!(default_reason);
+            value
+        });
+        let text = serde_json::to_string_pretty(&json!({
+            "selected": selected,
+            "missing": missing.as_ref().map(SelectedEnvironment::to_json),
+        }))
+        .map_err(|e| PyEnvError::Io {
+            context: "Failed to serialize the selection".to_string(),
+            source: e.into(),
+        })?;
+        Ok(SlashCommandOutput {
+            sections: vec![SlashCommandOutputSection {
+                range: (0..text.len()).into(),
+                label: "Current Python Environment (JSON)".to_string(),
+            }],
+            text,
+        })
+    }
+
+    /// Renders `/pyenvpkgs --json`: the packages installed in `env`.
+    pub(crate) fn render_packages_json(
+        env: &SelectedEnvironment,
+        packages: &[InstalledPackage],
+    ) -> Result<SlashCommandOutput, PyEnvError> {
+        let packages: Vec<_> = packages
+            .iter()
+            .map(|package| json!({ "name": package.name, "version": package.version }))
+            .collect();
+        let text = serde_json::to_string_pretty(&json!({
+            "environment": env.to_json(),
+            "packages": packages,
+        }))
+        .map_err(|e| PyEnvError::Io {
+            context: "Failed to serialize packages".to_string(),
+            source: e.into(),
+        })?;
+        Ok(SlashCommandOutput {
+            sections: vec![SlashCommandOutputSection {
+                range: (0..text.len()).into(),
+                label: format!("Packages: {} (JSON)", env.name),
+            }],
+            text,
+        })
+    }
+
+    /// Renders the `/pyenvcur` report for `worktree`, or for the project
+    /// directory `dir` inside it.
+    pub(crate) fn describe_current_selection(
+        &self,
+        worktree: Option<&Worktree>,
+        dir: Option<&Path>,
+    ) -> String {
+        let (selected, missing) = self.current_environment(worktree, dir);
+
+        let mut text = String::new();
+        if let Some(missing) = &missing {
+            text.push_str(&format!(
+                "Warning: the selected environment {} no longer exists ({}).\n",
+                missing.name,
+                missing.python_path.display()
+            ));
+        }
+        let Some((selected, default_reason)) = selected else {
+            text.push_str(
+                "No environment selected for this worktree.\n\
+                 Run /pyenvlst to see available environments and \
+                 /pyenvselect <name> to choose one.",
+            );
+            return text;
+        };
+
+        if let (Some(reason), Some(_)) = (default_reason, &missing) {
+            text.push_str(&format!(
+                "Using {} instead; run /pyenvselect {} to keep it.\n",
+                reason, selected.name
+            ));
+        } else if let Some(reason) = default_reason {
+            text.push_str(&format!(
+                "No environment selected; automatically using {}.\n",
+                reason
+            ));
+        }
+        text.push_str(&format!(
+            "Name: {}\nInterpreter: {}\nVersion: {}\nProvider: {}",
+            selected.name,
+            selected.python_path.display(),
+            selected.version.as_deref().unwrap_or("unknown"),
+            if selected.provider.is_empty() {
+                "unknown"
+            } else {
+                &selected.provider
+            }
+        ));
+        if let Some(ruff_path) = environment_executable(&selected.python_path, "ruff") {
+            text.push_str(&format!("\nRuff: {}", ruff_path.display()));
+        }
+        if let Some(warning) =
+            worktree.and_then(|worktree| Self::version_pin_mismatch(worktree, &selected))
+        {
+            text.push_str(&format!("\nWarning: {}", warning));
+        }
+        if let Some(warning) =
+            worktree.and_then(|worktree| self.architecture_mismatch(worktree, &selected))
+        {
+            text.push_str(&format!("\nWarning: {}", warning));
+        }
+
+        let scoped = worktree.map_or_else(Vec::new, |worktree| self.scoped_selections(worktree));
+        if dir.is_none() && !scoped.is_empty() {
+            text.push_str("\n\nProject directories:");
+            for (scope, selected) in scoped {
+                text.push_str(&format!(
+                    "\n  {}: {} ({})",
+                    scope.display(),
+                    selected.name,
+                    selected.python_path.display()
+                ));
+            }
+        }
+        text
+    }
+
+    /// Renders the `/pyenvinfo` report: an overview section and a `sys.path` section.
+    /// `size` is only measured when asked for with `--size`.
+    pub(crate) fn render_environment_info(
+        env: &SelectedEnvironment,
+        size: Option<u64>,
+    ) -> SlashCommandOutput {
+        let info = probe_interpreter(&env.python_path);
+
+        let mut text = format!(
+            "Name: {}\nInterpreter: {}\nProvider: {}\n",
+            env.name,
+            env.python_path.display(),
+            env.provider
+        );
+        match &info {
+            Ok(info) => text.push_str(&format!(
+                "Version: {}\nImplementation: {}\nPrefix: {}\nBase prefix: {}\npip: {}\n",
+                info.version,
+                info.implementation,
+                info.prefix,
+                info.base_prefix,
+                info.pip_version.as_deref().unwrap_or("not installed")
+            )),
+            Err(error) => text.push_str(&format!("Interpreter failed to run: {}\n", error)),
+        }
+        if let Some(cfg) = PyvenvConfig::for_interpreter(&env.python_path) {
+            if let Some(home) = &cfg.home {
+                text.push_str(&format!("Base interpreter: {}\n", home.display()));
+            }
+            text.push_str(&format!(
+                "System site-packages: {}\n",
+                if cfg.include_system_site_packages {
+                    "included"
+                } else {
+                    "isolated"
+                }
+            ));
+        }
+        if let Some(size) = size {
+            text.push_str(&format!("Size on disk: {}\n", format_size(size)));
+        }
+
+        let mut sections = vec![SlashCommandOutputSection {
+            range: (0..text.len()).into(),
+            label: format!("Environment: {}", env.name),
+        }];
+
+        if let Ok(info) = &info {
+            text.push('\n');
+            let start = text.len();
+            text.push_str("sys.path:\n");
+            for entry in info.sys_path.iter().filter(|entry| !entry.is_empty()) {
+                text.push_str(&format!("  {}\n", entry));
+            }
+            sections.push(SlashCommandOutputSection {
+                range: (start..text.len()).into(),
+                label: "sys.path".to_string(),
+            });
+        }
+
+        SlashCommandOutput { text, sections }
+    }
+
+    /// Renders `/pyenvreqs`: declared dependencies that are missing from `env`
+    /// or installed at a version outside the declared range.
+    pub(crate) fn render_requirements_check(
+        worktree: &Worktree,
+        env: &SelectedEnvironment,
+    ) -> Result<SlashCommandOutput, PyEnvError> {
+        let requirements = read_project_requirements(|path| worktree.read_text_file(path).ok());
+        if requirements.is_empty() {
+            return Err(PyEnvError::DiscoveryFailed {
+                context: worktree.root_path(),
+                message: "no requirements.txt or [project] dependencies found".to_string(),
+            });
+        }
+
+        let installed = list_installed_packages(&env.python_path)?;
+        let check = check_requirements(
+            &requirements,
+            installed
+                .into_iter()
+                .map(|package| (package.name, package.version)),
+        );
+        let missing: Vec<String> = check
+            .missing
+            .iter()
+            .map(|(source, requirement)| {
+                format!(
+                    "{}{}    ({})",
+                    requirement.name, requirement.specifiers, source
+                )
+            })
+            .collect();
+        let mismatched: Vec<String> = check
+            .mismatched
+            .iter()
+            .map(|((source, requirement), version)| {
+                format!(
+                    "{}: installed {}, required {}    ({})",
+                    requirement.name, version, requirement.specifiers, source
+                )
+            })
+            .collect();
+
+        let mut text = format!(
+            "{} of {} requirements satisfied in {}\n",
+            check.satisfied,
+            requirements.len(),
+            env.name
+        );
+        let mut sections = vec![SlashCommandOutputSection {
+            range: (0..text.len()).into(),
+            label: format!("Requirements: {}", env.name),
+        }];
+
+        for (label, lines) in [("Missing", &missing), ("Version mismatches", &mismatched)] {
+            if lines.is_empty() {
+                continue;
+            }
+            text.push('\n');
+            let start = text.len();
+            text.push_str(&format!("{}:\n", label));
+            for line in lines.iter() {
+                text.push_str(&format!("  {}\n", line));
+            }
+            sections.push(SlashCommandOutputSection {
+                range: (start..text.len()).into(),
+                label: format!("{} ({})", label, lines.len()),
+            });
+        }
+
+        Ok(SlashCommandOutput { text, sections })
+    }
+
+    /// Renders the `/pyenvdoctor` report: one line per check, with the
+    /// suggested fix under each warning and failure.
+    pub(crate) fn render_health_report(
+        env: &SelectedEnvironment,
+        checks: &[HealthCheck],
+    ) -> SlashCommandOutput {
+        let mut text = format!(
+            "Health check for {} ({})\n\n",
+            env.name,
+            env.python_path.display()
+        );
+        for check in checks {
+            let marker = match check.status {
+                CheckStatus::Pass => "✓",
+                CheckStatus::Warn => "!",
+                CheckStatus::Fail => "✗",
+                CheckStatus::Skip => "-",
+            };
+            text.push_str(&format!("{} {}: {}\n", marker, check.name, check.detail));
+            if let Some(fix) = &check.fix {
+                text.push_str(&format!("    Fix: {}\n", fix));
+            }
+        }
+
+        let count = |status| checks.iter().filter(|check| check.status == status).count();
+        text.push_str(&format!(
+            "\n{} passed, {} warnings, {} failed",
+            count(CheckStatus::Pass),
+            count(CheckStatus::Warn),
+            count(CheckStatus::Fail)
+        ));
+
+        SlashCommandOutput {
+            sections: vec![SlashCommandOutputSection {
+                range: (0..text.len()).into(),
+                label: format!("Health check: {}", env.name),
+            }],
+            text,
+        }
+    }
+
+    /// Renders `/pyenvdiff`: the Python versions of both environments, then
+    /// the packages added, removed and changed going from `first` to `second`.
+    pub(crate) fn render_environment_diff(
+        first: &SelectedEnvironment,
+        second: &SelectedEnvironment,
+        diff: &PackageDiff,
+    ) -> SlashCommandOutput {
+        let version =
+            |env: &SelectedEnvironment| env.version.clone().unwrap_or("unknown".to_string());
+        let mut text = format!(
+            "{} → {}\nPython: {} → {}",
+            first.name,
+            second.name,
+            version(first),
+            version(second)
+        );
+        if first.version == second.version {
+            text.push_str(" (same)");
+        }
+        text.push('\n');
+
+        if diff.added.is_empty() && diff.removed.is_empty() && diff.changed.is_empty() {
+            text.push_str("\nBoth environments have the same packages.\n");
+        }
+        if !diff.added.is_empty() {
+            text.push_str(&format!(
+                "\nOnly in {} ({}):\n",
+                second.name,
+                diff.added.len()
+            ));
+            for package in &diff.added {
+                text.push_str(&format!("+ {} {}\n", package.name, package.version));
+            }
+        }
+        if !diff.removed.is_empty() {
+            text.push_str(&format!(
+                "\nOnly in {} ({}):\n",
+                first.name,
+                diff.removed.len()
+            ));
+            for package in &diff.removed {
+                text.push_str(&format!("- {} {}\n", package.name, package.version));
+            }
+        }
+        if !diff.changed.is_empty() {
+            text.push_str(&format!("\nDifferent versions ({}):\n", diff.changed.len()));
+            for (name, first_version, second_version) in &diff.changed {
+                text.push_str(&format!(
+                    "~ {} {} → {}\n",
+                    name, first_version, second_version
+                ));
+            }
+        }
+
+        SlashCommandOutput {
+            sections: vec![SlashCommandOutputSection {
+                range: (0..text.len()).into(),
+                label: format!("Diff: {} → {}", first.name, second.name),
+            }],
+            text,
+        }
+    }
+
+    /// Renders a `/pyenvsync` run: the command, then what it installed,
+    /// removed and upgraded.
+    pub(crate) fn render_sync_report(
+        env: &SelectedEnvironment,
+        manifest: &str,
+        invocation: &str,
+        diff: &PackageDiff,
+    ) -> SlashCommandOutput {
+        let mut text = format!(
+            "$ {}\nEnvironment: {} ({})\nSynced from {}\n",
+            invocation,
+            env.name,
+            env.python_path.display(),
+            manifest
+        );
+        if diff.added.is_empty() && diff.removed.is_empty() && diff.changed.is_empty() {
+            text.push_str("\nAlready in sync; nothing changed.\n");
+        }
+        for (heading, sign, packages) in [
+            ("Installed", '+', &diff.added),
+            ("Removed", '-', &diff.removed),
+        ] {
+            if packages.is_empty() {
+                continue;
+            }
+            text.push_str(&format!("\n{} ({}):\n", heading, packages.len()));
+            for package in packages {
+                text.push_str(&format!("{} {} {}\n", sign, package.name, package.version));
+            }
+        }
+        if !diff.changed.is_empty() {
+            text.push_str(&format!("\nChanged versions ({}):\n", diff.changed.len()));
+            for (name, before, after) in &diff.changed {
+                text.push_str(&format!("~ {} {} → {}\n", name, before, after));
+            }
+        }
+
+        SlashCommandOutput {
+            sections: vec![SlashCommandOutputSection {
+                range: (0..text.len()).into(),
+                label: format!("Sync: {}", env.name),
+            }],
+            text,
+        }
+    }
+
+    /// Renders a `/pyenvrun` invocation: its exit status, then stdout and
+    /// stderr in separate sections.
+    pub(crate) fn render_run_output(
+        env: &SelectedEnvironment,
+        invocation: &str,
+        output: &Output,
+    ) -> SlashCommandOutput {
+        let mut text = format!(
+            "$ {} {}\nEnvironment: {} ({})\nExit status: {}\n",
+            env.python_path.display(),
+            invocation,
+            env.name,
+            env.python_path.display(),
+            output.status
+        );
+        let mut sections = vec![SlashCommandOutputSection {
+            range: (0..text.len()).into(),
+            label: format!("Run: {}", env.name),
+        }];
+
+        for (label, bytes) in [("stdout", &output.stdout), ("stderr", &output.stderr)] {
+            let stream = String::from_utf8_lossy(bytes);
+            if stream.trim().is_empty() {
+                continue;
+            }
+            text.push('\n');
+            let start = text.len();
+            text.push_str(&format!("```\n{}\n```", stream.trim_end()));
+            sections.push(SlashCommandOutputSection {
+                range: (start..text.len()).into(),
+                label: label.to_string(),
+            });
+            text.push('\n');
+        }
+
+        SlashCommandOutput { text, sections }
+    }
+
+    /// Renders `/pyenvwhich`: where a module is imported from in `env`, the
+    /// distribution providing it, and the console script of that name.
+    pub(crate) fn render_which(
+        env: &SelectedEnvironment,
+        location: &ModuleLocation,
+        script: Option<&Path>,
+        root_path: &Path,
+    ) -> SlashCommandOutput {
+        let name = &location.name;
+        let mut text = format!("{} in {} ({})\n", name, env.name, env.python_path.display());
+        match &location.resolved {
+            Some(resolved) => text.push_str(&format!("Imports from: {}\n", resolved)),
+            None => text.push_str("Module: not importable\n"),
+        }
+        if let Some(distribution) = &location.distribution {
+            text.push_str(&format!(
+                "Distribution: {} {}\n",
+                distribution,
+                location.version.as_deref().unwrap_or("")
+            ));
+        }
+        if let Some(script) = script {
+            text.push_str(&format!("Executable: {}\n", script.display()));
+        }
+        if location.shadows_installed(root_path) {
+            text.push_str(&format!(
+                "Warning: the worktree's {} shadows the installed one ({}) in code run from the worktree root; rename it to import the package.\n",
+                location.resolved.as_deref().unwrap_or_default(),
+                location.installed.as_deref().unwrap_or_default()
+            ));
+        }
+        SlashCommandOutput {
+            sections: vec![SlashCommandOutputSection {
+                range: (0..text.len()).into(),
+                label: format!("Which: {}", name),
+            }],
+            text,
+        }
+    }
+
+    /// Renders a `/pyenvtest` run: a summary, the failing tests or collected
+    /// node ids, and the tail of pytest's output, each in its own section.
+    pub(crate) fn render_pytest_report(
+        env: &SelectedEnvironment,
+        report: &PytestReport,
+    ) -> SlashCommandOutput {
+        let mut text = String::new();
+        let mut sections = Vec::new();
+        let mut push_section = |text: &mut String, label: String, body: String| {
+            let start = text.len();
+            text.push_str(&body);
+            sections.push(SlashCommandOutputSection {
+                range: (start..text.len()).into(),
+                label,
+            });
+            text.push('\n');
+        };
+
+        let mut summary = format!(
+            "pytest in {} ({}): {}\n",
+            env.name,
+            env.python_path.display(),
+            report.outcome()
+        );
+        if let Some(line) = &report.summary {
+            summary.push_str(&format!("{}\n", line));
+        }
+        push_section(&mut text, format!("pytest: {}", env.name), summary);
+
+        if !report.failures.is_empty() {
+            push_section(
+                &mut text,
+                format!("Failures ({})", report.failures.len()),
+                report.failures.join("\n") + "\n",
+            );
+        }
+        if !report.collected.is_empty() {
+            push_section(
+                &mut text,
+                format!("Collected tests ({})", report.collected.len()),
+                report.collected.join("\n") + "\n",
+            );
+        }
+        push_section(
+            &mut text,
+            "pytest output".to_string(),
+            format!("```\n{}\n```\n", report.output),
+        );
+
+        SlashCommandOutput { text, sections }
+    }
+
+    /// Renders `/pyenvlst --by-size`: every environment in one table, largest
+    /// first, as ordered in `environments`.
+    pub(crate) fn render_size_list(
+        environments: &[(&PythonEnvironment, Option<u64>)],
+    ) -> SlashCommandOutput {
+        let mut text = String::from("| # | Name | Size | Provider | Path |\n");
+        text.push_str("|--:|------|-----:|----------|------|\n");
+        for (index, (env, size)) in environments.iter().enumerate() {
+            text.push_str(&format!(
+                "| {} | {} | {} | {} | `{}` |\n",
+                index + 1,
+                markdown_cell(&env.name),
+                size.map_or("-".to_string(), format_size),
+                env.provider,
+                markdown_cell(&env.python_path.to_string_lossy()),
+            ));
+        }
+        let total: u64 = environments.iter().filter_map(|(_, size)| *size).sum();
+        text.push_str(&format!(
+            "\nTotal: {} across {} environments (shared installs such as system Python are not measured)\n",
+            format_size(total),
+            environments.iter().filter(|(_, size)| size.is_some()).count()
+        ));
+
+        SlashCommandOutput {
+            sections: vec![SlashCommandOutputSection {
+                range: (0..text.len()).into(),
+                label: "Python Environments by Size".to_string(),
+            }],
+            text,
+        }
+    }
+
+    /// One line naming how long discovery took and its slowest steps, so a
+    /// listing that took a while shows where the time went. Slash command
+    /// output can't be streamed, so this comes with the finished listing.
+    fn timing_summary(discovery: &Discovery) -> Option<String> {
+        /// Steps faster than this aren't worth naming.
+        const NOTABLE: Duration = Duration::from_millis(100);
+        if discovery.steps.is_empty() {
+            return None;
+        }
+
+        let mut slowest: Vec<&ScanStep> = discovery
+            .steps
+            .iter()
+            .filter(|step| step.elapsed >= NOTABLE)
+            .collect();
+        slowest.sort_by_key(|step| std::cmp::Reverse(step.elapsed));
+        let mut summary = format!("Discovery took {:.2}s", discovery.elapsed.as_secs_f64());
+        if !slowest.is_empty() {
+            let steps: Vec<String> = slowest
+                .iter()
+                .take(5)
+                .map(|step| format!("{} {:.2}s", step.name, step.elapsed.as_secs_f64()))
+                .collect();
+            summary.push_str(&format!(" (slowest: {})", steps.join(", ")));
+        }
+        if discovery.from_cache {
+            summary.push_str("; cached, /pyenvrefresh rescans");
+        }
+        Some(summary)
+    }
+
+    /// Appends the `/pyenvlst --verbose` diagnostics to `output`: how long
+    /// each scan step took and what it found, how far the worktree scan
+    /// reached, and every tool the providers ran with its exit status.
+    pub(crate) fn append_diagnostics(output: &mut SlashCommandOutput, discovery: &Discovery) {
+        let text = &mut output.text;
+        text.push_str("\n\n");
+        let start = text.len();
+        text.push_str("### Diagnostics\n\n");
+        if discovery.from_cache {
+            text.push_str("From a cached scan; run /pyenvrefresh first for fresh numbers.\n\n");
+        }
+        text.push_str("| Step | Time | Result |\n|------|-----:|--------|\n");
+        for step in &discovery.steps {
+            text.push_str(&format!(
+                "| {} | {:.2}s | {} |\n",
+                step.name,
+                step.elapsed.as_secs_f64(),
+                markdown_cell(&step.outcome)
+            ));
+        }
+        text.push_str(&format!(
+            "\nWorktree scan: {} directories read\n",
+            discovery.dirs_scanned
+        ));
+
+        if discovery.commands.is_empty() {
+            text.push_str("\nNo external tools were run.\n");
+        } else {
+            text.push_str("\nCommands:\n");
+            for run in &discovery.commands {
+                text.push_str(&format!(
+                    "- `{}` → {} in {:.2}s\n",
+                    run.command_line,
+                    run.outcome.lines().next().unwrap_or_default(),
+                    run.elapsed.as_secs_f64()
+                ));
+            }
+        }
+
+        output.sections.push(SlashCommandOutputSection {
+            range: (start..text.len()).into(),
+            label: "Diagnostics".to_string(),
+        });
+    }
+
+    /// Renders `/pyenvrecent`: the worktree's previous selections, most
+    /// recent first, marking those discovery no longer finds.
+    pub(crate) fn render_selection_history(
+        history: &[SelectedEnvironment],
+        environments: &[PythonEnvironment],
+    ) -> SlashCommandOutput {
+        let mut text = String::from("### Recent selections\n\n");
+        if history.is_empty() {
+            text.push_str("Nothing selected for this worktree yet; use /pyenvselect.\n");
+        } else {
+            text.push_str("| # | Name | Version | Path | Notes |\n");
+            text.push_str("|--:|------|---------|------|-------|\n");
+            for (index, selected) in history.iter().enumerate() {
+                let mut notes = Vec::new();
+                if index == 0 {
+                    notes.push("current");
+                }
+                if !environments
+                    .iter()
+                    .any(|env| env.python_path == selected.python_path)
+                {
+                    notes.push("no longer exists");
+                }
+                text.push_str(&format!(
+                    "| {} | {} | {} | `{}` | {} |\n",
+                    index + 1,
+                    markdown_cell(&selected.name),
+                    selected.version.as_deref().unwrap_or("unknown"),
+                    selected.python_path.display(),
+                    notes.join(", ")
+                ));
+            }
+            text.push_str("\nRun /pyenvrecent <#> to select one again.\n");
+        }
+
+        SlashCommandOutput {
+            sections: vec![SlashCommandOutputSection {
+                range: (0..text.len()).into(),
+                label: "Recent Python Environments".to_string(),
+            }],
+            text,
+        }
+    }
+
+    /// Renders `/pyenvlst` as one Markdown table per provider, numbered
+    /// continuously so the numbers work with `/pyenvselect <index>`.
+    pub(crate) fn render_environment_list(
+        &self,
+        discovery: &Discovery,
+        selected: Option<&SelectedEnvironment>,
+    ) -> SlashCommandOutput {
+        let all_envs = &discovery.environments;
+        let mut text = String::new();
+        let mut sections = Vec::new();
+        let mut index = 0;
+
+        // Project-local environments come first, and within each half
+        // providers run in order, so a provider's environments are contiguous.
+        // Environments of other worktrees follow, grouped by worktree.
+        for group in all_envs.chunk_by(|a, b| {
+            a.provider == b.provider
+                && a.project_local == b.project_local
+                && a.worktree == b.worktree
+        }) {
+            let label = if let Some(worktree) = &group[0].worktree {
+                format!("{} ({})", self.provider_label(&group[0].provider), worktree)
+            } else if group[0].project_local {
+                format!(
+                    "{} (project-local)",
+                    self.provider_label(&group[0].provider)
+                )
+            } else {
+                self.provider_label(&group[0].provider).to_string()
+            };
+            let start = text.len();
+            text.push_str(&format!("### {}\n\n", label));
+            text.push_str("| # | Name | Version | Provider | Path | Selected | Notes |\n");
+            text.push_str("|--:|------|---------|----------|------|:--------:|-------|\n");
+
+            for env in group {
+                index += 1;
+                let providers = std::iter::once(&env.provider)
+                    .chain(&env.also_found_by)
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let is_selected =
+                    selected.is_some_and(|selected| selected.python_path == env.python_path);
+
+                let mut notes = Vec::new();
+                if all_envs
+                    .iter()
+                    .filter(|other| other.name == env.name)
+                    .count()
+                    > 1
+                {
+                    notes.push(format!("id: {}", env.id));
+                }
+                if let Some(project) = &env.project {
+                    notes.push(format!("project: {}", project));
+                }
+                if let Some(guest) = &env.guest {
+                    notes.push(format!("in {}", guest));
+                }
+                if !env.factors.is_empty() {
+                    notes.push(format!("factors: {}", env.factors.join(", ")));
+                }
+                if let Some(session) = &env.session {
+                    notes.push(format!("session: {}", session));
+                }
+                if !env.kernels.is_empty() {
+                    notes.push(format!("kernel: {}", env.kernels.join(", ")));
+                }
+                if let Some(file) = &env.stale {
+                    notes.push(format!("stale — re-sync needed ({} changed)", file));
+                }
+                if env.project_env {
+                    notes.push("project env".to_string());
+                } else if env.project_local {
+                    notes.push("project-local".to_string());
+                }
+                if env.suggested {
+                    notes.push("suggested".to_string());
+                }
+                if env.active {
+                    notes.push("active in launching shell".to_string());
+                }
+                if env.broken.is_some() {
+                    notes.push("broken".to_string());
+                }
+                if discovery.violates_requires_python(env) {
+                    notes.push(format!(
+                        "outside requires-python {}",
+                        discovery.requires_python.as_deref().unwrap_or_default()
+                    ));
+                }
+
+                let name = if env.pinned {
+                    format!("{} (pinned)", env.name)
+                } else {
+                    env.name.clone()
+                };
+                let version = env.version.as_deref().unwrap_or("?");
+                let version = match &env.flavor {
+                    Some(flavor) => format!("{} ({})", version, flavor.label()),
+                    None => version.to_string(),
+                };
+                text.push_str(&format!(
+                    "| {} | {} | {} | {} | `{}` | {} | {} |\n",
+                    index,
+                    markdown_cell(&name),
+                    markdown_cell(&version),
+                    providers,
+                    markdown_cell(&env.python_path.to_string_lossy()),
+                    if is_selected { "✓" } else { "" },
+                    markdown_cell(&notes.join(", ")),
+                ));
+            }
+
+            sections.push(SlashCommandOutputSection {
+                range: (start..text.len()).into(),
+                label: label.to_string(),
+            });
+            text.push('\n');
+        }
+
+        if !discovery.warnings.is_empty() {
+            let start = text.len();
+            text.push_str("### Warnings\n\n");
+            for warning in &discovery.warnings {
+                text.push_str(&format!("- {}\n", warning));
+            }
+            sections.push(SlashCommandOutputSection {
+                range: (start..text.len()).into(),
+                label: format!("Warnings ({})", discovery.warnings.len()),
+            });
+            text.push('\n');
+        }
+
+        text.push_str(&format!("{} environments", all_envs.len()));
+        if let Some(timing) = Self::timing_summary(discovery) {
+            text.push_str(&format!("\n{}", timing));
+        }
+
+        SlashCommandOutput { text, sections }
+    }
+
+    /// Renders `/pyenvlst --json`: the environments as a JSON array, in the
+    /// same order (and so with the same indices) as the text listing.
+    pub(crate) fn render_environment_json(
+        discovery: &Discovery,
+        selected: Option<&SelectedEnvironment>,
+    ) -> Result<SlashCommandOutput, PyEnvError> {
+        let environments: Vec<_> = discovery
+            .environments
+            .iter()
+            .map(|env| {
+                json!({
+                    "id": env.id,
+                    "name": env.name,
+                    "path": env.python_path.to_string_lossy(),
+                    "version": env.version,
+                    "provider": env.provider,
+                    "active": env.active,
+                    "selected": selected.is_some_and(|selected| selected.python_path == env.python_path),
+                    "broken": env.broken.is_some(),
+                    "kernels": env.kernels,
+                    "stale": env.stale,
+                    "project_env": env.project_env,
+                    "project_local": env.project_local,
+                    "pinned": env.pinned,
+                    "worktree": env.worktree,
+                    "guest": env.guest.as_ref().map(ToString::to_string),
+                    "implementation": env.flavor.as_ref().map(|flavor| &flavor.implementation),
+                    "distribution": env.flavor.as_ref().and_then(|flavor| flavor.distribution.as_ref()),
+                    "architecture": env.flavor.as_ref().and_then(|flavor| flavor.machine.as_ref()),
+                    "satisfies_requires_python": !discovery.violates_requires_python(env),
+                })
+            })
+            .collect();
+
+        let text = serde_json::to_string_pretty(&environments).map_err(|e| PyEnvError::Io {
+            context: "Failed to serialize environments".to_string(),
+            source: e.into(),
+        })?;
+        Ok(SlashCommandOutput {
+            sections: vec![SlashCommandOutputSection {
+                range: (0..text.len()).into(),
+                label: "Python Environments (JSON)".to_string(),
+            }],
+            text,
+        })
+    }
+}
@@ -0,0 +1,180 @@
+use crate::error::PyEnvError;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Applies `f` to every item, each on its own thread. Items whose thread
+/// can't be spawned (e.g. on targets without thread support) are processed
+/// on the calling thread instead.
+pub(crate) fn parallel_map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let f = &f;
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .iter()
+            .map(|item| {
+                std::thread::Builder::new()
+                    .spawn_scoped(scope, move || f(item))
+                    .map_err(|_| item)
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| match handle {
+                Ok(handle) => handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic)),
+                Err(item) => f(item),
+            })
+            .collect()
+    })
+}
+
+/// Display name of the program `command` runs, for error messages.
+fn program_name(command: &Command) -> String {
+    Path::new(command.get_program())
+        .file_name()
+        .unwrap_or(command.get_program())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// `command` as it would be typed in a shell, for diagnostics.
+pub(crate) fn command_line(command: &Command) -> String {
+    std::iter::once(program_name(command))
+        .chain(command.get_args().map(|arg| {
+            let arg = arg.to_string_lossy();
+            if arg.contains(' ') {
+                format!("\"{}\"", arg)
+            } else {
+                arg.into_owned()
+            }
+        }))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Runs `command` and collects its output, giving up after
+/// [`COMMAND_TIMEOUT`]. Every subprocess the extension starts goes through
+/// here or [`output_with_timeout`]: zed_extension_api 0.1.0 has no process
+/// API, so these are the only places to change once the extension can move
+/// to a version that does.
+pub(crate) fn run_command(command: &mut Command) -> Result<Output, PyEnvError> {
+    output_with_timeout(command, COMMAND_TIMEOUT)
+}
+
+/// How long an external tool (conda, poetry, pip, ...) may run.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(20);
+/// How long commands that may hit the network (pip index queries, creating
+/// an environment that downloads an interpreter) may run.
+pub(crate) const NETWORK_COMMAND_TIMEOUT: Duration = Duration::from_secs(300);
+/// How long an interpreter gets to start during validation.
+pub(crate) const VALIDATION_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Runs `command` to completion, killing it if it is still running after
+/// `timeout`. Output goes to temporary files rather than pipes, so a chatty
+/// command can't fill a pipe and block while we wait for it to exit.
+pub(crate) fn output_with_timeout(
+    command: &mut Command,
+    timeout: Duration,
+) -> Result<Output, PyEnvError> {
+    static NEXT_CAPTURE: AtomicUsize = AtomicUsize::new(0);
+    let capture = std::env::temp_dir().join(format!(
+        "pyenvselect-{}-{}",
+        std::process::id(),
+        NEXT_CAPTURE.fetch_add(1, Ordering::Relaxed)
+    ));
+    let stdout_path = capture.with_extension("out");
+    let stderr_path = capture.with_extension("err");
+    let result = run_captured(command, timeout, &stdout_path, &stderr_path);
+    let _ = fs::remove_file(&stdout_path);
+    let _ = fs::remove_file(&stderr_path);
+    result
+}
+
+fn run_captured(
+    command: &mut Command,
+    timeout: Duration,
+    stdout_path: &Path,
+    stderr_path: &Path,
+) -> Result<Output, PyEnvError> {
+    let create = |path: &Path| {
+        fs::File::create(path).map_err(PyEnvError::io(format!(
+            "Failed to create {}",
+            path.display()
+        )))
+    };
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(create(stdout_path)?)
+        .stderr(create(stderr_path)?)
+        .spawn()
+        .map_err(PyEnvError::io(format!(
+            "Failed to run {}",
+            program_name(command)
+        )))?;
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(PyEnvError::DiscoveryFailed {
+                    context: program_name(command),
+                    message: format!("timed out after {}s", timeout.as_secs()),
+                });
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(10)),
+            Err(e) => {
+                return Err(PyEnvError::io(format!(
+                    "Failed to wait for {}",
+                    program_name(command)
+                ))(e))
+            }
+        }
+    };
+
+    let read = |path: &Path| {
+        fs::read(path).map_err(PyEnvError::io(format!(
+            "Failed to read output of {}",
+            program_name(command)
+        )))
+    };
+    Ok(Output {
+        status,
+        stdout: read(stdout_path)?,
+        stderr: read(stderr_path)?,
+    })
+}
+
+/// Runs `command` and returns its stdout, treating a non-zero exit as an error.
+pub(crate) fn command_stdout(command: &mut Command) -> Result<String, PyEnvError> {
+    command_stdout_with_timeout(command, COMMAND_TIMEOUT)
+}
+
+pub(crate) fn command_stdout_with_timeout(
+    command: &mut Command,
+    timeout: Duration,
+) -> Result<String, PyEnvError> {
+    let output = output_with_timeout(command, timeout)?;
+    successful_stdout(command, output)
+}
+
+/// The stdout of a finished `command`, treating a non-zero exit as an error.
+pub(crate) fn successful_stdout(command: &Command, output: Output) -> Result<String, PyEnvError> {
+    if !output.status.success() {
+        return Err(PyEnvError::DiscoveryFailed {
+            context: program_name(command),
+            message: format!(
+                "exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,589 @@
+use std::collections::HashMap;
+
+/// Reads a string value from a TOML table without pulling in a full TOML
+/// parser. Only handles the simple `key = "value"` form.
+pub(crate) fn read_toml_string(contents: &str, table: &str, key: &str) -> Option<String> {
+    let header = format!("[{}]", table);
+    let mut in_table = false;
+
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_table = line == header;
+            continue;
+        }
+        if !in_table {
+            continue;
+        }
+        if let Some((name, value)) = line.split_once('=') {
+            if name.trim() == key {
+                let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+                return Some(value.to_string());
+            }
+        }
+    }
+    None
+}
+
+/// The interpreter constraint of a `pyproject.toml`, e.g. `>=3.11`.
+pub(crate) fn read_requires_python(pyproject: &str) -> Option<String> {
+    read_toml_string(pyproject, "project", "requires-python")
+}
+
+/// Reads an array of strings (possibly spanning several lines) from a TOML
+/// table, e.g. `[project] dependencies = ["requests>=2", ...]`.
+fn read_toml_string_array(contents: &str, table: &str, key: &str) -> Option<Vec<String>> {
+    let header = format!("[{}]", table);
+    let mut in_table = false;
+    let mut lines = contents.lines();
+
+    while let Some(line) = lines.next() {
+        let line = line.trim();
+        if line.starts_with('[') && !line.starts_with("[[") && in_table != (line == header) {
+            in_table = line == header;
+            continue;
+        }
+        if !in_table {
+            continue;
+        }
+        let Some((name, value)) = line.split_once('=') else {
+            continue;
+        };
+        if name.trim() != key {
+            continue;
+        }
+
+        let mut array = value.trim().to_string();
+        while !array.contains(']') {
+            match lines.next() {
+                Some(next) => {
+                    array.push_str(next.split('#').next().unwrap_or(""));
+                }
+                None => break,
+            }
+        }
+        let inner = array.trim().trim_start_matches('[');
+        let inner = inner.split(']').next().unwrap_or("");
+        return Some(
+            inner
+                .split(',')
+                .map(|item| {
+                    item.trim()
+                        .trim_matches(|c| c == '"' || c == '\'')
+                        .to_string()
+                })
+                .filter(|item| !item.is_empty())
+                .collect(),
+        );
+    }
+    None
+}
+
+/// Returns the version names listed in a `.python-version` file.
+pub(crate) fn parse_python_version_file(contents: &str) -> Option<Vec<String>> {
+    let versions: Vec<String> = contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or("").trim())
+        .filter(|line| !line.is_empty())
+        .flat_map(|line| line.split_whitespace().map(str::to_string))
+        .collect();
+    if versions.is_empty() {
+        None
+    } else {
+        Some(versions)
+    }
+}
+
+/// The interpreter versions a worktree pins with pyenv's `.python-version`
+/// or, failing that, the `python` line of asdf/mise's `.tool-versions`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct VersionPin {
+    /// The file the pin was read from.
+    pub(crate) source: &'static str,
+    /// Versions (or pyenv environment names) in order of preference.
+    pub(crate) versions: Vec<String>,
+}
+
+impl VersionPin {
+    /// Reads the pin, with `read` returning a worktree file's contents.
+    pub(crate) fn read(read: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        if let Some(versions) = read(".python-version")
+            .as_deref()
+            .and_then(parse_python_version_file)
+        {
+            return Some(Self {
+                source: ".python-version",
+                versions,
+            });
+        }
+        let versions = read(".tool-versions")
+            .and_then(|contents| parse_tool_versions(&contents, "python"))
+            .filter(|versions| !versions.is_empty())?;
+        Some(Self {
+            source: ".tool-versions",
+            versions,
+        })
+    }
+
+    /// Whether an environment called `name` running Python `version`
+    /// satisfies any of the pinned entries.
+    pub(crate) fn allows(&self, name: &str, version: &str) -> bool {
+        self.versions
+            .iter()
+            .any(|pin| pin == name || version_matches_pin(version, pin))
+    }
+}
+
+/// Returns the versions pinned for `tool` in a `.tool-versions` file
+/// (asdf/mise format: `python 3.12.1 3.11.7`).
+pub(crate) fn parse_tool_versions(contents: &str, tool: &str) -> Option<Vec<String>> {
+    contents.lines().find_map(|line| {
+        let line = line.split('#').next().unwrap_or("");
+        let mut parts = line.split_whitespace();
+        if parts.next()? != tool {
+            return None;
+        }
+        Some(parts.map(str::to_string).collect())
+    })
+}
+
+/// The environment name a conda `environment.yml` declares with its
+/// top-level `name:` key.
+pub(crate) fn read_environment_yml_name(contents: &str) -> Option<String> {
+    contents.lines().find_map(|line| {
+        let value = line.strip_prefix("name:")?;
+        let value = value.split(" #").next().unwrap_or("").trim();
+        let value = value.trim_matches(|c| c == '"' || c == '\'');
+        Some(value.to_string()).filter(|value| !value.is_empty())
+    })
+}
+
+/// The committed file that pins a worktree's environment, written by
+/// `/pyenvselect --pin`.
+pub(crate) const PIN_FILE: &str = ".zed-python-env";
+
+/// The environment a [`PIN_FILE`] names: its first line that isn't blank or
+/// a `#` comment.
+pub(crate) fn parse_pin_file(contents: &str) -> Option<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+}
+
+/// The environment pinned in `pyproject.toml` with
+/// `[tool.zed-pyenv] environment = "..."`.
+pub(crate) fn read_pyproject_pin(pyproject: &str) -> Option<String> {
+    read_toml_string(pyproject, "tool.zed-pyenv", "environment").filter(|pin| !pin.is_empty())
+}
+
+/// Normalizes a distribution name per PEP 503 (`Foo_Bar.baz` -> `foo-bar-baz`).
+pub(crate) fn normalize_package_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    for c in name.chars() {
+        if matches!(c, '-' | '_' | '.') {
+            if !normalized.ends_with('-') {
+                normalized.push('-');
+            }
+        } else {
+            normalized.push(c.to_ascii_lowercase());
+        }
+    }
+    normalized
+}
+
+/// Splits the numeric release part of a version (`3.11.4rc1` -> `[3, 11, 4]`).
+fn release_numbers(version: &str) -> Vec<u64> {
+    version
+        .trim()
+        .trim_start_matches('v')
+        .split('.')
+        .map_while(|part| {
+            let digits: String = part.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse().ok()
+        })
+        .collect()
+}
+
+fn compare_versions(a: &[u64], b: &[u64]) -> std::cmp::Ordering {
+    let len = a.len().max(b.len());
+    (0..len)
+        .map(|i| a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0)))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(std::cmp::Ordering::Equal)
+}
+
+/// Checks `version` against a comma-separated PEP 440 specifier set such as
+/// `>=3.9,<4` or `~=1.4.2`. Only release numbers are compared; pre-release
+/// and local version labels are ignored.
+pub(crate) fn version_satisfies(version: &str, specifiers: &str) -> bool {
+    use std::cmp::Ordering;
+
+    let version = release_numbers(version);
+    specifiers
+        .split(',')
+        .map(str::trim)
+        .filter(|spec| !spec.is_empty())
+        .all(|spec| {
+            let operator_len = spec
+                .find(|c: char| c.is_ascii_digit() || c == '*' || c == 'v')
+                .unwrap_or(spec.len());
+            let (operator, target) = spec.split_at(operator_len);
+            let operator = operator.trim();
+            let target = target.trim();
+
+            if let Some(prefix) = target.strip_suffix(".*") {
+                let prefix = release_numbers(prefix);
+                let matches = version.len() >= prefix.len() && version[..prefix.len()] == prefix;
+                return if operator == "!=" { !matches } else { matches };
+            }
+
+            let target = release_numbers(target);
+            let ordering = compare_versions(&version, &target);
+            match operator {
+                "==" | "===" | "" => ordering == Ordering::Equal,
+                "!=" => ordering != Ordering::Equal,
+                ">=" => ordering != Ordering::Less,
+                "<=" => ordering != Ordering::Greater,
+                ">" => ordering == Ordering::Greater,
+                "<" => ordering == Ordering::Less,
+                "~=" => {
+                    // ~=1.4.2 means >=1.4.2,==1.4.*
+                    let prefix = &target[..target.len().saturating_sub(1).max(1)];
+                    ordering != Ordering::Less
+                        && version.len() >= prefix.len()
+                        && version[..prefix.len()] == *prefix
+                }
+                _ => true,
+            }
+        })
+}
+
+/// A dependency declared by the project.
+pub(crate) struct Requirement {
+    pub(crate) name: String,
+    /// PEP 440 specifier set, empty when any version is acceptable.
+    pub(crate) specifiers: String,
+}
+
+impl Requirement {
+    /// Parses a PEP 508 requirement such as `requests[socks]>=2.31; python_version>"3.8"`.
+    /// Returns `None` for URLs, editable installs and pip options.
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.split(" #").next().unwrap_or("").trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with('-') || line.contains("://")
+        {
+            return None;
+        }
+        let line = line.split(';').next().unwrap_or("").trim();
+        let name_end = line
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+            .unwrap_or(line.len());
+        let (name, rest) = line.split_at(name_end);
+        if name.is_empty() {
+            return None;
+        }
+        let rest = match rest.trim_start().strip_prefix('[') {
+            Some(rest) => rest.split_once(']').map_or("", |(_, rest)| rest),
+            None => rest,
+        };
+        let specifiers = rest
+            .trim()
+            .trim_start_matches('(')
+            .trim_end_matches(')')
+            .replace(' ', "");
+        Some(Self {
+            name: name.to_string(),
+            specifiers,
+        })
+    }
+}
+
+/// Collects the worktree's declared dependencies from `requirements.txt` and
+/// `[project] dependencies` in `pyproject.toml`, with the file each came from.
+pub(crate) fn read_project_requirements(
+    read_file: impl Fn(&str) -> Option<String>,
+) -> Vec<(String, Requirement)> {
+    let mut requirements = Vec::new();
+
+    if let Some(contents) = read_file("requirements.txt") {
+        requirements.extend(
+            contents
+                .lines()
+                .filter_map(Requirement::parse)
+                .map(|requirement| ("requirements.txt".to_string(), requirement)),
+        );
+    }
+    if let Some(contents) = read_file("pyproject.toml") {
+        if let Some(dependencies) = read_toml_string_array(&contents, "project", "dependencies") {
+            requirements.extend(
+                dependencies
+                    .iter()
+                    .filter_map(|dependency| Requirement::parse(dependency))
+                    .map(|requirement| ("pyproject.toml".to_string(), requirement)),
+            );
+        }
+    }
+
+    requirements
+}
+
+/// Declared requirements sorted by whether the installed packages meet them.
+pub(crate) struct RequirementsCheck<'a> {
+    pub(crate) satisfied: usize,
+    /// Requirements with no installed distribution, with the file declaring them.
+    pub(crate) missing: Vec<&'a (String, Requirement)>,
+    /// Requirements installed at a version outside the declared range, with
+    /// the installed version.
+    pub(crate) mismatched: Vec<(&'a (String, Requirement), String)>,
+}
+
+/// Compares `requirements` against `installed` `(name, version)` pairs.
+pub(crate) fn check_requirements(
+    requirements: &[(String, Requirement)],
+    installed: impl IntoIterator<Item = (String, String)>,
+) -> RequirementsCheck<'_> {
+    let installed: HashMap<String, String> = installed
+        .into_iter()
+        .map(|(name, version)| (normalize_package_name(&name), version))
+        .collect();
+
+    let mut check = RequirementsCheck {
+        satisfied: 0,
+        missing: Vec::new(),
+        mismatched: Vec::new(),
+    };
+    for declared in requirements {
+        let requirement = &declared.1;
+        match installed.get(&normalize_package_name(&requirement.name)) {
+            None => check.missing.push(declared),
+            Some(version) if !version_satisfies(version, &requirement.specifiers) => {
+                check.mismatched.push((declared, version.clone()))
+            }
+            Some(_) => check.satisfied += 1,
+        }
+    }
+    check
+}
+
+/// Whether an interpreter `version` matches a `.python-version` entry: `3.12`
+/// accepts any 3.12.x and `3.12.1` only itself. Entries that aren't versions
+/// (pyenv virtualenv names, `system`) never match.
+pub(crate) fn version_matches_pin(version: &str, pin: &str) -> bool {
+    if !pin.starts_with(|c: char| c.is_ascii_digit()) {
+        return false;
+    }
+    let pin = release_numbers(pin);
+    let version = release_numbers(version);
+    version.len() >= pin.len() && version[..pin.len()] == pin[..]
+}
+
+/// Lines added to and removed from a requirements file going from `old` to
+/// `new`, ignoring order, blank lines and comments.
+pub(crate) fn diff_requirement_lines(old: &str, new: &str) -> (Vec<String>, Vec<String>) {
+    let entries = |contents: &str| -> Vec<String> {
+        contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect()
+    };
+    let (old, new) = (entries(old), entries(new));
+    let added = new
+        .iter()
+        .filter(|line| !old.contains(line))
+        .cloned()
+        .collect();
+    let removed = old
+        .iter()
+        .filter(|line| !new.contains(line))
+        .cloned()
+        .collect();
+    (added, removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diffs_requirement_files() {
+        let old = "# pinned\nrequests==2.31.0\ndjango==4.2.7\n\n";
+        let new = "django==5.0\nrequests==2.31.0\npytest==8.0.0\n";
+        let (added, removed) = diff_requirement_lines(old, new);
+        assert_eq!(added, ["django==5.0", "pytest==8.0.0"]);
+        assert_eq!(removed, ["django==4.2.7"]);
+    }
+
+    #[test]
+    fn checks_version_specifiers() {
+        assert!(version_satisfies("3.11.4", ">=3.10,<3.13"));
+        assert!(!version_satisfies("3.8.18", ">=3.10"));
+        assert!(version_satisfies("2.31.0", "==2.31.*"));
+        assert!(!version_satisfies("2.32.0", "==2.31.*"));
+        assert!(version_satisfies("1.4.5", "~=1.4.2"));
+        assert!(!version_satisfies("1.5.0", "~=1.4.2"));
+        assert!(version_satisfies("0.1.0", ""));
+    }
+
+    #[test]
+    fn reads_requires_python() {
+        let pyproject = "[tool.poetry]\nrequires-python = \"^3.8\"\n\n\
+                         [project]\nname = \"app\"\nrequires-python = \">=3.11\"\n";
+        assert_eq!(read_requires_python(pyproject).as_deref(), Some(">=3.11"));
+        assert_eq!(read_requires_python("[project]\nname = \"app\"\n"), None);
+    }
+
+    #[test]
+    fn parses_requirements() {
+        let requirement =
+            Requirement::parse("Requests[socks] >= 2.31 ; python_version > \"3.8\"").unwrap();
+        assert_eq!(requirement.name, "Requests");
+        assert_eq!(requirement.specifiers, ">=2.31");
+
+        for line in ["# comment", "-e .", "pkg @ https://example.com/pkg.whl", ""] {
+            assert!(Requirement::parse(line).is_none(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn reads_requirements_from_both_files() {
+        let requirements = read_project_requirements(|path| {
+            match path {
+            "requirements.txt" => Some("django>=4.2  # web\n--index-url https://pypi.org\n".into()),
+            "pyproject.toml" => Some(
+                "[project]\nname = \"app\"\ndependencies = [\n  \"httpx\",  # client\n  \"pydantic>=2\",\n]\n"
+                    .into(),
+            ),
+            _ => None,
+        }
+        });
+        let found: Vec<_> = requirements
+            .iter()
+            .map(|(source, requirement)| {
+                (
+                    source.as_str(),
+                    requirement.name.as_str(),
+                    requirement.specifiers.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("requirements.txt", "django", ">=4.2"),
+                ("pyproject.toml", "httpx", ""),
+                ("pyproject.toml", "pydantic", ">=2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_version_files() {
+        assert_eq!(
+            parse_python_version_file("3.12.1\n# fallback\n3.11.7 system\n"),
+            Some(vec!["3.12.1".into(), "3.11.7".into(), "system".into()])
+        );
+        assert_eq!(parse_python_version_file("\n# nothing pinned\n"), None);
+        assert_eq!(
+            parse_tool_versions("nodejs 20.11.0\npython 3.12.1 3.11.7 # dev\n", "python"),
+            Some(vec!["3.12.1".into(), "3.11.7".into()])
+        );
+        assert_eq!(parse_tool_versions("nodejs 20.11.0\n", "python"), None);
+    }
+
+    #[test]
+    fn reads_version_pins() {
+        let files = |python_version: Option<&'static str>| {
+            move |file: &str| match file {
+                ".python-version" => python_version.map(str::to_string),
+                ".tool-versions" => Some("nodejs 20.11.0\npython 3.11\n".to_string()),
+                _ => None,
+            }
+        };
+        let pin = VersionPin::read(files(Some("3.12\n"))).unwrap();
+        assert_eq!(pin.source, ".python-version");
+        assert!(pin.allows(".venv", "3.12.4"));
+        assert!(!pin.allows(".venv", "3.11.9"));
+
+        let pin = VersionPin::read(files(None)).unwrap();
+        assert_eq!(pin.source, ".tool-versions");
+        assert_eq!(pin.versions, ["3.11"]);
+    }
+
+    #[test]
+    fn reads_environment_pins() {
+        assert_eq!(
+            parse_pin_file("# pinned with /pyenvselect --pin\n\n.venv\n").as_deref(),
+            Some(".venv")
+        );
+        assert_eq!(parse_pin_file("# nothing yet\n"), None);
+        let pyproject =
+            "[project]\nname = \"app\"\n\n[tool.zed-pyenv]\nenvironment = \"conda:ml\"\n";
+        assert_eq!(read_pyproject_pin(pyproject).as_deref(), Some("conda:ml"));
+        assert_eq!(read_pyproject_pin("[project]\nname = \"app\"\n"), None);
+    }
+
+    #[test]
+    fn reads_environment_yml_name() {
+        let environment_yml = "# conda env create -f environment.yml\n\
+                               name: 'ml-project'  # shared with CI\n\
+                               channels:\n  - conda-forge\n\
+                               dependencies:\n  - python=3.11\n  - pip:\n    - name: ignored\n";
+        assert_eq!(
+            read_environment_yml_name(environment_yml).as_deref(),
+            Some("ml-project")
+        );
+        assert_eq!(
+            read_environment_yml_name("dependencies:\n  - numpy\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn sorts_requirements_by_installed_version() {
+        let requirements: Vec<_> = ["Django>=4.2", "httpx", "pydantic>=2", "rich"]
+            .into_iter()
+            .map(|line| {
+                (
+                    "requirements.txt".to_string(),
+                    Requirement::parse(line).unwrap(),
+                )
+            })
+            .collect();
+        let installed = [
+            ("django", "5.0.1"),
+            ("HTTPX", "0.27.0"),
+            ("pydantic", "1.10.13"),
+        ]
+        .map(|(name, version)| (name.to_string(), version.to_string()));
+
+        let check = check_requirements(&requirements, installed);
+        assert_eq!(check.satisfied, 2);
+        assert_eq!(check.missing.len(), 1);
+        assert_eq!(check.missing[0].1.name, "rich");
+        assert_eq!(check.mismatched.len(), 1);
+        assert_eq!(check.mismatched[0].0 .1.name, "pydantic");
+        assert_eq!(check.mismatched[0].1, "1.10.13");
+    }
+
+    #[test]
+    fn matches_version_pins() {
+        assert!(version_matches_pin("3.12.1", "3.12"));
+        assert!(version_matches_pin("3.12.1", "3.12.1"));
+        assert!(!version_matches_pin("3.12.1", "3.11"));
+        assert!(!version_matches_pin("3.1.4", "3.12"));
+        assert!(!version_matches_pin("3.12.1", "system"));
+    }
+
+    #[test]
+    fn normalizes_package_names() {
+        assert_eq!(normalize_package_name("Foo_Bar.baz"), "foo-bar-baz");
+        assert_eq!(
+            normalize_package_name("typing--extensions"),
+            "typing-extensions"
+        );
+    }
+}
@@ -0,0 +1,30 @@
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::environment::{environments_in, home_dir, PythonEnvironment};
+use crate::error::PyEnvError;
+use std::path::PathBuf;
+
+/// asdf-managed interpreters from `$ASDF_DATA_DIR/installs/python/<version>`.
+pub(crate) struct AsdfProvider;
+
+impl EnvironmentProvider for AsdfProvider {
+    fn name(&self) -> &'static str {
+        "asdf"
+    }
+
+    fn label(&self) -> &'static str {
+        "asdf"
+    }
+
+    fn discover(&self, _cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        let data_dir = std::env::var_os("ASDF_DATA_DIR")
+            .map(PathBuf::from)
+            .or_else(|| home_dir().map(|home| home.join(".asdf")))
+            .ok_or_else(|| PyEnvError::unavailable(self.name(), "no data directory found"))?;
+
+        let mut envs = environments_in(&data_dir.join("installs").join("python"), self.name());
+        for env in &mut envs {
+            env.version = Some(env.name.clone());
+        }
+        Ok(envs)
+    }
+}
@@ -0,0 +1,353 @@
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::environment::{find_python_executable, home_dir, PythonEnvironment};
+use crate::error::PyEnvError;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Environments listed by `conda env list --json`.
+pub(crate) struct CondaProvider;
+
+/// Output of `conda env list --json`.
+#[derive(Deserialize)]
+struct CondaEnvList {
+    envs: Vec<PathBuf>,
+}
+
+impl CondaProvider {
+    /// Parses the `env list --json` output shared by conda, mamba and micromamba.
+    fn parse_conda_output(
+        output: &str,
+        provider: &str,
+    ) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        let env_list: CondaEnvList = serde_json::from_str(output)
+            .map_err(PyEnvError::parse(format!("{} output", provider)))?;
+
+        Ok(Self::environments_at(env_list.envs, provider))
+    }
+
+    fn environments_at(env_paths: Vec<PathBuf>, provider: &str) -> Vec<PythonEnvironment> {
+        env_paths
+            .into_iter()
+            .filter_map(|env_path| {
+                let python_path = find_python_executable(&env_path)?;
+                Some(PythonEnvironment {
+                    name: Self::env_name(&env_path),
+                    active: Self::is_active(&env_path),
+                    python_path,
+                    provider: provider.to_string(),
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+
+    /// Whether `env_path` is the environment activated via `conda activate`.
+    fn is_active(env_path: &Path) -> bool {
+        if let Some(prefix) = std::env::var_os("CONDA_PREFIX") {
+            return Path::new(&prefix) == env_path;
+        }
+        std::env::var("CONDA_DEFAULT_ENV").is_ok_and(|name| name == Self::env_name(env_path))
+    }
+
+    /// Names an environment the way `conda activate` accepts it: `base` for the
+    /// installation root, the directory name for anything else.
+    pub(crate) fn env_name(env_path: &Path) -> String {
+        if env_path.join("condabin").is_dir() {
+            return "base".to_string();
+        }
+        env_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| env_path.display().to_string())
+    }
+
+    /// Finds environments without running `conda`, which often isn't on the
+    /// PATH of a GUI-launched Zed: `~/.conda/environments.txt`, the
+    /// `envs_dirs` of `~/.condarc`, and the usual installation directories.
+    fn known_environments() -> Vec<PathBuf> {
+        let home = home_dir();
+        let mut env_paths = Vec::new();
+
+        if let Some(home) = &home {
+            if let Ok(contents) = fs::read_to_string(home.join(".conda").join("environments.txt")) {
+                env_paths.extend(
+                    contents
+                        .lines()
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty())
+                        .map(PathBuf::from),
+                );
+            }
+        }
+
+        let install_roots = Self::install_roots(home.as_deref());
+        let envs_dirs = Self::envs_dirs(home.as_deref(), &install_roots);
+        env_paths.extend(install_roots);
+        for envs_dir in envs_dirs {
+            if let Ok(entries) = fs::read_dir(&envs_dir) {
+                env_paths.extend(entries.filter_map(Result::ok).map(|entry| entry.path()));
+            }
+        }
+
+        env_paths.sort();
+        env_paths.dedup();
+        env_paths
+    }
+
+    /// Where conda may be installed: next to `$CONDA_EXE` and the usual
+    /// installer locations.
+    fn install_roots(home: Option<&Path>) -> Vec<PathBuf> {
+        let mut install_roots: Vec<PathBuf> = std::env::var_os("CONDA_EXE")
+            .and_then(|conda_exe| {
+                Path::new(&conda_exe)
+                    .parent()?
+                    .parent()
+                    .map(Path::to_path_buf)
+            })
+            .into_iter()
+            .collect();
+        let bases = home
+            .map(Path::to_path_buf)
+            .into_iter()
+            .chain(["/opt", "/usr/local"].map(PathBuf::from));
+        for base in bases {
+            for name in [
+                "miniconda3",
+                "anaconda3",
+                "miniforge3",
+                "mambaforge",
+                "miniconda",
+                "anaconda",
+            ] {
+                install_roots.push(base.join(name));
+            }
+        }
+        // Homebrew's miniconda cask.
+        install_roots.push(PathBuf::from("/opt/homebrew/Caskroom/miniconda/base"));
+        install_roots
+    }
+
+    /// The directories named environments are created in: each installation's
+    /// `envs`, `~/.conda/envs` and the `envs_dirs` of `~/.condarc`.
+    fn envs_dirs(home: Option<&Path>, install_roots: &[PathBuf]) -> Vec<PathBuf> {
+        let mut envs_dirs: Vec<PathBuf> =
+            install_roots.iter().map(|root| root.join("envs")).collect();
+        if let Some(home) = home {
+            envs_dirs.push(home.join(".conda").join("envs"));
+            if let Ok(contents) = fs::read_to_string(home.join(".condarc")) {
+                envs_dirs.extend(Self::condarc_envs_dirs(&contents, home));
+            }
+        }
+        envs_dirs
+    }
+
+    /// Reads the `envs_dirs` list from a `.condarc`, in either block
+    /// (`- path` lines) or flow (`[a, b]`) style.
+    fn condarc_envs_dirs(contents: &str, home: &Path) -> Vec<PathBuf> {
+        let expand = |dir: &str| {
+            let dir = dir.trim().trim_matches(|c| c == '"' || c == '\'');
+            match dir.strip_prefix("~/") {
+                Some(rest) => home.join(rest),
+                None => PathBuf::from(dir),
+            }
+        };
+
+        let mut lines = contents
+            .lines()
+            .skip_while(|line| !line.starts_with("envs_dirs:"));
+        let Some(header) = lines.next() else {
+            return Vec::new();
+        };
+        let inline = header.trim_start_matches("envs_dirs:").trim();
+        if let Some(list) = inline.strip_prefix('[') {
+            return list
+                .trim_end_matches(']')
+                .split(',')
+                .filter(|dir| !dir.trim().is_empty())
+                .map(expand)
+                .collect();
+        }
+
+        lines
+            .map(str::trim)
+            .take_while(|line| line.starts_with('-') || line.is_empty() || line.starts_with('#'))
+            .filter_map(|line| line.strip_prefix('-'))
+            .map(expand)
+            .collect()
+    }
+}
+
+impl EnvironmentProvider for CondaProvider {
+    fn name(&self) -> &'static str {
+        "conda"
+    }
+
+    fn label(&self) -> &'static str {
+        "Conda"
+    }
+
+    fn discover(&self, cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        match cx.run_tool("conda", |command| {
+            command.args(["env", "list", "--json"]);
+        }) {
+            Ok(output) => Self::parse_conda_output(&output, self.name()),
+            Err(_) => Ok(Self::environments_at(
+                Self::known_environments(),
+                self.name(),
+            )),
+        }
+    }
+
+    fn watched_paths(&self, _cx: &DiscoveryContext) -> Vec<PathBuf> {
+        let home = home_dir();
+        let mut paths = Self::envs_dirs(home.as_deref(), &Self::install_roots(home.as_deref()));
+        // conda records every environment it creates here, wherever it lives.
+        paths.extend(home.map(|home| home.join(".conda").join("environments.txt")));
+        paths
+    }
+}
+
+/// micromamba and mamba environments for machines without a `conda` binary.
+pub(crate) struct MambaProvider;
+
+impl MambaProvider {
+    fn root_prefix() -> Option<PathBuf> {
+        std::env::var_os("MAMBA_ROOT_PREFIX")
+            .map(PathBuf::from)
+            .or_else(|| home_dir().map(|home| home.join("micromamba")))
+    }
+}
+
+impl EnvironmentProvider for MambaProvider {
+    fn name(&self) -> &'static str {
+        "mamba"
+    }
+
+    fn label(&self) -> &'static str {
+        "Mamba"
+    }
+
+    fn discover(&self, cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        // With conda installed the conda provider already lists these environments.
+        if cx.find_tool("conda").is_some() {
+            return Ok(Vec::new());
+        }
+
+        for binary in ["micromamba", "mamba"] {
+            if let Ok(output) = cx.run_tool(binary, |command| {
+                command.args(["env", "list", "--json"]);
+            }) {
+                return CondaProvider::parse_conda_output(&output, self.name());
+            }
+        }
+
+        // Neither binary is runnable: read the root prefix's envs directory directly.
+        let root_prefix = Self::root_prefix()
+            .ok_or_else(|| PyEnvError::unavailable(self.name(), "no root prefix found"))?;
+        let env_paths = fs::read_dir(root_prefix.join("envs"))
+            .map(|entries| entries.filter_map(Result::ok).map(|entry| entry.path()))
+            .map_err(PyEnvError::io(format!(
+                "Failed to read {}",
+                root_prefix.display()
+            )))?;
+        Ok(CondaProvider::environments_at(
+            std::iter::once(root_prefix.clone())
+                .chain(env_paths)
+                .collect(),
+            self.name(),
+        ))
+    }
+
+    fn watched_paths(&self, _cx: &DiscoveryContext) -> Vec<PathBuf> {
+        Self::root_prefix()
+            .map(|root_prefix| root_prefix.join("envs"))
+            .into_iter()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+
+    #[test]
+    fn parses_env_list_output() {
+        let fixture = Fixture::new();
+        let base = fixture.path().join("miniconda3");
+        fixture.touch("miniconda3/bin/python");
+        fixture.touch("miniconda3/condabin/conda");
+        fixture.touch("miniconda3/envs/data/bin/python");
+        let output = serde_json::json!({
+            "envs": [base, base.join("envs/data"), base.join("envs/removed")],
+        })
+        .to_string();
+
+        let envs = CondaProvider::parse_conda_output(&output, "conda").unwrap();
+        let found: Vec<_> = envs
+            .iter()
+            .map(|env| (env.name.as_str(), env.python_path.clone()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("base", base.join("bin/python")),
+                ("data", base.join("envs/data/bin/python")),
+            ]
+        );
+        assert!(envs.iter().all(|env| env.provider == "conda"));
+    }
+
+    #[test]
+    fn parses_windows_env_layout() {
+        let fixture = Fixture::new();
+        let python_path = fixture.touch("Miniconda3/envs/ml/python.exe");
+        let output =
+            serde_json::json!({ "envs": [fixture.path().join("Miniconda3/envs/ml")] }).to_string();
+
+        let envs = CondaProvider::parse_conda_output(&output, "mamba").unwrap();
+        assert_eq!(envs.len(), 1);
+        assert_eq!(envs[0].name, "ml");
+        assert_eq!(envs[0].python_path, python_path);
+        assert_eq!(envs[0].provider, "mamba");
+    }
+
+    #[test]
+    fn rejects_malformed_output() {
+        let error =
+            CondaProvider::parse_conda_output("EnvironmentLocationNotFound", "conda").unwrap_err();
+        assert!(matches!(error, PyEnvError::Parse { .. }));
+        assert!(error
+            .to_string()
+            .starts_with("Failed to parse conda output"));
+    }
+
+    #[test]
+    fn reads_block_style_envs_dirs() {
+        let home = Path::new("/home/dev");
+        let condarc = "\
+channels:
+  - conda-forge
+envs_dirs:
+  - ~/envs
+  # shared with the team
+  - \"/opt/conda envs\"
+auto_activate_base: false
+";
+        assert_eq!(
+            CondaProvider::condarc_envs_dirs(condarc, home),
+            [home.join("envs"), PathBuf::from("/opt/conda envs")]
+        );
+    }
+
+    #[test]
+    fn reads_flow_style_envs_dirs() {
+        let home = Path::new("/home/dev");
+        assert_eq!(
+            CondaProvider::condarc_envs_dirs("envs_dirs: [~/envs, '/data/envs']\n", home),
+            [home.join("envs"), PathBuf::from("/data/envs")]
+        );
+        assert!(CondaProvider::condarc_envs_dirs("channels: [defaults]\n", home).is_empty());
+    }
+}
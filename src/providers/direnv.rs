@@ -0,0 +1,137 @@
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::environment::{find_python_executable, PythonEnvironment};
+use crate::error::PyEnvError;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// The virtual environment the worktree's `.envrc` activates, resolved with
+/// `direnv export json` so it matches what a direnv-enabled shell gets.
+pub(crate) struct DirenvProvider;
+
+impl DirenvProvider {
+    /// Whether an `.envrc` sets up a Python environment, with one of direnv's
+    /// Python layouts or by exporting `VIRTUAL_ENV` itself.
+    fn activates_python(envrc: &str) -> bool {
+        envrc
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or("").trim())
+            .any(|line| {
+                ["python", "pyenv", "uv", "poetry", "pipenv"]
+                    .iter()
+                    .any(|layout| {
+                        line.strip_prefix("layout ")
+                            .and_then(|rest| rest.split_whitespace().next())
+                            .is_some_and(|name| name.starts_with(layout))
+                    })
+                    || line.contains("VIRTUAL_ENV=")
+            })
+    }
+
+    /// The `VIRTUAL_ENV` of `direnv export json` output, which maps each
+    /// changed variable to its new value (`null` when unset).
+    fn exported_virtual_env(output: &str) -> Result<Option<PathBuf>, PyEnvError> {
+        if output.trim().is_empty() {
+            return Ok(None);
+        }
+        let exported: HashMap<String, Option<String>> =
+            serde_json::from_str(output).map_err(PyEnvError::parse("direnv export json output"))?;
+        Ok(exported
+            .get("VIRTUAL_ENV")
+            .cloned()
+            .flatten()
+            .map(PathBuf::from))
+    }
+
+    fn environment_at(&self, env_root: &Path) -> Option<PythonEnvironment> {
+        Some(PythonEnvironment {
+            name: env_root
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| env_root.display().to_string()),
+            python_path: find_python_executable(env_root)?,
+            provider: self.name().to_string(),
+            ..Default::default()
+        })
+    }
+}
+
+impl EnvironmentProvider for DirenvProvider {
+    fn name(&self) -> &'static str {
+        "direnv"
+    }
+
+    fn label(&self) -> &'static str {
+        "direnv"
+    }
+
+    fn discover(&self, cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        let Some(root_path) = cx.root_path else {
+            return Ok(Vec::new());
+        };
+        if !cx
+            .read_project_file(".envrc")
+            .is_some_and(|envrc| Self::activates_python(&envrc))
+        {
+            return Ok(Vec::new());
+        }
+
+        let (_, output) = cx.run_tool_unchecked("direnv", |command| {
+            command.args(["export", "json"]).current_dir(root_path);
+        })?;
+        let output = output?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // direnv refuses to load an .envrc until the user has allowed it.
+        if stderr.contains("is blocked") {
+            cx.warn(
+                ".envrc sets up a Python environment, but direnv hasn't been allowed to load it; run `direnv allow`"
+                    .to_string(),
+            );
+            return Ok(Vec::new());
+        }
+        let env_root = Self::exported_virtual_env(&String::from_utf8_lossy(&output.stdout))?;
+        Ok(env_root
+            .and_then(|env_root| self.environment_at(&env_root))
+            .into_iter()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+
+    #[test]
+    fn recognizes_python_envrcs() {
+        assert!(DirenvProvider::activates_python("layout python3\n"));
+        assert!(DirenvProvider::activates_python(
+            "dotenv\nlayout pyenv 3.12.1\n"
+        ));
+        assert!(DirenvProvider::activates_python(
+            "export VIRTUAL_ENV=$PWD/.venv\nPATH_add .venv/bin\n"
+        ));
+        assert!(!DirenvProvider::activates_python(
+            "# layout python\nuse nix\n"
+        ));
+    }
+
+    #[test]
+    fn reads_the_exported_virtual_env() {
+        let fixture = Fixture::new();
+        let python = fixture.venv(".direnv/python-3.12");
+        let env_root = fixture.path().join(".direnv/python-3.12");
+        let output = serde_json::json!({
+            "VIRTUAL_ENV": env_root,
+            "DIRENV_DIR": "-/work/app",
+            "PYTHONHOME": null,
+        })
+        .to_string();
+
+        let exported = DirenvProvider::exported_virtual_env(&output).unwrap();
+        assert_eq!(exported.as_deref(), Some(env_root.as_path()));
+        let env = DirenvProvider.environment_at(&env_root).unwrap();
+        assert_eq!(env.name, "python-3.12");
+        assert_eq!(env.python_path, python);
+        assert_eq!(DirenvProvider::exported_virtual_env("").unwrap(), None);
+    }
+}
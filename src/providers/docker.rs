@@ -0,0 +1,90 @@
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::container::{parse_docker_ps, read_devcontainer_service, RunningContainer};
+use crate::environment::{Guest, PythonEnvironment};
+use crate::error::PyEnvError;
+use std::path::PathBuf;
+
+/// The interpreter inside the running dev container or docker-compose
+/// service that serves as the worktree's Python runtime. Such an
+/// interpreter only exists inside the container, so it is listed with
+/// [`PythonEnvironment::guest`] set and run through `docker exec`.
+pub(crate) struct DockerProvider;
+
+impl DockerProvider {
+    /// Where a dev container's configuration may live.
+    const DEVCONTAINER_FILES: &'static [&'static str] =
+        &[".devcontainer/devcontainer.json", ".devcontainer.json"];
+
+    /// Asks `container` for its interpreter and that interpreter's version.
+    fn container_interpreter(
+        &self,
+        cx: &DiscoveryContext,
+        container: &RunningContainer,
+    ) -> Result<PythonEnvironment, PyEnvError> {
+        let output = cx.run_tool("docker", |command| {
+            command.args(["exec", &container.name, "sh", "-c"]).arg(
+                r#"p=$(command -v python3 || command -v python) && echo "$p" && "$p" -c "import platform; print(platform.python_version())""#,
+            );
+        })?;
+        let mut lines = output.lines().map(str::trim);
+        let python_path = lines
+            .next()
+            .filter(|line| line.starts_with('/'))
+            .ok_or_else(|| {
+                PyEnvError::unavailable(
+                    self.name(),
+                    format!("the container {} has no python", container.name),
+                )
+            })?;
+        Ok(PythonEnvironment {
+            name: container.name.clone(),
+            python_path: PathBuf::from(python_path),
+            provider: self.name().to_string(),
+            version: lines.next().map(str::to_string),
+            guest: Some(Guest::Container(container.name.clone())),
+            ..Default::default()
+        })
+    }
+}
+
+impl EnvironmentProvider for DockerProvider {
+    fn name(&self) -> &'static str {
+        "docker"
+    }
+
+    fn label(&self) -> &'static str {
+        "Dev containers"
+    }
+
+    /// Talks to the docker daemon and runs commands in containers.
+    fn opt_in(&self) -> bool {
+        true
+    }
+
+    fn discover(&self, cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        let Some(root_path) = cx.root_path else {
+            return Ok(Vec::new());
+        };
+        let service = Self::DEVCONTAINER_FILES
+            .iter()
+            .find_map(|file| cx.read_project_file(file))
+            .and_then(|contents| read_devcontainer_service(&contents));
+
+        let output = cx.run_tool("docker", |command| {
+            command.args(["ps", "--format", "{{.Names}}\t{{.Labels}}"]);
+        })?;
+        let mut envs = Vec::new();
+        for container in parse_docker_ps(&output)
+            .iter()
+            .filter(|container| container.runs_python_for(root_path, service.as_deref()))
+        {
+            match self.container_interpreter(cx, container) {
+                Ok(env) => envs.push(env),
+                Err(PyEnvError::ProviderUnavailable { reason, .. }) => cx.warn(reason),
+                // Already reported by `run_tool`.
+                Err(_) => {}
+            }
+        }
+        Ok(envs)
+    }
+}
@@ -0,0 +1,56 @@
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::environment::{environments_in, find_python_executable, home_dir, PythonEnvironment};
+use crate::error::PyEnvError;
+use std::path::{Path, PathBuf};
+
+/// Environments in the directories listed in the `extra_search_paths` setting.
+pub(crate) struct ExtraSearchPathsProvider;
+
+impl ExtraSearchPathsProvider {
+    fn resolve(path: &str, root_path: Option<&Path>) -> Option<PathBuf> {
+        if let Some(rest) = path.strip_prefix("~/").or_else(|| path.strip_prefix("~\\")) {
+            return home_dir().map(|home| home.join(rest));
+        }
+        let path = PathBuf::from(path);
+        if path.is_absolute() {
+            Some(path)
+        } else {
+            root_path.map(|root_path| root_path.join(path))
+        }
+    }
+}
+
+impl EnvironmentProvider for ExtraSearchPathsProvider {
+    fn name(&self) -> &'static str {
+        "custom"
+    }
+
+    fn label(&self) -> &'static str {
+        "Extra search paths"
+    }
+
+    fn discover(&self, cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        let mut envs = Vec::new();
+
+        for search_path in &cx.settings.extra_search_paths {
+            let Some(dir) = Self::resolve(search_path, cx.root_path) else {
+                continue;
+            };
+            // Each entry may point at an environment itself or at a folder of them.
+            match find_python_executable(&dir) {
+                Some(python_path) => envs.push(PythonEnvironment {
+                    name: dir
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| dir.display().to_string()),
+                    python_path,
+                    provider: self.name().to_string(),
+                    ..Default::default()
+                }),
+                None => envs.extend(environments_in(&dir, self.name())),
+            }
+        }
+
+        Ok(envs)
+    }
+}
@@ -0,0 +1,153 @@
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::environment::PythonEnvironment;
+use crate::error::PyEnvError;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The interpreters of each `python@3.x` formula, through Homebrew's
+/// version-independent `opt` links so a venv created from one survives
+/// `brew upgrade`.
+pub(crate) struct HomebrewProvider;
+
+impl HomebrewProvider {
+    /// `$HOMEBREW_PREFIX`, or the default prefixes on Apple silicon and Intel.
+    fn prefixes(cx: &DiscoveryContext) -> Vec<PathBuf> {
+        match cx.env_var("HOMEBREW_PREFIX") {
+            Some(prefix) => vec![PathBuf::from(prefix)],
+            None => vec![PathBuf::from("/opt/homebrew"), PathBuf::from("/usr/local")],
+        }
+    }
+
+    /// `<prefix>/opt/python@3.12/bin/python3.12` and friends.
+    fn formula_interpreters(&self, prefix: &Path) -> Vec<PythonEnvironment> {
+        let Ok(entries) = fs::read_dir(prefix.join("opt")) else {
+            return Vec::new();
+        };
+        let mut envs: Vec<PythonEnvironment> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let formula = entry.file_name().to_string_lossy().into_owned();
+                let version = formula.strip_prefix("python@")?;
+                let python_path = entry.path().join("bin").join(format!("python{}", version));
+                python_path.is_file().then(|| PythonEnvironment {
+                    name: formula.clone(),
+                    python_path,
+                    provider: self.name().to_string(),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        envs.sort_by(|a, b| a.name.cmp(&b.name));
+        envs
+    }
+}
+
+impl EnvironmentProvider for HomebrewProvider {
+    fn name(&self) -> &'static str {
+        "homebrew"
+    }
+
+    fn label(&self) -> &'static str {
+        "Homebrew"
+    }
+
+    fn discover(&self, cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        Ok(Self::prefixes(cx)
+            .iter()
+            .flat_map(|prefix| self.formula_interpreters(prefix))
+            .collect())
+    }
+
+    fn watched_paths(&self, cx: &DiscoveryContext) -> Vec<PathBuf> {
+        Self::prefixes(cx)
+            .into_iter()
+            .map(|prefix| prefix.join("opt"))
+            .collect()
+    }
+}
+
+/// Pythons from the python.org macOS installer, which installs each version
+/// as a framework under `/Library/Frameworks/Python.framework/Versions`.
+pub(crate) struct FrameworkProvider;
+
+impl FrameworkProvider {
+    const VERSIONS_DIR: &'static str = "/Library/Frameworks/Python.framework/Versions";
+
+    /// `<versions_dir>/3.12/bin/python3.12` and friends, skipping the
+    /// `Current` link to one of them.
+    fn framework_interpreters(&self, versions_dir: &Path) -> Vec<PythonEnvironment> {
+        let Ok(entries) = fs::read_dir(versions_dir) else {
+            return Vec::new();
+        };
+        let mut envs: Vec<PythonEnvironment> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let version = entry.file_name().to_string_lossy().into_owned();
+                if version == "Current" {
+                    return None;
+                }
+                let python_path = entry.path().join("bin").join(format!("python{}", version));
+                python_path.is_file().then(|| PythonEnvironment {
+                    name: version,
+                    python_path,
+                    provider: self.name().to_string(),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        envs.sort_by(|a, b| a.name.cmp(&b.name));
+        envs
+    }
+}
+
+impl EnvironmentProvider for FrameworkProvider {
+    fn name(&self) -> &'static str {
+        "python-org"
+    }
+
+    fn label(&self) -> &'static str {
+        "python.org framework"
+    }
+
+    fn discover(&self, _cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        Ok(self.framework_interpreters(Path::new(Self::VERSIONS_DIR)))
+    }
+
+    fn watched_paths(&self, _cx: &DiscoveryContext) -> Vec<PathBuf> {
+        vec![PathBuf::from(Self::VERSIONS_DIR)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+
+    #[test]
+    fn finds_homebrew_formulae() {
+        let fixture = Fixture::new();
+        fixture.touch("opt/python@3.12/bin/python3.12");
+        fixture.touch("opt/python@3.11/bin/python3.11");
+        fixture.touch("opt/node/bin/node");
+
+        let envs = HomebrewProvider.formula_interpreters(fixture.path());
+        let names: Vec<&str> = envs.iter().map(|env| env.name.as_str()).collect();
+        assert_eq!(names, ["python@3.11", "python@3.12"]);
+        assert_eq!(
+            envs[1].python_path,
+            fixture.path().join("opt/python@3.12/bin/python3.12")
+        );
+    }
+
+    #[test]
+    fn finds_framework_versions() {
+        let fixture = Fixture::new();
+        fixture.touch("Versions/3.12/bin/python3.12");
+        fixture.touch("Versions/3.13t/bin/python3.13t");
+        fixture.touch("Versions/Current/bin/python3.12");
+
+        let envs = FrameworkProvider.framework_interpreters(&fixture.path().join("Versions"));
+        let names: Vec<&str> = envs.iter().map(|env| env.name.as_str()).collect();
+        assert_eq!(names, ["3.12", "3.13t"]);
+    }
+}
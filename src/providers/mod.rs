@@ -0,0 +1,308 @@
+use crate::environment::{find_on_path, PythonEnvironment};
+use crate::error::PyEnvError;
+use crate::logging;
+use crate::process::{command_line, run_command, successful_stdout};
+use crate::ExtensionSettings;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use zed_extension_api::Worktree;
+
+mod asdf;
+mod conda;
+mod direnv;
+mod docker;
+mod extra_search_paths;
+mod macos;
+mod nix;
+mod nox;
+mod pipenv;
+mod pipx;
+mod pixi;
+mod poetry;
+mod pyenv;
+mod rye;
+mod shell;
+mod system;
+mod tox;
+mod uv;
+mod venv;
+mod virtualenvwrapper;
+mod windows_registry;
+mod windows_store;
+mod wsl;
+
+use asdf::AsdfProvider;
+use conda::{CondaProvider, MambaProvider};
+use direnv::DirenvProvider;
+use docker::DockerProvider;
+use extra_search_paths::ExtraSearchPathsProvider;
+use macos::{FrameworkProvider, HomebrewProvider};
+use nix::NixProvider;
+use nox::NoxProvider;
+use pipenv::PipenvProvider;
+use pipx::PipxProvider;
+use pixi::PixiProvider;
+use poetry::PoetryProvider;
+use pyenv::PyenvProvider;
+use rye::RyeProvider;
+use shell::ShellActivatedProvider;
+use system::SystemProvider;
+use tox::ToxProvider;
+use uv::UvProvider;
+use venv::WorktreeVenvProvider;
+use virtualenvwrapper::VirtualenvwrapperProvider;
+use windows_registry::WindowsRegistryProvider;
+use windows_store::WindowsStoreProvider;
+use wsl::WslProvider;
+
+/// Files at the worktree root that providers look at.
+const PROJECT_FILES: &[&str] = &[
+    "pyproject.toml",
+    ".python-version",
+    ".tool-versions",
+    "Pipfile",
+    "uv.lock",
+    "pixi.toml",
+    "pixi.lock",
+    "tox.ini",
+    "noxfile.py",
+    "environment.yml",
+    "environment.yaml",
+    ".zed-python-env",
+    "flake.nix",
+    "devenv.nix",
+    ".envrc",
+    ".devcontainer/devcontainer.json",
+    ".devcontainer.json",
+];
+
+/// Command-line tools providers run, resolved with the worktree's `which`.
+const DISCOVERY_TOOLS: &[&str] = &[
+    "conda",
+    "mamba",
+    "micromamba",
+    "poetry",
+    "pipenv",
+    "uv",
+    "reg",
+    "nix",
+    "direnv",
+    "docker",
+    "wsl",
+];
+
+/// What discovery needs to know about a worktree, read through the worktree
+/// API so it reflects the host the project lives on (which differs from the
+/// extension's own environment for remote projects and GUI launches). The API
+/// can neither list directories nor run processes, so scanning still goes
+/// through `std::fs` and tools are spawned locally, but with the worktree's
+/// shell environment and the tool paths its `which` resolves.
+#[derive(Clone, Default)]
+pub(crate) struct WorktreeSnapshot {
+    pub(crate) shell_env: HashMap<String, String>,
+    /// Contents of the [`PROJECT_FILES`] that exist.
+    pub(crate) files: HashMap<&'static str, String>,
+    /// Paths of the [`DISCOVERY_TOOLS`] found in the worktree's environment.
+    pub(crate) tools: HashMap<&'static str, String>,
+}
+
+impl WorktreeSnapshot {
+    pub(crate) fn capture(worktree: &Worktree) -> Self {
+        Self {
+            shell_env: worktree.shell_env().into_iter().collect(),
+            tools: DISCOVERY_TOOLS
+                .iter()
+                .filter_map(|tool| Some((*tool, worktree.which(tool)?)))
+                .collect(),
+            files: PROJECT_FILES
+                .iter()
+                .filter_map(|name| Some((*name, worktree.read_text_file(name).ok()?)))
+                .collect(),
+        }
+    }
+}
+
+/// A tool run during discovery, for `/pyenvlst --verbose`.
+#[derive(Clone, Debug)]
+pub(crate) struct CommandRun {
+    pub(crate) command_line: String,
+    /// The exit status, or why the tool didn't finish.
+    pub(crate) outcome: String,
+    pub(crate) elapsed: Duration,
+}
+
+/// Everything a provider may look at while discovering environments.
+pub(crate) struct DiscoveryContext<'a> {
+    /// Root of the worktree the command was run in, if any.
+    pub(crate) root_path: Option<&'a Path>,
+    pub(crate) settings: &'a ExtensionSettings,
+    /// Captured from the worktree, when discovery runs for one.
+    pub(crate) snapshot: Option<&'a WorktreeSnapshot>,
+    pub(crate) warnings: Mutex<Vec<String>>,
+    /// Tools run through [`DiscoveryContext::run_tool`].
+    pub(crate) commands: Mutex<Vec<CommandRun>>,
+    /// Directories read by the worktree scan.
+    pub(crate) dirs_scanned: AtomicUsize,
+}
+
+impl DiscoveryContext<'_> {
+    /// Reads `name` from the worktree's shell environment, falling back to
+    /// the extension's own environment.
+    fn env_var(&self, name: &str) -> Option<String> {
+        self.snapshot
+            .and_then(|snapshot| snapshot.shell_env.get(name).cloned())
+            .or_else(|| std::env::var(name).ok())
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Reads one of the [`PROJECT_FILES`] from the worktree root.
+    pub(crate) fn read_project_file(&self, name: &str) -> Option<String> {
+        match self.snapshot {
+            Some(snapshot) => snapshot.files.get(name).cloned(),
+            None => fs::read_to_string(self.root_path?.join(name)).ok(),
+        }
+    }
+
+    fn has_project_file(&self, name: &str) -> bool {
+        self.read_project_file(name).is_some()
+    }
+
+    /// Locates one of the [`DISCOVERY_TOOLS`].
+    fn find_tool(&self, name: &str) -> Option<PathBuf> {
+        match self.snapshot {
+            Some(snapshot) => snapshot.tools.get(name).map(PathBuf::from),
+            None => find_on_path(name),
+        }
+    }
+
+    /// Runs `tool` with the worktree's shell environment and returns its
+    /// stdout. A tool that isn't installed is an ordinary error; one that is
+    /// installed but fails or times out is also recorded as a warning, since
+    /// its environments will be missing from the list.
+    fn run_tool(
+        &self,
+        tool: &str,
+        configure: impl FnOnce(&mut Command),
+    ) -> Result<String, PyEnvError> {
+        let (command, output) = self.run_tool_unchecked(tool, configure)?;
+        output
+            .and_then(|output| successful_stdout(&command, output))
+            .inspect_err(|error| self.warn(error.to_string()))
+    }
+
+    /// Like [`Self::run_tool`], but leaves the exit status to the caller, for
+    /// tools whose failure is routine (`reg query` of a missing key).
+    fn run_tool_unchecked(
+        &self,
+        tool: &str,
+        configure: impl FnOnce(&mut Command),
+    ) -> Result<(Command, Result<Output, PyEnvError>), PyEnvError> {
+        let path = self
+            .find_tool(tool)
+            .ok_or_else(|| PyEnvError::unavailable(tool, "it is not installed"))?;
+        let mut command = Command::new(path);
+        if let Some(snapshot) = self.snapshot {
+            command.envs(&snapshot.shell_env);
+        }
+        configure(&mut command);
+
+        let started = Instant::now();
+        let output = run_command(&mut command);
+        let run = CommandRun {
+            command_line: command_line(&command),
+            outcome: match &output {
+                Ok(output) => output.status.to_string(),
+                Err(error) => error.to_string(),
+            },
+            elapsed: started.elapsed(),
+        };
+        logging::debug(
+            tool,
+            format!(
+                "`{}` → {} in {:.2}s",
+                run.command_line,
+                run.outcome,
+                run.elapsed.as_secs_f64()
+            ),
+        );
+        if let Ok(mut commands) = self.commands.lock() {
+            commands.push(run);
+        }
+        Ok((command, output))
+    }
+
+    /// Counts a directory read by the worktree scan.
+    pub(crate) fn note_dir_scanned(&self) {
+        self.dirs_scanned.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a problem to show alongside the environment list.
+    pub(crate) fn warn(&self, message: String) {
+        logging::warn("discovery", &message);
+        if let Ok(mut warnings) = self.warnings.lock() {
+            warnings.push(message);
+        }
+    }
+}
+
+/// A source of Python environments: a package manager, a tool's well-known
+/// directory, or a scan of the worktree itself.
+pub(crate) trait EnvironmentProvider: Send + Sync {
+    /// Short identifier used as the `provider` of the environments it finds.
+    fn name(&self) -> &'static str;
+
+    /// Heading of this provider's section in `/pyenvlst`.
+    fn label(&self) -> &'static str;
+
+    /// Opt-in providers only run when listed in `enabled_providers`.
+    fn opt_in(&self) -> bool {
+        false
+    }
+
+    fn discover(&self, cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError>;
+
+    /// Directories (or files) whose modification time changes when an
+    /// environment this provider reports is created or deleted. A cached scan
+    /// is redone as soon as one of them changes.
+    fn watched_paths(&self, _cx: &DiscoveryContext) -> Vec<PathBuf> {
+        Vec::new()
+    }
+}
+
+/// The registered providers, in priority order: when two providers report the
+/// same interpreter, the earlier one wins.
+pub(crate) fn providers() -> Vec<Box<dyn EnvironmentProvider>> {
+    vec![
+        // Project managers first so an in-project .venv keeps their tag
+        Box::new(PoetryProvider),
+        Box::new(PipenvProvider),
+        Box::new(UvProvider),
+        Box::new(RyeProvider),
+        Box::new(PixiProvider),
+        Box::new(ToxProvider),
+        Box::new(NoxProvider),
+        Box::new(NixProvider),
+        Box::new(DirenvProvider),
+        Box::new(DockerProvider),
+        Box::new(WorktreeVenvProvider),
+        Box::new(CondaProvider),
+        Box::new(MambaProvider),
+        Box::new(PyenvProvider),
+        Box::new(VirtualenvwrapperProvider),
+        Box::new(PipxProvider),
+        Box::new(AsdfProvider),
+        Box::new(ExtraSearchPathsProvider),
+        Box::new(ShellActivatedProvider),
+        Box::new(WindowsRegistryProvider),
+        Box::new(WindowsStoreProvider),
+        Box::new(WslProvider),
+        Box::new(HomebrewProvider),
+        Box::new(FrameworkProvider),
+        Box::new(SystemProvider),
+    ]
+}
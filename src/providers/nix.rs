@@ -0,0 +1,110 @@
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::environment::{find_python_executable, PythonEnvironment};
+use crate::error::PyEnvError;
+use std::path::{Path, PathBuf};
+
+/// The Python of a Nix project's development shell: devenv's profile and
+/// the virtual environment it can manage in `.devenv/state/venv`, or for a
+/// flake without them, whatever `nix develop` puts on `PATH`.
+pub(crate) struct NixProvider;
+
+impl NixProvider {
+    /// Interpreters devenv has already built into the worktree.
+    fn devenv_interpreters(&self, root_path: &Path) -> Vec<PythonEnvironment> {
+        let devenv = root_path.join(".devenv");
+        [
+            ("devenv", devenv.join("profile")),
+            ("devenv-venv", devenv.join("state").join("venv")),
+        ]
+        .into_iter()
+        .filter_map(|(name, dir)| {
+            Some(PythonEnvironment {
+                name: name.to_string(),
+                python_path: find_python_executable(&dir)?,
+                provider: self.name().to_string(),
+                ..Default::default()
+            })
+        })
+        .collect()
+    }
+
+    /// Asks the flake's development shell for its interpreter. This evaluates
+    /// the flake, so it only runs when devenv hasn't left a profile behind.
+    fn flake_interpreter(&self, cx: &DiscoveryContext) -> Result<PythonEnvironment, PyEnvError> {
+        let output = cx.run_tool("nix", |command| {
+            command
+                .args(["develop", "--command", "sh", "-c"])
+                .arg("command -v python3 || command -v python");
+            if let Some(root_path) = cx.root_path {
+                command.current_dir(root_path);
+            }
+        })?;
+        let python_path = output
+            .lines()
+            .map(str::trim)
+            .rfind(|line| line.starts_with('/'))
+            .map(PathBuf::from)
+            .ok_or_else(|| {
+                PyEnvError::unavailable(self.name(), "the development shell has no python")
+            })?;
+        Ok(PythonEnvironment {
+            name: "nix-develop".to_string(),
+            python_path,
+            provider: self.name().to_string(),
+            ..Default::default()
+        })
+    }
+}
+
+impl EnvironmentProvider for NixProvider {
+    fn name(&self) -> &'static str {
+        "nix"
+    }
+
+    fn label(&self) -> &'static str {
+        "Nix / devenv"
+    }
+
+    fn discover(&self, cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        let Some(root_path) = cx.root_path else {
+            return Ok(Vec::new());
+        };
+        let is_devenv = cx.has_project_file("devenv.nix");
+        let is_flake = cx.has_project_file("flake.nix");
+        if !is_devenv && !is_flake {
+            return Ok(Vec::new());
+        }
+
+        let envs = self.devenv_interpreters(root_path);
+        if !envs.is_empty() || !is_flake {
+            return Ok(envs);
+        }
+        Ok(vec![self.flake_interpreter(cx)?])
+    }
+
+    fn watched_paths(&self, cx: &DiscoveryContext) -> Vec<PathBuf> {
+        cx.root_path
+            .map(|root_path| root_path.join(".devenv"))
+            .into_iter()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+
+    #[test]
+    fn finds_devenv_interpreters() {
+        let fixture = Fixture::new();
+        assert!(NixProvider.devenv_interpreters(fixture.path()).is_empty());
+
+        fixture.touch(".devenv/profile/bin/python3");
+        let venv_python = fixture.venv(".devenv/state/venv");
+        let envs = NixProvider.devenv_interpreters(fixture.path());
+        let names: Vec<&str> = envs.iter().map(|env| env.name.as_str()).collect();
+        assert_eq!(names, ["devenv", "devenv-venv"]);
+        assert_eq!(envs[1].python_path, venv_python);
+    }
+}
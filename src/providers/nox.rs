@@ -0,0 +1,169 @@
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::environment::{find_python_executable, PythonEnvironment};
+use crate::error::PyEnvError;
+use std::fs;
+
+/// Environments nox sessions create under `<worktree>/.nox/<session>`.
+pub(crate) struct NoxProvider;
+
+impl NoxProvider {
+    /// Names of the sessions a `noxfile.py` declares with `@nox.session`,
+    /// honouring an explicit `name=`.
+    fn session_names(noxfile: &str) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut decorator: Option<String> = None;
+        for line in noxfile.lines().map(str::trim) {
+            if line.starts_with("@nox.session") || line.starts_with("@session") {
+                decorator = Some(line.to_string());
+                continue;
+            }
+            let Some(pending) = &mut decorator else {
+                continue;
+            };
+            if let Some(function) = line.strip_prefix("def ") {
+                let explicit = pending.split_once("name=").and_then(|(_, rest)| {
+                    let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+                    rest[1..].split(quote).next().map(str::to_string)
+                });
+                let function = function.split('(').next().unwrap_or_default().trim();
+                names.push(explicit.unwrap_or_else(|| function.to_string()));
+                decorator = None;
+            } else {
+                // A decorator's arguments may span several lines.
+                pending.push_str(line);
+            }
+        }
+        names
+    }
+
+    /// Recovers the session signature from an environment directory: nox
+    /// names it after `tests-3.11` with punctuation replaced by dashes, so
+    /// `tests-3-11` maps back to `tests-3.11` when `tests` is a known session.
+    fn session_signature(dir_name: &str, sessions: &[String]) -> String {
+        let session = sessions
+            .iter()
+            .filter(|session| {
+                let slug = session.to_lowercase().replace(' ', "-");
+                dir_name == slug
+                    || dir_name
+                        .strip_prefix(&slug)
+                        .is_some_and(|rest| rest.starts_with('-'))
+            })
+            .max_by_key(|session| session.len());
+        let Some(session) = session else {
+            return dir_name.to_string();
+        };
+        match dir_name[session.len()..].strip_prefix('-') {
+            None => session.clone(),
+            Some(python) if python.chars().all(|c| c.is_ascii_digit() || c == '-') => {
+                format!("{}-{}", session, python.replace('-', "."))
+            }
+            // Parametrized sessions can't be recovered from the directory name.
+            Some(_) => dir_name.to_string(),
+        }
+    }
+}
+
+impl EnvironmentProvider for NoxProvider {
+    fn name(&self) -> &'static str {
+        "nox"
+    }
+
+    fn label(&self) -> &'static str {
+        "nox"
+    }
+
+    fn discover(&self, cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        let Some(root_path) = cx.root_path else {
+            return Ok(Vec::new());
+        };
+        let Ok(entries) = fs::read_dir(root_path.join(".nox")) else {
+            return Ok(Vec::new());
+        };
+        let sessions = cx
+            .read_project_file("noxfile.py")
+            .map(|noxfile| Self::session_names(&noxfile))
+            .unwrap_or_default();
+
+        let mut environments: Vec<PythonEnvironment> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                Some(PythonEnvironment {
+                    python_path: find_python_executable(&entry.path())?,
+                    session: Some(Self::session_signature(&name, &sessions)),
+                    name,
+                    provider: self.name().to_string(),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        environments.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(environments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+    use crate::ExtensionSettings;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Mutex;
+
+    const NOXFILE: &str = r#"
+import nox
+
+@nox.session(python=["3.11", "3.12"])
+def tests(session):
+    session.run("pytest")
+
+@nox.session(
+    name="type-check",
+    python="3.12",
+)
+def mypy(session):
+    session.run("mypy", "src")
+
+def helper():
+    pass
+"#;
+
+    #[test]
+    fn reads_session_names() {
+        assert_eq!(NoxProvider::session_names(NOXFILE), ["tests", "type-check"]);
+    }
+
+    #[test]
+    fn maps_directories_to_sessions() {
+        let sessions = NoxProvider::session_names(NOXFILE);
+        let signature = |dir| NoxProvider::session_signature(dir, &sessions);
+        assert_eq!(signature("tests-3-11"), "tests-3.11");
+        assert_eq!(signature("type-check"), "type-check");
+        assert_eq!(signature("tests-django-4-2"), "tests-django-4-2");
+        assert_eq!(signature("lint"), "lint");
+    }
+
+    #[test]
+    fn lists_nox_environments() {
+        let fixture = Fixture::new();
+        fixture.write("noxfile.py", NOXFILE);
+        let tests = fixture.venv(".nox/tests-3-12");
+        fixture.touch(".nox/stale/README");
+
+        let settings = ExtensionSettings::default();
+        let cx = DiscoveryContext {
+            root_path: Some(fixture.path()),
+            settings: &settings,
+            snapshot: None,
+            warnings: Mutex::new(Vec::new()),
+            commands: Mutex::new(Vec::new()),
+            dirs_scanned: AtomicUsize::new(0),
+        };
+        let envs = NoxProvider.discover(&cx).unwrap();
+        assert_eq!(envs.len(), 1);
+        assert_eq!(envs[0].name, "tests-3-12");
+        assert_eq!(envs[0].python_path, tests);
+        assert_eq!(envs[0].session.as_deref(), Some("tests-3.12"));
+    }
+}
@@ -0,0 +1,47 @@
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::environment::{find_python_executable, PythonEnvironment};
+use crate::error::PyEnvError;
+use std::path::PathBuf;
+
+/// The virtualenv `pipenv --venv` resolves for a worktree with a Pipfile.
+pub(crate) struct PipenvProvider;
+
+impl EnvironmentProvider for PipenvProvider {
+    fn name(&self) -> &'static str {
+        "pipenv"
+    }
+
+    fn label(&self) -> &'static str {
+        "Pipenv"
+    }
+
+    fn discover(&self, cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        let Some(root_path) = cx.root_path else {
+            return Ok(Vec::new());
+        };
+        if !cx.has_project_file("Pipfile") {
+            return Ok(Vec::new());
+        }
+
+        let output = cx.run_tool("pipenv", |command| {
+            command.arg("--venv").current_dir(root_path);
+        })?;
+        let env_path = PathBuf::from(output.trim());
+        let Some(python_path) = find_python_executable(&env_path) else {
+            return Ok(Vec::new());
+        };
+
+        Ok(vec![PythonEnvironment {
+            name: env_path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            python_path,
+            provider: self.name().to_string(),
+            project: root_path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned()),
+            ..Default::default()
+        }])
+    }
+}
@@ -0,0 +1,41 @@
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::environment::{environments_in, home_dir, PythonEnvironment};
+use crate::error::PyEnvError;
+use std::path::PathBuf;
+
+/// The per-application venvs pipx keeps under `$PIPX_HOME/venvs`. Opt-in,
+/// since these are rarely what a project should use.
+pub(crate) struct PipxProvider;
+
+impl EnvironmentProvider for PipxProvider {
+    fn name(&self) -> &'static str {
+        "pipx"
+    }
+
+    fn label(&self) -> &'static str {
+        "pipx"
+    }
+
+    fn opt_in(&self) -> bool {
+        true
+    }
+
+    fn discover(&self, _cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        let pipx_homes = match std::env::var_os("PIPX_HOME") {
+            Some(pipx_home) => vec![PathBuf::from(pipx_home)],
+            None => {
+                let home = home_dir()
+                    .ok_or_else(|| PyEnvError::unavailable(self.name(), "no home directory"))?;
+                vec![
+                    home.join(".local").join("pipx"),
+                    home.join(".local").join("share").join("pipx"),
+                ]
+            }
+        };
+
+        Ok(pipx_homes
+            .iter()
+            .flat_map(|pipx_home| environments_in(&pipx_home.join("venvs"), self.name()))
+            .collect())
+    }
+}
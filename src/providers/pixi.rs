@@ -0,0 +1,59 @@
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::environment::{find_python_executable, PythonEnvironment};
+use crate::error::PyEnvError;
+use crate::project::read_toml_string;
+use std::fs;
+
+/// Environments pixi installs under `<worktree>/.pixi/envs/<name>`.
+pub(crate) struct PixiProvider;
+
+impl PixiProvider {
+    fn project_name(cx: &DiscoveryContext) -> Option<String> {
+        if let Some(manifest) = cx.read_project_file("pixi.toml") {
+            return read_toml_string(&manifest, "workspace", "name")
+                .or_else(|| read_toml_string(&manifest, "project", "name"));
+        }
+        let pyproject = cx.read_project_file("pyproject.toml")?;
+        read_toml_string(&pyproject, "tool.pixi.workspace", "name")
+            .or_else(|| read_toml_string(&pyproject, "tool.pixi.project", "name"))
+            .or_else(|| read_toml_string(&pyproject, "project", "name"))
+    }
+}
+
+impl EnvironmentProvider for PixiProvider {
+    fn name(&self) -> &'static str {
+        "pixi"
+    }
+
+    fn label(&self) -> &'static str {
+        "Pixi"
+    }
+
+    fn discover(&self, cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        let Some(root_path) = cx.root_path else {
+            return Ok(Vec::new());
+        };
+        let is_pixi_project = cx.has_project_file("pixi.toml") || cx.has_project_file("pixi.lock");
+        if !is_pixi_project {
+            return Ok(Vec::new());
+        }
+
+        let project = Self::project_name(cx);
+        let Ok(entries) = fs::read_dir(root_path.join(".pixi").join("envs")) else {
+            return Ok(Vec::new());
+        };
+
+        Ok(entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                Some(PythonEnvironment {
+                    python_path: find_python_executable(&entry.path())?,
+                    name: entry.file_name().to_string_lossy().into_owned(),
+                    provider: self.name().to_string(),
+                    project: project.clone(),
+                    ..Default::default()
+                })
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,114 @@
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::environment::{find_python_executable, home_dir, is_venv, PythonEnvironment};
+use crate::error::PyEnvError;
+use crate::project::read_toml_string;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Poetry environments of the worktree's project, from `poetry env list` or,
+/// without the `poetry` binary, from Poetry's cache directory.
+pub(crate) struct PoetryProvider;
+
+impl PoetryProvider {
+    fn project_name(cx: &DiscoveryContext) -> Option<String> {
+        let pyproject = cx.read_project_file("pyproject.toml")?;
+        if !pyproject.contains("[tool.poetry") {
+            return None;
+        }
+        read_toml_string(&pyproject, "tool.poetry", "name")
+            .or_else(|| read_toml_string(&pyproject, "project", "name"))
+    }
+
+    fn cache_dir() -> Option<PathBuf> {
+        if let Some(cache_dir) = std::env::var_os("POETRY_CACHE_DIR") {
+            return Some(PathBuf::from(cache_dir));
+        }
+        let home = home_dir()?;
+        [
+            home.join(".cache").join("pypoetry"),
+            home.join("Library").join("Caches").join("pypoetry"),
+            home.join("AppData")
+                .join("Local")
+                .join("pypoetry")
+                .join("Cache"),
+        ]
+        .into_iter()
+        .find(|path| path.is_dir())
+    }
+
+    fn env_list(cx: &DiscoveryContext, root_path: &Path) -> Result<Vec<PathBuf>, PyEnvError> {
+        let output = cx.run_tool("poetry", |command| {
+            command
+                .args(["env", "list", "--full-path"])
+                .current_dir(root_path);
+        })?;
+
+        // Each line is a path, optionally followed by " (Activated)".
+        Ok(output
+            .lines()
+            .map(|line| line.trim().trim_end_matches("(Activated)").trim())
+            .filter(|line| !line.is_empty())
+            .map(PathBuf::from)
+            .collect())
+    }
+
+    /// Locates Poetry environments without the `poetry` binary: the in-project
+    /// `.venv` plus `<cache>/virtualenvs/<project>-<hash>-py<version>`.
+    fn env_fallback_paths(root_path: &Path, project: &str) -> Vec<PathBuf> {
+        let mut env_paths = Vec::new();
+
+        let in_project = root_path.join(".venv");
+        if is_venv(&in_project) {
+            env_paths.push(in_project);
+        }
+
+        let prefix = format!("{}-", project.to_lowercase().replace(['_', '.', ' '], "-"));
+        let virtualenvs = Self::cache_dir().map(|dir| dir.join("virtualenvs"));
+        if let Some(Ok(entries)) = virtualenvs.map(fs::read_dir) {
+            for entry in entries.filter_map(Result::ok) {
+                let name = entry.file_name().to_string_lossy().to_lowercase();
+                if name.starts_with(&prefix) && is_venv(&entry.path()) {
+                    env_paths.push(entry.path());
+                }
+            }
+        }
+
+        env_paths
+    }
+}
+
+impl EnvironmentProvider for PoetryProvider {
+    fn name(&self) -> &'static str {
+        "poetry"
+    }
+
+    fn label(&self) -> &'static str {
+        "Poetry"
+    }
+
+    fn discover(&self, cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        let Some(root_path) = cx.root_path else {
+            return Ok(Vec::new());
+        };
+        let Some(project) = Self::project_name(cx) else {
+            return Ok(Vec::new());
+        };
+
+        let env_paths = Self::env_list(cx, root_path)
+            .unwrap_or_else(|_| Self::env_fallback_paths(root_path, &project));
+
+        Ok(env_paths
+            .into_iter()
+            .filter_map(|env_path| {
+                let python_path = find_python_executable(&env_path)?;
+                Some(PythonEnvironment {
+                    name: env_path.file_name()?.to_string_lossy().into_owned(),
+                    python_path,
+                    provider: self.name().to_string(),
+                    project: Some(project.clone()),
+                    ..Default::default()
+                })
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,83 @@
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::environment::{find_python_executable, home_dir, PythonEnvironment};
+use crate::error::PyEnvError;
+use std::fs;
+use std::path::PathBuf;
+
+/// pyenv base interpreters from `$PYENV_ROOT/versions/<version>` and
+/// pyenv-virtualenv environments from `$PYENV_ROOT/versions/<version>/envs/<name>`.
+pub(crate) struct PyenvProvider;
+
+impl PyenvProvider {
+    fn pyenv_root() -> Option<PathBuf> {
+        std::env::var_os("PYENV_ROOT")
+            .map(PathBuf::from)
+            .or_else(|| home_dir().map(|home| home.join(".pyenv")))
+    }
+}
+
+impl EnvironmentProvider for PyenvProvider {
+    fn name(&self) -> &'static str {
+        "pyenv"
+    }
+
+    fn label(&self) -> &'static str {
+        "pyenv"
+    }
+
+    fn discover(&self, _cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        let mut envs = Vec::new();
+        let Some(versions_dir) = Self::pyenv_root().map(|root| root.join("versions")) else {
+            return Ok(envs);
+        };
+        let Ok(entries) = fs::read_dir(&versions_dir) else {
+            return Ok(envs);
+        };
+
+        for entry in entries.filter_map(Result::ok) {
+            let version_path = entry.path();
+            // pyenv-virtualenv symlinks each env into versions/; we pick those up from envs/.
+            if entry
+                .file_type()
+                .map_or(true, |file_type| file_type.is_symlink())
+            {
+                continue;
+            }
+
+            let version = entry.file_name().to_string_lossy().into_owned();
+            if let Some(python_path) = find_python_executable(&version_path) {
+                envs.push(PythonEnvironment {
+                    name: version.clone(),
+                    python_path,
+                    provider: self.name().to_string(),
+                    ..Default::default()
+                });
+            }
+
+            let Ok(virtualenvs) = fs::read_dir(version_path.join("envs")) else {
+                continue;
+            };
+            for virtualenv in virtualenvs.filter_map(Result::ok) {
+                if let Some(python_path) = find_python_executable(&virtualenv.path()) {
+                    envs.push(PythonEnvironment {
+                        name: virtualenv.file_name().to_string_lossy().into_owned(),
+                        python_path,
+                        provider: self.name().to_string(),
+                        project: Some(version.clone()),
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+
+        envs.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(envs)
+    }
+
+    fn watched_paths(&self, _cx: &DiscoveryContext) -> Vec<PathBuf> {
+        Self::pyenv_root()
+            .map(|root| root.join("versions"))
+            .into_iter()
+            .collect()
+    }
+}
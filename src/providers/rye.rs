@@ -0,0 +1,73 @@
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::environment::{find_python_executable, home_dir, PythonEnvironment};
+use crate::error::PyEnvError;
+use crate::project::read_toml_string;
+use std::fs;
+use std::path::PathBuf;
+
+/// Rye-managed toolchains from `$RYE_HOME/py/<toolchain>` and the project
+/// `.venv` created by `rye sync`.
+pub(crate) struct RyeProvider;
+
+impl RyeProvider {
+    fn rye_home() -> Option<PathBuf> {
+        std::env::var_os("RYE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home_dir().map(|home| home.join(".rye")))
+    }
+
+    fn project_env(&self, cx: &DiscoveryContext) -> Option<PythonEnvironment> {
+        let pyproject = cx.read_project_file("pyproject.toml")?;
+        if !pyproject.contains("[tool.rye]") {
+            return None;
+        }
+        let env_path = cx.root_path?.join(".venv");
+        Some(PythonEnvironment {
+            name: ".venv".to_string(),
+            python_path: find_python_executable(&env_path)?,
+            provider: self.name().to_string(),
+            project: read_toml_string(&pyproject, "project", "name"),
+            ..Default::default()
+        })
+    }
+}
+
+impl EnvironmentProvider for RyeProvider {
+    fn name(&self) -> &'static str {
+        "rye"
+    }
+
+    fn label(&self) -> &'static str {
+        "Rye"
+    }
+
+    fn discover(&self, cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        let mut envs = Vec::new();
+        if let Some(env) = self.project_env(cx) {
+            envs.push(env);
+        }
+
+        let toolchains_dir = Self::rye_home().map(|home| home.join("py"));
+        if let Some(Ok(entries)) = toolchains_dir.map(fs::read_dir) {
+            for entry in entries.filter_map(Result::ok) {
+                let toolchain_path = entry.path();
+                // Older Rye releases unpacked toolchains into an `install` subdirectory.
+                let Some(python_path) = find_python_executable(&toolchain_path)
+                    .or_else(|| find_python_executable(&toolchain_path.join("install")))
+                else {
+                    continue;
+                };
+                let name = entry.file_name().to_string_lossy().into_owned();
+                envs.push(PythonEnvironment {
+                    version: name.split_once('@').map(|(_, version)| version.to_string()),
+                    name,
+                    python_path,
+                    provider: self.name().to_string(),
+                    ..Default::default()
+                });
+            }
+        }
+
+        Ok(envs)
+    }
+}
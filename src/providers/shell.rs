@@ -0,0 +1,52 @@
+use super::conda::CondaProvider;
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::environment::{find_python_executable, PythonEnvironment};
+use crate::error::PyEnvError;
+use std::path::PathBuf;
+
+/// The environment activated in the shell Zed was launched from. Usually
+/// another provider has already found it, in which case this only marks the
+/// existing entry as active.
+pub(crate) struct ShellActivatedProvider;
+
+impl EnvironmentProvider for ShellActivatedProvider {
+    fn name(&self) -> &'static str {
+        "shell"
+    }
+
+    fn label(&self) -> &'static str {
+        "Launching shell"
+    }
+
+    fn discover(&self, cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        let mut envs = Vec::new();
+        if let Some(virtual_env) = cx.env_var("VIRTUAL_ENV") {
+            let env_path = PathBuf::from(virtual_env);
+            if let Some(python_path) = find_python_executable(&env_path) {
+                envs.push(PythonEnvironment {
+                    name: env_path.file_name().map_or_else(
+                        || env_path.display().to_string(),
+                        |name| name.to_string_lossy().into_owned(),
+                    ),
+                    python_path,
+                    provider: self.name().to_string(),
+                    active: true,
+                    ..Default::default()
+                });
+            }
+        }
+        if let Some(conda_prefix) = cx.env_var("CONDA_PREFIX") {
+            let env_path = PathBuf::from(conda_prefix);
+            if let Some(python_path) = find_python_executable(&env_path) {
+                envs.push(PythonEnvironment {
+                    name: CondaProvider::env_name(&env_path),
+                    python_path,
+                    provider: self.name().to_string(),
+                    active: true,
+                    ..Default::default()
+                });
+            }
+        }
+        Ok(envs)
+    }
+}
@@ -0,0 +1,68 @@
+use super::windows_store::is_windows_apps_dir;
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::environment::{is_venv, PythonEnvironment};
+use crate::error::PyEnvError;
+use std::fs;
+use std::path::Path;
+
+/// Interpreters on `PATH`, so there is something to select even without any
+/// virtual environment.
+pub(crate) struct SystemProvider;
+
+impl SystemProvider {
+    const EXECUTABLE_NAMES: &'static [&'static str] =
+        &["python3", "python", "python3.exe", "python.exe"];
+
+    /// Whether a `PATH` directory should be ignored: version-manager shims are
+    /// scripts rather than interpreters, an activated environment's `bin`
+    /// is already reported by the provider that owns it, and the Microsoft
+    /// Store's aliases may be stubs (see [`super::windows_store`]).
+    fn is_excluded_dir(dir: &Path) -> bool {
+        dir.file_name().is_some_and(|name| name == "shims")
+            || dir.parent().is_some_and(is_venv)
+            || is_windows_apps_dir(dir)
+    }
+}
+
+impl EnvironmentProvider for SystemProvider {
+    fn name(&self) -> &'static str {
+        "system"
+    }
+
+    fn label(&self) -> &'static str {
+        "System"
+    }
+
+    fn discover(&self, cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        let path_var = cx
+            .env_var("PATH")
+            .ok_or_else(|| PyEnvError::unavailable(self.name(), "PATH is not set"))?;
+        let mut envs: Vec<PythonEnvironment> = Vec::new();
+
+        for dir in std::env::split_paths(&path_var) {
+            if Self::is_excluded_dir(&dir) {
+                continue;
+            }
+            for executable in Self::EXECUTABLE_NAMES {
+                // Resolve symlinks so python -> python3 -> python3.12 is listed once.
+                let Ok(python_path) = fs::canonicalize(dir.join(executable)) else {
+                    continue;
+                };
+                if !python_path.is_file() || envs.iter().any(|env| env.python_path == python_path) {
+                    continue;
+                }
+                envs.push(PythonEnvironment {
+                    name: python_path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                    python_path,
+                    provider: self.name().to_string(),
+                    ..Default::default()
+                });
+            }
+        }
+
+        Ok(envs)
+    }
+}
@@ -0,0 +1,217 @@
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::environment::{find_python_executable, PythonEnvironment};
+use crate::error::PyEnvError;
+use std::fs;
+
+/// Environments tox creates under `<worktree>/.tox/<env>`, ordered as the
+/// `envlist` of `tox.ini` declares them.
+pub(crate) struct ToxProvider;
+
+impl ToxProvider {
+    /// Reads the `envlist` (or tox 4's `env_list`) of the `[tox]` section,
+    /// expanding generative names: `py{310,311}-django{42,50}` yields four
+    /// environments.
+    fn envlist(tox_ini: &str) -> Vec<String> {
+        let mut in_tox_section = false;
+        let mut value: Option<String> = None;
+        for line in tox_ini.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with(';') {
+                continue;
+            }
+            if trimmed.starts_with('[') {
+                if value.is_some() {
+                    break;
+                }
+                in_tox_section = trimmed == "[tox]";
+                continue;
+            }
+            if !in_tox_section {
+                continue;
+            }
+            match &mut value {
+                // Indented lines continue the previous value.
+                Some(value) if line.starts_with(char::is_whitespace) => {
+                    value.push('\n');
+                    value.push_str(trimmed);
+                }
+                Some(_) => break,
+                None => {
+                    if let Some((key, rest)) = trimmed.split_once('=') {
+                        if matches!(key.trim(), "envlist" | "env_list") {
+                            value = Some(rest.trim().to_string());
+                        }
+                    }
+                }
+            }
+        }
+
+        let Some(value) = value else {
+            return Vec::new();
+        };
+        // Commas inside braces separate alternatives, not environments.
+        let mut items = Vec::new();
+        let mut current = String::new();
+        let mut depth = 0usize;
+        for c in value.chars() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth = depth.saturating_sub(1),
+                ',' | '\n' if depth == 0 => {
+                    items.push(std::mem::take(&mut current));
+                    continue;
+                }
+                _ => {}
+            }
+            current.push(c);
+        }
+        items.push(current);
+
+        items
+            .iter()
+            .map(|item| item.trim())
+            .filter(|item| !item.is_empty())
+            .flat_map(Self::expand)
+            .collect()
+    }
+
+    fn expand(pattern: &str) -> Vec<String> {
+        let Some((open, close)) = pattern
+            .find('{')
+            .and_then(|open| Some((open, open + pattern[open..].find('}')?)))
+        else {
+            return vec![pattern.to_string()];
+        };
+        pattern[open + 1..close]
+            .split(',')
+            .flat_map(|alternative| {
+                Self::expand(&format!(
+                    "{}{}{}",
+                    &pattern[..open],
+                    alternative.trim(),
+                    &pattern[close + 1..]
+                ))
+            })
+            .collect()
+    }
+}
+
+impl EnvironmentProvider for ToxProvider {
+    fn name(&self) -> &'static str {
+        "tox"
+    }
+
+    fn label(&self) -> &'static str {
+        "tox"
+    }
+
+    fn discover(&self, cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        let Some(root_path) = cx.root_path else {
+            return Ok(Vec::new());
+        };
+        let Ok(entries) = fs::read_dir(root_path.join(".tox")) else {
+            return Ok(Vec::new());
+        };
+        let envlist = cx
+            .read_project_file("tox.ini")
+            .map(|tox_ini| Self::envlist(&tox_ini))
+            .unwrap_or_default();
+
+        let mut environments: Vec<PythonEnvironment> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                // tox 4 builds packages in `.tox/.pkg`, which isn't a test environment.
+                if name.starts_with('.') {
+                    return None;
+                }
+                Some(PythonEnvironment {
+                    python_path: find_python_executable(&entry.path())?,
+                    factors: name.split('-').map(str::to_string).collect(),
+                    name,
+                    provider: self.name().to_string(),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        environments.sort_by_key(|env| {
+            let position = envlist.iter().position(|name| *name == env.name);
+            (position.unwrap_or(envlist.len()), env.name.clone())
+        });
+        Ok(environments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+    use crate::ExtensionSettings;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Mutex;
+
+    #[test]
+    fn expands_generative_envlist() {
+        let tox_ini = "\
+[tox]
+requires = tox>=4
+envlist =
+    py{310, 311}-django{42,50}
+    lint, docs
+
+[testenv]
+deps = pytest
+";
+        assert_eq!(
+            ToxProvider::envlist(tox_ini),
+            [
+                "py310-django42",
+                "py310-django50",
+                "py311-django42",
+                "py311-django50",
+                "lint",
+                "docs",
+            ]
+        );
+        assert_eq!(
+            ToxProvider::envlist("[tox]\nenv_list = py312, type\n"),
+            ["py312", "type"]
+        );
+        assert!(ToxProvider::envlist("[testenv]\nenvlist = py312\n").is_empty());
+    }
+
+    #[test]
+    fn lists_tox_environments_in_envlist_order() {
+        let fixture = Fixture::new();
+        fixture.write("tox.ini", "[tox]\nenvlist = py312-django50, lint\n");
+        let lint = fixture.venv(".tox/lint");
+        let django = fixture.venv(".tox/py312-django50");
+        let adhoc = fixture.venv(".tox/format");
+        fixture.venv(".tox/.pkg");
+        fixture.touch(".tox/log/1-commands.log");
+
+        let settings = ExtensionSettings::default();
+        let cx = DiscoveryContext {
+            root_path: Some(fixture.path()),
+            settings: &settings,
+            snapshot: None,
+            warnings: Mutex::new(Vec::new()),
+            commands: Mutex::new(Vec::new()),
+            dirs_scanned: AtomicUsize::new(0),
+        };
+        let envs = ToxProvider.discover(&cx).unwrap();
+        let found: Vec<_> = envs
+            .iter()
+            .map(|env| (env.name.as_str(), env.python_path.clone()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("py312-django50", django),
+                ("lint", lint),
+                ("format", adhoc)
+            ]
+        );
+        assert_eq!(envs[0].factors, ["py312", "django50"]);
+    }
+}
@@ -0,0 +1,126 @@
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::environment::{find_python_executable, PythonEnvironment};
+use crate::error::PyEnvError;
+use crate::project::read_toml_string;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// uv projects' environments plus the toolchains listed by `uv python list`.
+pub(crate) struct UvProvider;
+
+impl UvProvider {
+    /// Whether `env_path` was created by uv, which records itself in `pyvenv.cfg`.
+    fn is_uv_venv(env_path: &Path) -> bool {
+        fs::read_to_string(env_path.join("pyvenv.cfg")).is_ok_and(|contents| {
+            contents.lines().any(|line| {
+                line.split_once('=')
+                    .is_some_and(|(key, _)| key.trim() == "uv")
+            })
+        })
+    }
+
+    fn project_env(&self, cx: &DiscoveryContext) -> Option<PythonEnvironment> {
+        let root_path = cx.root_path?;
+        let env_path = cx
+            .env_var("UV_PROJECT_ENVIRONMENT")
+            .map(|env_path| root_path.join(env_path))
+            .unwrap_or_else(|| root_path.join(".venv"));
+        let is_uv_project = cx.has_project_file("uv.lock");
+        if !is_uv_project && !Self::is_uv_venv(&env_path) {
+            return None;
+        }
+
+        let python_path = find_python_executable(&env_path)?;
+        let project = cx
+            .read_project_file("pyproject.toml")
+            .and_then(|pyproject| read_toml_string(&pyproject, "project", "name"));
+
+        Some(PythonEnvironment {
+            name: env_path.file_name()?.to_string_lossy().into_owned(),
+            python_path,
+            provider: self.name().to_string(),
+            project,
+            ..Default::default()
+        })
+    }
+
+    /// Parses `uv python list --only-installed`, whose lines look like
+    /// `cpython-3.12.1-linux-x86_64-gnu    /path/to/bin/python3.12 -> python3`.
+    fn parse_python_list(&self, output: &str) -> Vec<PythonEnvironment> {
+        output
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                let key = parts.next()?;
+                let python_path = PathBuf::from(parts.next()?);
+                if !python_path.is_file() {
+                    return None;
+                }
+                let version = key.split('-').nth(1).map(str::to_string);
+                Some(PythonEnvironment {
+                    name: key.to_string(),
+                    python_path,
+                    provider: self.name().to_string(),
+                    version,
+                    ..Default::default()
+                })
+            })
+            .collect()
+    }
+}
+
+impl EnvironmentProvider for UvProvider {
+    fn name(&self) -> &'static str {
+        "uv"
+    }
+
+    fn label(&self) -> &'static str {
+        "uv"
+    }
+
+    fn discover(&self, cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        let mut envs = Vec::new();
+        if let Some(env) = self.project_env(cx) {
+            envs.push(env);
+        }
+
+        // uv being absent is only an error if there was nothing else to report.
+        match cx.run_tool("uv", |command| {
+            command.args(["python", "list", "--only-installed"]);
+        }) {
+            Ok(output) => envs.extend(self.parse_python_list(&output)),
+            Err(error) if envs.is_empty() => return Err(error),
+            Err(_) => {}
+        }
+
+        Ok(envs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+
+    #[test]
+    fn parses_python_list_output() {
+        let fixture = Fixture::new();
+        let installed = fixture.touch("uv/python/cpython-3.12.1-linux-x86_64-gnu/bin/python3.12");
+        let output = format!(
+            "cpython-3.12.1-linux-x86_64-gnu    {} -> python3\n\
+             cpython-3.11.7-linux-x86_64-gnu    {}\n\
+             pypy-3.10.14-linux-x86_64-gnu      <download available>\n",
+            installed.display(),
+            fixture
+                .path()
+                .join("uv/python/removed/bin/python3.11")
+                .display(),
+        );
+
+        let envs = UvProvider.parse_python_list(&output);
+        assert_eq!(envs.len(), 1);
+        assert_eq!(envs[0].name, "cpython-3.12.1-linux-x86_64-gnu");
+        assert_eq!(envs[0].python_path, installed);
+        assert_eq!(envs[0].version.as_deref(), Some("3.12.1"));
+    }
+}
@@ -0,0 +1,170 @@
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::environment::{find_python_executable, is_venv, PythonEnvironment};
+use crate::error::PyEnvError;
+use crate::SCAN_DEPTH_LIMIT;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Virtual environments found by recursively scanning the worktree.
+pub(crate) struct WorktreeVenvProvider;
+
+impl WorktreeVenvProvider {
+    /// `visited` holds the canonical path of every directory already scanned,
+    /// so symlinks pointing back up the tree (or at each other) are followed
+    /// at most once.
+    fn find_venvs_rec(
+        &self,
+        dir: &Path,
+        depth: usize,
+        cx: &DiscoveryContext,
+        visited: &mut HashSet<PathBuf>,
+    ) -> Vec<PythonEnvironment> {
+        let settings = cx.settings;
+        let mut venvs = Vec::new();
+        let canonical = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+        if !visited.insert(canonical) {
+            return venvs;
+        }
+
+        cx.note_dir_scanned();
+        match fs::read_dir(dir) {
+            Ok(entries) => {
+                for entry in entries.filter_map(Result::ok) {
+                    let path = entry.path();
+                    let ignored = settings
+                        .scan_ignore
+                        .iter()
+                        .any(|name| entry.file_name().to_string_lossy() == name.as_str());
+                    if path.is_dir() && !ignored {
+                        if is_venv(&path) {
+                            if let Some(python_path) = find_python_executable(&path) {
+                                venvs.push(PythonEnvironment {
+                                    name: path.file_name().unwrap().to_string_lossy().into_owned(),
+                                    python_path,
+                                    provider: self.name().to_string(),
+                                    ..Default::default()
+                                });
+                            }
+                        } else if depth < settings.scan_max_depth.min(SCAN_DEPTH_LIMIT) {
+                            // Recursively search subdirectories
+                            venvs.extend(self.find_venvs_rec(&path, depth + 1, cx, visited));
+                        }
+                    }
+                }
+            }
+            Err(e) => cx.warn(format!("Could not read {}: {}", dir.display(), e)),
+        }
+        venvs
+    }
+}
+
+impl EnvironmentProvider for WorktreeVenvProvider {
+    fn name(&self) -> &'static str {
+        "venv"
+    }
+
+    fn label(&self) -> &'static str {
+        "Workspace venvs"
+    }
+
+    fn discover(&self, cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        Ok(cx
+            .root_path
+            .map(|root_path| self.find_venvs_rec(root_path, 0, cx, &mut HashSet::new()))
+            .unwrap_or_default())
+    }
+
+    /// The worktree root, where `python -m venv .venv` lands, and the
+    /// directories holding the `project_env_dirs` (e.g. `.direnv`). Deeper
+    /// environments are picked up on the next scan after the cache expires.
+    fn watched_paths(&self, cx: &DiscoveryContext) -> Vec<PathBuf> {
+        let Some(root_path) = cx.root_path else {
+            return Vec::new();
+        };
+        let mut paths = vec![root_path.to_path_buf()];
+        for pattern in &cx.settings.project_env_dirs {
+            if let Some(parent) = Path::new(pattern)
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+            {
+                paths.push(root_path.join(parent));
+            }
+        }
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+    use crate::ExtensionSettings;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Mutex;
+
+    fn scan(root: &Path, settings: &ExtensionSettings) -> Vec<PythonEnvironment> {
+        let cx = DiscoveryContext {
+            root_path: Some(root),
+            settings,
+            snapshot: None,
+            warnings: Mutex::new(Vec::new()),
+            commands: Mutex::new(Vec::new()),
+            dirs_scanned: AtomicUsize::new(0),
+        };
+        let mut envs = WorktreeVenvProvider.discover(&cx).unwrap();
+        envs.sort_by(|a, b| a.python_path.cmp(&b.python_path));
+        envs
+    }
+
+    #[test]
+    fn finds_nested_venvs() {
+        let fixture = Fixture::new();
+        let root_venv = fixture.venv(".venv");
+        let nested_venv = fixture.venv("services/api/venv");
+        fixture.touch("services/api/main.py");
+
+        let envs = scan(fixture.path(), &ExtensionSettings::default());
+        let found: Vec<_> = envs
+            .iter()
+            .map(|env| (env.name.as_str(), env.python_path.clone()))
+            .collect();
+        assert_eq!(found, [(".venv", root_venv), ("venv", nested_venv)]);
+        assert!(envs.iter().all(|env| env.provider == "venv"));
+    }
+
+    #[test]
+    fn detects_windows_venv_without_pyvenv_cfg() {
+        let fixture = Fixture::new();
+        fixture.touch("env/Scripts/activate.bat");
+        let python_path = fixture.touch("env/Scripts/python.exe");
+
+        let envs = scan(fixture.path(), &ExtensionSettings::default());
+        assert_eq!(envs.len(), 1);
+        assert_eq!(envs[0].python_path, python_path);
+    }
+
+    #[test]
+    fn skips_ignored_and_too_deep_directories() {
+        let fixture = Fixture::new();
+        fixture.venv("node_modules/pkg/.venv");
+        fixture.venv("a/b/c/.venv");
+        let shallow = fixture.venv("a/.venv");
+
+        let settings = ExtensionSettings {
+            scan_max_depth: 1,
+            ..Default::default()
+        };
+        let envs = scan(fixture.path(), &settings);
+        assert_eq!(envs.len(), 1);
+        assert_eq!(envs[0].python_path, shallow);
+    }
+
+    #[test]
+    fn ignores_venvs_without_an_interpreter() {
+        let fixture = Fixture::new();
+        fixture.write("broken/pyvenv.cfg", "home = /usr/bin\n");
+
+        assert!(scan(fixture.path(), &ExtensionSettings::default()).is_empty());
+    }
+}
@@ -0,0 +1,47 @@
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::environment::{environments_in, home_dir, PythonEnvironment};
+use crate::error::PyEnvError;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// virtualenvwrapper environments under `$WORKON_HOME` (default `~/.virtualenvs`).
+pub(crate) struct VirtualenvwrapperProvider;
+
+impl VirtualenvwrapperProvider {
+    fn workon_home() -> Option<PathBuf> {
+        std::env::var_os("WORKON_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home_dir().map(|home| home.join(".virtualenvs")))
+    }
+}
+
+impl EnvironmentProvider for VirtualenvwrapperProvider {
+    fn name(&self) -> &'static str {
+        "virtualenvwrapper"
+    }
+
+    fn label(&self) -> &'static str {
+        "virtualenvwrapper"
+    }
+
+    fn discover(&self, _cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        let workon_home = Self::workon_home()
+            .ok_or_else(|| PyEnvError::unavailable(self.name(), "WORKON_HOME is not set"))?;
+
+        let mut envs = environments_in(&workon_home, self.name());
+        for env in &mut envs {
+            // `mkproject`/`setvirtualenvproject` record the bound project directory here.
+            let project_file = workon_home.join(&env.name).join(".project");
+            env.project = fs::read_to_string(project_file).ok().and_then(|project| {
+                Path::new(project.trim())
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+            });
+        }
+        Ok(envs)
+    }
+
+    fn watched_paths(&self, _cx: &DiscoveryContext) -> Vec<PathBuf> {
+        Self::workon_home().into_iter().collect()
+    }
+}
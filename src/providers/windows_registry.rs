@@ -0,0 +1,148 @@
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::environment::PythonEnvironment;
+use crate::error::PyEnvError;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Interpreters registered under `Software\Python` in the Windows registry
+/// (PEP 514): python.org installers, Anaconda and other distributions, found
+/// whether or not they were added to `PATH`. The registry is read with
+/// `reg query`, so this provider finds nothing outside Windows.
+pub(crate) struct WindowsRegistryProvider;
+
+impl WindowsRegistryProvider {
+    /// Per-user installs, machine-wide installs, and 32-bit installs on 64-bit
+    /// Windows.
+    const ROOT_KEYS: &'static [&'static str] = &[
+        r"HKCU\Software\Python",
+        r"HKLM\Software\Python",
+        r"HKLM\Software\Wow6432Node\Python",
+    ];
+
+    /// Reads `reg query <key> /s` output into each key's values.
+    fn parse_reg_query(output: &str) -> Vec<(String, HashMap<String, String>)> {
+        let mut keys: Vec<(String, HashMap<String, String>)> = Vec::new();
+        for line in output.lines() {
+            if line.starts_with("HKEY_") {
+                keys.push((line.trim().to_string(), HashMap::new()));
+                continue;
+            }
+            // Values are indented: `    <name>    REG_SZ    <data>`.
+            let Some((_, values)) = keys.last_mut() else {
+                continue;
+            };
+            let mut fields = line.trim().splitn(3, "    ");
+            if let (Some(name), Some(kind), Some(data)) =
+                (fields.next(), fields.next(), fields.next())
+            {
+                if kind.starts_with("REG_") {
+                    values.insert(name.to_string(), data.trim().to_string());
+                }
+            }
+        }
+        keys
+    }
+
+    /// The registered interpreters in `reg query` output, as `(tag, path)`.
+    /// PEP 514 keys are `<root>\<Company>\<Tag>\InstallPath`, holding the
+    /// install directory and optionally the interpreter's path. The `py`
+    /// launcher's own `PyLauncher` company is skipped.
+    fn registered_interpreters(output: &str) -> Vec<(String, String)> {
+        Self::parse_reg_query(output)
+            .into_iter()
+            .filter_map(|(key, values)| {
+                let tag_key = key.strip_suffix(r"\InstallPath")?;
+                let mut segments = tag_key.rsplit('\\');
+                let tag = segments.next()?;
+                let company = segments.next()?;
+                if company.eq_ignore_ascii_case("PyLauncher") || segments.next()? != "Python" {
+                    return None;
+                }
+                let python_path = values.get("ExecutablePath").cloned().or_else(|| {
+                    let install_dir = values.get("(Default)")?;
+                    Some(format!(
+                        r"{}\python.exe",
+                        install_dir.trim_end_matches('\\')
+                    ))
+                })?;
+                Some((tag.to_string(), python_path))
+            })
+            .collect()
+    }
+}
+
+impl EnvironmentProvider for WindowsRegistryProvider {
+    fn name(&self) -> &'static str {
+        "windows-registry"
+    }
+
+    fn label(&self) -> &'static str {
+        "Windows registry"
+    }
+
+    fn discover(&self, cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        let mut envs: Vec<PythonEnvironment> = Vec::new();
+        for root_key in Self::ROOT_KEYS {
+            let (_, output) = cx.run_tool_unchecked("reg", |command| {
+                command.args(["query", root_key, "/s"]);
+            })?;
+            // `reg` exits with 1 when the key doesn't exist, which is routine.
+            let Ok(output) =
+                output.map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+            else {
+                continue;
+            };
+            for (tag, python_path) in Self::registered_interpreters(&output) {
+                let python_path = PathBuf::from(python_path);
+                // Uninstallers sometimes leave their keys behind.
+                if !python_path.is_file() || envs.iter().any(|env| env.python_path == python_path) {
+                    continue;
+                }
+                envs.push(PythonEnvironment {
+                    name: tag,
+                    python_path,
+                    provider: self.name().to_string(),
+                    ..Default::default()
+                });
+            }
+        }
+        Ok(envs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_pep_514_install_paths() {
+        let output = r"
+HKEY_CURRENT_USER\Software\Python\PythonCore\3.12
+    DisplayName    REG_SZ    Python 3.12 (64-bit)
+    SysVersion    REG_SZ    3.12
+
+HKEY_CURRENT_USER\Software\Python\PythonCore\3.12\InstallPath
+    (Default)    REG_SZ    C:\Users\dev\AppData\Local\Programs\Python\Python312\
+    ExecutablePath    REG_SZ    C:\Users\dev\AppData\Local\Programs\Python\Python312\python.exe
+
+HKEY_CURRENT_USER\Software\Python\ContinuumAnalytics\Anaconda39-64\InstallPath
+    (Default)    REG_SZ    C:\ProgramData\Anaconda3\
+
+HKEY_CURRENT_USER\Software\Python\PyLauncher\InstallPath
+    (Default)    REG_SZ    C:\Windows\py.exe
+";
+        assert_eq!(
+            WindowsRegistryProvider::registered_interpreters(output),
+            [
+                (
+                    "3.12".to_string(),
+                    r"C:\Users\dev\AppData\Local\Programs\Python\Python312\python.exe".to_string()
+                ),
+                (
+                    "Anaconda39-64".to_string(),
+                    r"C:\ProgramData\Anaconda3\python.exe".to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::environment::PythonEnvironment;
+use crate::error::PyEnvError;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Microsoft Store Pythons, through the execution aliases each installed
+/// version adds under `%LOCALAPPDATA%\Microsoft\WindowsApps`.
+///
+/// The bare `python.exe` and `python3.exe` in that directory are aliases too,
+/// but on a machine without a Store Python they are stubs that open the
+/// Store instead of running anything, so the directory is skipped when
+/// scanning `PATH` and only the per-package aliases are listed.
+pub(crate) struct WindowsStoreProvider;
+
+/// Publisher prefix of the Python packages in the Microsoft Store.
+const PACKAGE_PREFIX: &str = "PythonSoftwareFoundation.Python.";
+
+/// Whether `dir` is a `WindowsApps` directory of execution aliases.
+pub(super) fn is_windows_apps_dir(dir: &Path) -> bool {
+    dir.file_name()
+        .is_some_and(|name| name.eq_ignore_ascii_case("WindowsApps"))
+}
+
+impl WindowsStoreProvider {
+    /// The per-package aliases in `apps_dir`, e.g.
+    /// `PythonSoftwareFoundation.Python.3.12_qbz5n2kfra8p0\python.exe`, named
+    /// after the Python version in the package name.
+    fn store_interpreters(&self, apps_dir: &Path) -> Vec<PythonEnvironment> {
+        let Ok(entries) = fs::read_dir(apps_dir) else {
+            return Vec::new();
+        };
+        let mut envs: Vec<PythonEnvironment> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let package = entry.file_name().to_string_lossy().into_owned();
+                let version = package.strip_prefix(PACKAGE_PREFIX)?.split('_').next()?;
+                let python_path = entry.path().join("python.exe");
+                // Aliases are reparse points that don't resolve like symlinks,
+                // so check the link itself rather than its target.
+                fs::symlink_metadata(&python_path).ok()?;
+                Some(PythonEnvironment {
+                    name: version.to_string(),
+                    python_path,
+                    provider: self.name().to_string(),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        envs.sort_by(|a, b| a.name.cmp(&b.name));
+        envs
+    }
+}
+
+impl EnvironmentProvider for WindowsStoreProvider {
+    fn name(&self) -> &'static str {
+        "windows-store"
+    }
+
+    fn label(&self) -> &'static str {
+        "Microsoft Store"
+    }
+
+    fn discover(&self, cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        let local_app_data = cx
+            .env_var("LOCALAPPDATA")
+            .ok_or_else(|| PyEnvError::unavailable(self.name(), "LOCALAPPDATA is not set"))?;
+        let apps_dir = PathBuf::from(local_app_data)
+            .join("Microsoft")
+            .join("WindowsApps");
+        Ok(self.store_interpreters(&apps_dir))
+    }
+
+    fn watched_paths(&self, cx: &DiscoveryContext) -> Vec<PathBuf> {
+        cx.env_var("LOCALAPPDATA")
+            .map(|local_app_data| {
+                PathBuf::from(local_app_data)
+                    .join("Microsoft")
+                    .join("WindowsApps")
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+
+    #[test]
+    fn lists_package_aliases_but_not_stubs() {
+        let fixture = Fixture::new();
+        fixture.touch("WindowsApps/python.exe");
+        fixture.touch("WindowsApps/python3.exe");
+        fixture.touch("WindowsApps/PythonSoftwareFoundation.Python.3.12_qbz5n2kfra8p0/python.exe");
+        fixture.touch("WindowsApps/Microsoft.WindowsTerminal_8wekyb3d8bbwe/wt.exe");
+
+        let apps_dir = fixture.path().join("WindowsApps");
+        assert!(is_windows_apps_dir(&apps_dir));
+        let envs = WindowsStoreProvider.store_interpreters(&apps_dir);
+        assert_eq!(envs.len(), 1);
+        assert_eq!(envs[0].name, "3.12");
+        assert!(envs[0]
+            .python_path
+            .starts_with(apps_dir.join("PythonSoftwareFoundation.Python.3.12_qbz5n2kfra8p0")));
+    }
+}
@@ -0,0 +1,133 @@
+use super::{DiscoveryContext, EnvironmentProvider};
+use crate::environment::{Guest, PythonEnvironment};
+use crate::error::PyEnvError;
+use std::path::{Path, PathBuf};
+
+/// Interpreters inside the WSL distributions of a Windows machine: each
+/// distro's `python3`, conda installs and their environments, pyenv
+/// versions and virtualenvwrapper environments. They are listed with
+/// [`PythonEnvironment::guest`] set and run through `wsl -d <distro>`.
+pub(crate) struct WslProvider;
+
+impl WslProvider {
+    /// Prints `<path>\t<version>` for every interpreter in the distro, with
+    /// one `wsl` call per distro since each starts the distro's shell.
+    const SCAN_SCRIPT: &'static str = r#"for p in "$(command -v python3)" \
+    "$HOME"/miniconda3/bin/python "$HOME"/miniconda3/envs/*/bin/python \
+    "$HOME"/anaconda3/bin/python "$HOME"/anaconda3/envs/*/bin/python \
+    "$HOME"/miniforge3/bin/python "$HOME"/miniforge3/envs/*/bin/python \
+    "$HOME"/.pyenv/versions/*/bin/python "$HOME"/.virtualenvs/*/bin/python; do
+    [ -x "$p" ] && printf '%s\t%s\n' "$p" "$("$p" -c 'import platform; print(platform.python_version())' 2>/dev/null)"
+done"#;
+
+    /// Distro names from `wsl --list --quiet`. wsl.exe writes UTF-16 unless
+    /// `WSL_UTF8` is set, so stray NULs are dropped either way.
+    fn parse_distros(output: &str) -> Vec<String> {
+        output
+            .replace('\0', "")
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Reads [`Self::SCAN_SCRIPT`]'s output for `distro`. Environments are
+    /// named after their directory and prefixed with the distro, e.g.
+    /// `Ubuntu/ml` for `~/miniconda3/envs/ml`.
+    fn parse_interpreters(&self, distro: &str, output: &str) -> Vec<PythonEnvironment> {
+        let mut envs: Vec<PythonEnvironment> = Vec::new();
+        for line in output.lines() {
+            let (path, version) = line.split_once('\t').unwrap_or((line, ""));
+            let path = path.trim();
+            if !path.starts_with('/') || envs.iter().any(|env| env.python_path == Path::new(path)) {
+                continue;
+            }
+            let segments: Vec<&str> = path.split('/').collect();
+            // `<root>/bin/python`: a conda base install, a pyenv version or
+            // a virtualenv; anything else is the distro's own python3.
+            let env_name = match segments.as_slice() {
+                [.., "envs" | "versions" | ".virtualenvs", name, "bin", _] => name,
+                [.., install @ ("miniconda3" | "anaconda3" | "miniforge3"), "bin", _] => install,
+                _ => "python3",
+            };
+            envs.push(PythonEnvironment {
+                name: format!("{}/{}", distro, env_name),
+                python_path: PathBuf::from(path),
+                provider: self.name().to_string(),
+                version: Some(version.trim().to_string()).filter(|version| !version.is_empty()),
+                guest: Some(Guest::Wsl(distro.to_string())),
+                ..Default::default()
+            });
+        }
+        envs
+    }
+}
+
+impl EnvironmentProvider for WslProvider {
+    fn name(&self) -> &'static str {
+        "wsl"
+    }
+
+    fn label(&self) -> &'static str {
+        "WSL"
+    }
+
+    /// Starting every distro is slow and most Windows users don't want it.
+    fn opt_in(&self) -> bool {
+        true
+    }
+
+    fn discover(&self, cx: &DiscoveryContext) -> Result<Vec<PythonEnvironment>, PyEnvError> {
+        let output = cx.run_tool("wsl", |command| {
+            command.args(["--list", "--quiet"]).env("WSL_UTF8", "1");
+        })?;
+        let mut envs = Vec::new();
+        for distro in Self::parse_distros(&output) {
+            // A broken distro shouldn't hide the others; `run_tool` has
+            // already reported it.
+            if let Ok(output) = cx.run_tool("wsl", |command| {
+                command
+                    .args(["-d", &distro, "--", "sh", "-c", Self::SCAN_SCRIPT])
+                    .env("WSL_UTF8", "1");
+            }) {
+                envs.extend(self.parse_interpreters(&distro, &output));
+            }
+        }
+        Ok(envs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_distros_and_their_interpreters() {
+        assert_eq!(
+            WslProvider::parse_distros("U\0b\0u\0n\0t\0u\0\r\0\n\0D\0e\0b\0i\0a\0n\0\r\0\n\0"),
+            ["Ubuntu", "Debian"]
+        );
+
+        let output = "\
+/usr/bin/python3\t3.12.3
+/home/dev/miniconda3/bin/python\t3.11.9
+/home/dev/miniconda3/envs/ml/bin/python\t3.10.14
+/home/dev/.pyenv/versions/3.13.0/bin/python\t
+";
+        let envs = WslProvider.parse_interpreters("Ubuntu", output);
+        let names: Vec<&str> = envs.iter().map(|env| env.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "Ubuntu/python3",
+                "Ubuntu/miniconda3",
+                "Ubuntu/ml",
+                "Ubuntu/3.13.0"
+            ]
+        );
+        assert_eq!(envs[2].version.as_deref(), Some("3.10.14"));
+        assert_eq!(envs[3].version, None);
+        assert_eq!(envs[0].guest, Some(Guest::Wsl("Ubuntu".to_string())));
+    }
+}
@@ -0,0 +1,171 @@
+use crate::environment::python_command;
+use crate::error::PyEnvError;
+use crate::process::{output_with_timeout, NETWORK_COMMAND_TIMEOUT};
+use std::path::Path;
+
+/// How many trailing lines of pytest's own output `/pyenvtest` shows.
+const OUTPUT_TAIL_LINES: usize = 200;
+
+/// The parts of a pytest run worth showing in the assistant panel.
+#[derive(Debug, Default)]
+pub(crate) struct PytestReport {
+    /// pytest's exit code, when it wasn't killed.
+    pub(crate) exit_code: Option<i32>,
+    /// The closing line, e.g. `2 failed, 40 passed in 3.10s`.
+    pub(crate) summary: Option<String>,
+    /// `FAILED` and `ERROR` lines of the short test summary.
+    pub(crate) failures: Vec<String>,
+    /// Node ids listed by `--collect-only`.
+    pub(crate) collected: Vec<String>,
+    /// The last [`OUTPUT_TAIL_LINES`] lines of stdout and stderr.
+    pub(crate) output: String,
+}
+
+impl PytestReport {
+    fn parse(stdout: &str, stderr: &str, exit_code: Option<i32>) -> Self {
+        let lines: Vec<&str> = stdout.lines().collect();
+        let summary = lines
+            .iter()
+            .rev()
+            .map(|line| line.trim().trim_matches('=').trim())
+            .find(|line| {
+                [
+                    " passed",
+                    " failed",
+                    " error",
+                    "no tests ran",
+                    " skipped",
+                    " collected",
+                    " selected",
+                ]
+                .iter()
+                .any(|outcome| line.contains(outcome))
+                    && line.contains(" in ")
+            })
+            .map(str::to_string);
+        let failures = lines
+            .iter()
+            .filter(|line| line.starts_with("FAILED ") || line.starts_with("ERROR "))
+            .map(|line| line.to_string())
+            .collect();
+        let collected = lines
+            .iter()
+            .filter(|line| line.contains("::") && !line.contains(' '))
+            .map(|line| line.to_string())
+            .collect();
+
+        let all_lines: Vec<&str> = stdout.lines().chain(stderr.lines()).collect();
+        let tail = &all_lines[all_lines.len().saturating_sub(OUTPUT_TAIL_LINES)..];
+        Self {
+            exit_code,
+            summary,
+            failures,
+            collected,
+            output: tail.join("\n"),
+        }
+    }
+
+    /// What pytest's exit code means.
+    pub(crate) fn outcome(&self) -> &'static str {
+        match self.exit_code {
+            Some(0) => "all tests passed",
+            Some(1) => "some tests failed",
+            Some(2) => "interrupted",
+            Some(3) => "internal error",
+            Some(4) => "usage error",
+            Some(5) => "no tests collected",
+            _ => "pytest did not finish",
+        }
+    }
+}
+
+/// Runs pytest with the interpreter at `python_path` from `cwd`, on
+/// `targets` (node ids or paths; the whole suite when empty).
+pub(crate) fn run_pytest(
+    python_path: &Path,
+    cwd: &Path,
+    targets: &[String],
+    collect_only: bool,
+) -> Result<PytestReport, PyEnvError> {
+    let mut command = python_command(python_path);
+    command
+        .args(["-m", "pytest", "-rfE", "--color=no"])
+        .current_dir(cwd);
+    if collect_only {
+        command.args(["--collect-only", "-q"]);
+    }
+    command.args(targets);
+
+    let output = output_with_timeout(&mut command, NETWORK_COMMAND_TIMEOUT)?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.contains("No module named pytest") {
+        return Err(PyEnvError::unavailable(
+            "pytest",
+            format!(
+                "it is not installed in {}; run `{} -m pip install pytest`",
+                python_path.display(),
+                python_path.display()
+            ),
+        ));
+    }
+    Ok(PytestReport::parse(
+        &String::from_utf8_lossy(&output.stdout),
+        &stderr,
+        output.status.code(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_a_failing_run() {
+        let stdout = "\
+============================= test session starts ==============================
+collected 3 items
+
+tests/test_app.py .F.                                                    [100%]
+
+=================================== FAILURES ===================================
+________________________________ test_parse ____________________________________
+E       AssertionError: assert 1 == 2
+=========================== short test summary info ============================
+FAILED tests/test_app.py::test_parse - AssertionError: assert 1 == 2
+========================= 1 failed, 2 passed in 0.12s ==========================
+";
+        let report = PytestReport::parse(stdout, "", Some(1));
+        assert_eq!(
+            report.summary.as_deref(),
+            Some("1 failed, 2 passed in 0.12s")
+        );
+        assert_eq!(
+            report.failures,
+            ["FAILED tests/test_app.py::test_parse - AssertionError: assert 1 == 2"]
+        );
+        assert!(report.collected.is_empty());
+        assert_eq!(report.outcome(), "some tests failed");
+    }
+
+    #[test]
+    fn lists_collected_node_ids() {
+        let stdout = "\
+tests/test_app.py::test_parse
+tests/test_app.py::TestCli::test_help
+
+2 tests collected in 0.01s
+";
+        let report = PytestReport::parse(stdout, "", Some(0));
+        assert_eq!(
+            report.collected,
+            [
+                "tests/test_app.py::test_parse",
+                "tests/test_app.py::TestCli::test_help"
+            ]
+        );
+        assert_eq!(
+            report.summary.as_deref(),
+            Some("2 tests collected in 0.01s")
+        );
+    }
+}
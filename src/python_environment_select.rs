@@ -1,225 +1,1074 @@
+mod activate;
+mod commands;
+mod container;
+mod doctor;
+mod environment;
+mod error;
+mod kernels;
+mod logging;
+mod output;
+mod process;
+mod project;
+mod providers;
+mod pytest;
+mod selection;
+mod sync;
+mod tasks;
+#[cfg(test)]
+mod test_support;
+mod which;
+
+use environment::{
+    assign_identifiers, directory_size, environment_executable, environment_root,
+    environment_size_root, interpreter_identity, matches_dir_pattern, query_flavor,
+    query_python_version, read_pyvenv_version, stale_dependency_file, validate_interpreter, Flavor,
+    PythonEnvironment, PyvenvConfig,
+};
+use error::PyEnvError;
+use kernels::{kernels_for, list_kernelspecs};
+use process::parallel_map;
+use project::{
+    read_environment_yml_name, read_requires_python, version_matches_pin, version_satisfies,
+    VersionPin,
+};
+use providers::{providers, CommandRun, DiscoveryContext, EnvironmentProvider, WorktreeSnapshot};
+use selection::{EnvironmentPin, SelectedEnvironment, SelectionState};
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::sync::atomic::AtomicUsize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
 use zed_extension_api::{
-    self as zed, SlashCommand, SlashCommandArgumentCompletion, SlashCommandOutput,
-    SlashCommandOutputSection, Worktree,
+    self as zed, serde_json::json, settings::LspSettings, LanguageServerId, SlashCommand,
+    SlashCommandArgumentCompletion, SlashCommandOutput, Worktree,
 };
 
-struct PythonEnvironment {
-    name: String,
-    python_path: PathBuf,
+/// File in the extension's working directory holding the persisted selections.
+const STATE_FILE: &str = "pyenvselect_state.json";
+
+/// Key under `lsp` in Zed's settings holding this extension's own settings.
+const SETTINGS_KEY: &str = "pyenvselect";
+
+const DEFAULT_CACHE_TTL_SECS: u64 = 300;
+
+/// Directory names never descended into by the worktree scan.
+const DEFAULT_SCAN_IGNORE: &[&str] = &[
+    ".git",
+    ".hg",
+    ".svn",
+    "node_modules",
+    "__pycache__",
+    "target",
+    ".tox",
+    ".nox",
+    ".mypy_cache",
+    ".pytest_cache",
+    ".ruff_cache",
+    ".idea",
+    ".vscode",
+];
+
+const DEFAULT_SCAN_MAX_DEPTH: usize = 6;
+
+/// Worktree directories treated as the project's own environment.
+const DEFAULT_PROJECT_ENV_DIRS: &[&str] = &[".venv", "venv", "env", ".direnv/python-*"];
+/// Hard cap on `scan_max_depth`, so a misconfigured value can't make the scan
+/// walk an entire home directory.
+const SCAN_DEPTH_LIMIT: usize = 32;
+
+const DEFAULT_LANGUAGE_SERVER: &str = "pyright";
+
+/// A Pyright-compatible language server distributed through npm.
+struct NpmLanguageServer {
+    /// Language server ID, as registered in `extension.toml`.
+    id: &'static str,
+    package: &'static str,
+    /// Entry point relative to the extension's working directory.
+    script: &'static str,
+    /// Executable name used when the server is already installed on `PATH`.
+    binary: &'static str,
+}
+
+const PYLSP_LANGUAGE_SERVER: &str = "pylsp";
+
+const NPM_LANGUAGE_SERVERS: &[NpmLanguageServer] = &[
+    NpmLanguageServer {
+        id: "pyright",
+        package: "pyright",
+        script: "node_modules/pyright/langserver.index.js",
+        binary: "pyright-langserver",
+    },
+    NpmLanguageServer {
+        id: "basedpyright",
+        package: "basedpyright",
+        script: "node_modules/basedpyright/langserver.index.js",
+        binary: "basedpyright-langserver",
+    },
+];
+
+/// Extension settings, read from `lsp.pyenvselect.settings`:
+///
+/// ```json
+/// { "lsp": { "pyenvselect": { "settings": { "cache_ttl_secs": 60 } } } }
+/// ```
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+struct ExtensionSettings {
+    /// How long discovery results are reused before re-scanning. `0` disables caching.
+    cache_ttl_secs: u64,
+    /// Directory names skipped by the recursive worktree scan.
+    scan_ignore: Vec<String>,
+    /// How many directory levels below the worktree root are scanned.
+    scan_max_depth: usize,
+    /// Opt-in providers to run in addition to the default ones, e.g. `["pipx"]`.
+    enabled_providers: Vec<String>,
+    /// Providers never to run, e.g. `["conda"]`, or `["venv"]` to skip
+    /// scanning a huge worktree. Takes precedence over `enabled_providers`.
+    disabled_providers: Vec<String>,
+    /// Additional directories holding environments (or that are one), e.g.
+    /// `["/opt/venvs", "~/envs"]`. Relative paths are resolved against the worktree.
+    extra_search_paths: Vec<String>,
+    /// Write every selection into `.zed/settings.json`, as if `--persist` was
+    /// passed. Zed reacts to the settings change by re-sending the language
+    /// server configuration, so the new interpreter takes effect immediately.
+    update_project_settings: bool,
+    /// Which language server receives the selected interpreter: `"pyright"`,
+    /// `"basedpyright"` or `"pylsp"`. The others refuse to start.
+    language_server: String,
+    /// Worktree-relative directories holding the project's own environment,
+    /// most preferred first. A `*` ends a component matching any suffix, as
+    /// in `.direnv/python-*`. `/pyenvcreate` creates the first plain one.
+    project_env_dirs: Vec<String>,
+    /// Keep `.zed/python-environment.json` up to date with the selected
+    /// interpreter, for formatters, test runners and other extensions to
+    /// read.
+    selection_file: bool,
+}
+
+impl Default for ExtensionSettings {
+    fn default() -> Self {
+        Self {
+            cache_ttl_secs: DEFAULT_CACHE_TTL_SECS,
+            scan_ignore: DEFAULT_SCAN_IGNORE.iter().map(|s| s.to_string()).collect(),
+            scan_max_depth: DEFAULT_SCAN_MAX_DEPTH,
+            enabled_providers: Vec::new(),
+            disabled_providers: Vec::new(),
+            extra_search_paths: Vec::new(),
+            update_project_settings: false,
+            language_server: DEFAULT_LANGUAGE_SERVER.to_string(),
+            project_env_dirs: DEFAULT_PROJECT_ENV_DIRS
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            selection_file: false,
+        }
+    }
+}
+
+impl ExtensionSettings {
+    fn for_worktree(worktree: &Worktree) -> Self {
+        LspSettings::for_worktree(SETTINGS_KEY, worktree)
+            .ok()
+            .and_then(|lsp_settings| lsp_settings.settings)
+            .and_then(|settings| zed::serde_json::from_value(settings).ok())
+            .unwrap_or_default()
+    }
+
+    /// Whether `provider` runs: it isn't disabled, and is either on by
+    /// default or explicitly enabled.
+    fn provider_enabled(&self, provider: &dyn EnvironmentProvider) -> bool {
+        let listed = |names: &[String]| names.iter().any(|name| name == provider.name());
+        !listed(&self.disabled_providers) && (!provider.opt_in() || listed(&self.enabled_providers))
+    }
+
+    /// Position in `project_env_dirs` of the pattern matching the
+    /// environment of `python_path`, if it is a project environment of the
+    /// worktree at `root_path`.
+    fn project_env_rank(&self, root_path: &Path, python_path: &Path) -> Option<usize> {
+        let relative = environment_root(python_path)?
+            .strip_prefix(root_path)
+            .ok()?;
+        self.project_env_dirs
+            .iter()
+            .position(|pattern| matches_dir_pattern(relative, pattern))
+    }
+}
+
+/// One step of a scan: a provider, or the interpreter checks after them.
+#[derive(Clone)]
+struct ScanStep {
+    name: &'static str,
+    elapsed: Duration,
+    /// What the step found, or why it found nothing.
+    outcome: String,
+}
+
+/// The result of running every provider.
+#[derive(Clone, Default)]
+struct Discovery {
+    environments: Vec<PythonEnvironment>,
+    /// Problems that didn't stop discovery, such as unreadable directories.
+    warnings: Vec<String>,
+    /// `requires-python` of the worktree's `pyproject.toml`.
+    requires_python: Option<String>,
+    /// Each provider in priority order, followed by the interpreter checks
+    /// that run once every provider has finished.
+    steps: Vec<ScanStep>,
+    /// Wall-clock time of the whole scan; providers overlap, so this is less
+    /// than the sum of the steps.
+    elapsed: Duration,
+    /// Tools providers ran, in the order they finished.
+    commands: Vec<CommandRun>,
+    /// Directories read by the worktree scan.
+    dirs_scanned: usize,
+    /// The providers' watched paths and their modification times at the
+    /// start of the scan.
+    watched: Vec<(PathBuf, Option<SystemTime>)>,
+    /// Set when this result was reused from the cache rather than scanned.
+    from_cache: bool,
+}
+
+impl Discovery {
+    /// Why the result no longer matches the disk, if it doesn't: a watched
+    /// directory changed, e.g. because `python -m venv .venv` ran in a
+    /// terminal, or a listed interpreter was deleted.
+    fn changed_on_disk(&self) -> Option<String> {
+        if let Some((path, _)) = self
+            .watched
+            .iter()
+            .find(|(path, modified)| modified_time(path) != *modified)
+        {
+            return Some(format!("{} changed", path.display()));
+        }
+        // Not `exists()`: a broken environment's dangling symlink is still listed.
+        self.environments
+            .iter()
+            .find(|env| env.guest.is_none() && fs::symlink_metadata(&env.python_path).is_err())
+            .map(|env| format!("{} was deleted", env.python_path.display()))
+    }
+
+    /// Whether `env` is known to run a Python outside `requires-python`.
+    /// Environments of unknown version get the benefit of the doubt.
+    fn violates_requires_python(&self, env: &PythonEnvironment) -> bool {
+        match (&self.requires_python, &env.version) {
+            (Some(requires), Some(version)) => !version_satisfies(version, requires),
+            _ => false,
+        }
+    }
 }
 
-struct PythonEnvironmentSelectExtension;
+/// How a worktree is referred to in listings and `--worktree`: the name of
+/// its root directory.
+fn worktree_name(root_path: &Path) -> String {
+    root_path.file_name().map_or_else(
+        || root_path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+struct CachedDiscovery {
+    discovered_at: Instant,
+    discovery: Discovery,
+}
+
+struct PythonEnvironmentSelectExtension {
+    providers: Vec<Box<dyn EnvironmentProvider>>,
+    state: Mutex<SelectionState>,
+    settings: Mutex<ExtensionSettings>,
+    /// Discovery results keyed by worktree root (`None` when run without one).
+    cache: Mutex<HashMap<Option<PathBuf>, CachedDiscovery>>,
+    /// Interpreter versions keyed by interpreter path; survives cache refreshes
+    /// since an interpreter's version doesn't change underneath it.
+    versions: Mutex<HashMap<PathBuf, Option<String>>>,
+    /// Interpreter flavors keyed by interpreter path, cached like `versions`.
+    flavors: Mutex<HashMap<PathBuf, Option<Flavor>>>,
+    /// Worktrees seen so far, keyed by root.
+    snapshots: Mutex<HashMap<PathBuf, WorktreeSnapshot>>,
+    /// Roots of every worktree the extension has been called for. The API
+    /// can't enumerate the open worktrees, so `/pyenvlst` lists these.
+    worktree_roots: Mutex<BTreeSet<PathBuf>>,
+    /// Interpreter paths in the order the last `/pyenvlst` printed them, so
+    /// `/pyenvselect 3` picks what the user saw as entry 3.
+    last_listing: Mutex<Vec<PathBuf>>,
+    /// Sizes of environment roots in bytes, measured on demand (walking a
+    /// large conda environment takes seconds) and cleared with the cache.
+    sizes: Mutex<HashMap<PathBuf, u64>>,
+}
+
+/// Sets `value[keys[0]][keys[1]]...` to `leaf`, replacing any non-object
+/// values along the way.
+fn set_json_path(value: &mut zed::serde_json::Value, keys: &[&str], leaf: zed::serde_json::Value) {
+    let Some((last, parents)) = keys.split_last() else {
+        *value = leaf;
+        return;
+    };
+    let mut value = value;
+    for key in parents {
+        if !value.get(*key).is_some_and(|child| child.is_object()) {
+            value[*key] = json!({});
+        }
+        value = &mut value[*key];
+    }
+    value[*last] = leaf;
+}
+
+/// Where a language server reads the interpreter from within its settings.
+fn interpreter_setting_path(language_server: &str) -> &'static [&'static str] {
+    match language_server {
+        PYLSP_LANGUAGE_SERVER => &["pylsp", "plugins", "jedi", "environment"],
+        _ => &["python", "pythonPath"],
+    }
+}
 
 impl PythonEnvironmentSelectExtension {
-    fn is_venv(path: &Path) -> bool {
-        let activate_script = path.join("bin").join("activate");
-        let pyvenv_cfg = path.join("pyvenv.cfg");
+    /// Appends `envs`, merging interpreters another provider already reported
+    /// into the earlier entry: its provider is recorded in `also_found_by` and
+    /// any metadata the earlier entry lacks is filled in.
+    fn extend_unique(environments: &mut Vec<PythonEnvironment>, envs: Vec<PythonEnvironment>) {
+        for env in envs {
+            let identity = interpreter_identity(&env.python_path);
+            let Some(known) = environments
+                .iter_mut()
+                .find(|known| interpreter_identity(&known.python_path) == identity)
+            else {
+                environments.push(env);
+                continue;
+            };
+
+            if known.provider != env.provider && !known.also_found_by.contains(&env.provider) {
+                known.also_found_by.push(env.provider);
+            }
+            known.project = known.project.take().or(env.project);
+            known.version = known.version.take().or(env.version);
+            known.suggested |= env.suggested;
+            known.active |= env.active;
+        }
+    }
 
-        activate_script.exists() || pyvenv_cfg.exists()
+    fn get_all_python_environments(&self, worktree: Option<&Worktree>) -> Vec<PythonEnvironment> {
+        self.get_discovery(worktree).environments
     }
 
-    fn find_python_executable(venv_path: &Path) -> Option<PathBuf> {
-        let python_path = venv_path.join("bin").join("python");
-        if python_path.exists() {
-            Some(python_path)
-        } else {
-            None
+    fn get_discovery(&self, worktree: Option<&Worktree>) -> Discovery {
+        let root_path = worktree.map(|worktree| PathBuf::from(worktree.root_path()));
+        if let (Some(worktree), Some(root_path)) = (worktree, &root_path) {
+            if let Ok(mut snapshots) = self.snapshots.lock() {
+                snapshots
+                    .entry(root_path.clone())
+                    .or_insert_with(|| WorktreeSnapshot::capture(worktree));
+            }
+            if let Ok(mut roots) = self.worktree_roots.lock() {
+                roots.insert(root_path.clone());
+            }
         }
+        self.discover_environments(root_path.as_deref())
     }
 
-    fn find_venvs_from_worktree(_worktree: &Worktree) -> Vec<PythonEnvironment> {
-        let root_path = PathBuf::from(_worktree.root_path());
-        Self::find_venvs_rec(&root_path)
+    /// [`Self::get_discovery`] for `worktree`, followed by the project-local
+    /// environments of the other worktrees seen so far, grouped by root. Those
+    /// are tagged with their worktree's name, which also prefixes their
+    /// identifiers (`backend/venv:.venv`) so they stay unique.
+    fn workspace_discovery(&self, worktree: Option<&Worktree>) -> Discovery {
+        let mut discovery = self.get_discovery(worktree);
+        let root_path = worktree.map(|worktree| PathBuf::from(worktree.root_path()));
+        let other_roots: Vec<PathBuf> = self
+            .worktree_roots
+            .lock()
+            .map(|roots| {
+                roots
+                    .iter()
+                    .filter(|root| Some(*root) != root_path.as_ref())
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        for other_root in other_roots {
+            let name = worktree_name(&other_root);
+            let environments = self.discover_environments(Some(&other_root)).environments;
+            for mut env in environments.into_iter().filter(|env| env.project_local) {
+                if discovery
+                    .environments
+                    .iter()
+                    .any(|known| known.python_path == env.python_path)
+                {
+                    continue;
+                }
+                env.id = format!("{}/{}", name, env.id);
+                env.worktree = Some(name.clone());
+                discovery.environments.push(env);
+            }
+        }
+        discovery
     }
 
-    fn find_venvs_rec(dir: &Path) -> Vec<PythonEnvironment> {
-        let mut venvs = Vec::new();
+    /// Returns the environments for `root_path`, reusing a previous scan while
+    /// it is younger than the configured TTL.
+    fn discover_environments(&self, root_path: Option<&Path>) -> Discovery {
+        let settings = self
+            .settings
+            .lock()
+            .map(|settings| settings.clone())
+            .unwrap_or_default();
+        let ttl = Duration::from_secs(settings.cache_ttl_secs);
+        let key = root_path.map(Path::to_path_buf);
 
-        match fs::read_dir(dir) {
-            Ok(entries) => {
-                for entry in entries.filter_map(Result::ok) {
-                    let path = entry.path();
-                    if path.is_dir() {
-                        if Self::is_venv(&path) {
-                            if let Some(python_path) = Self::find_python_executable(&path) {
-                                venvs.push(PythonEnvironment {
-                                    name: path.file_name().unwrap().to_string_lossy().into_owned(),
-                                    python_path,
-                                });
-                            }
-                        } else {
-                            // Recursively search subdirectories
-                            venvs.extend(Self::find_venvs_rec(&path));
+        if let Ok(cache) = self.cache.lock() {
+            if let Some(cached) = cache.get(&key) {
+                if cached.discovered_at.elapsed() < ttl {
+                    // Re-validate rather than trust the cache until it expires:
+                    // the extension API can't watch the file system.
+                    match cached.discovery.changed_on_disk() {
+                        Some(change) => {
+                            logging::debug("discovery", format!("rescanning: {}", change))
+                        }
+                        None => {
+                            logging::debug(
+                                "discovery",
+                                format!(
+                                    "reusing the scan from {}s ago",
+                                    cached.discovered_at.elapsed().as_secs()
+                                ),
+                            );
+                            return Discovery {
+                                from_cache: true,
+                                ..cached.discovery.clone()
+                            };
                         }
                     }
                 }
             }
-            Err(e) => {
-                venvs.push(PythonEnvironment {
-                    name: format!("Error reading directory ({}): {}", dir.display(), e),
-                    python_path: PathBuf::new(),
-                });
-            }
         }
-        venvs
+
+        let discovery = self.scan_environments(root_path, &settings);
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.insert(
+                key,
+                CachedDiscovery {
+                    discovered_at: Instant::now(),
+                    discovery: discovery.clone(),
+                },
+            );
+        }
+        discovery
     }
 
-    fn find_envs_from_conda() -> Result<Vec<PythonEnvironment>, String> {
-        let output = Command::new("conda")
-            .args(&["info", "--envs"])
-            .output()
-            .map_err(|e| format!("Failed to execute command: {}", e))?;
+    fn invalidate_cache(&self, root_path: Option<&Path>) {
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.remove(&root_path.map(Path::to_path_buf));
+        }
+        if let (Some(root_path), Ok(mut snapshots)) = (root_path, self.snapshots.lock()) {
+            snapshots.remove(root_path);
+        }
+        if let Ok(mut sizes) = self.sizes.lock() {
+            sizes.clear();
+        }
+    }
 
-        if !output.status.success() {
-            return Err(format!(
-                "Command executed with failing error code: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ));
+    fn scan_environments(
+        &self,
+        root_path: Option<&Path>,
+        settings: &ExtensionSettings,
+    ) -> Discovery {
+        let scan_started = Instant::now();
+        let mut environments = Vec::new();
+        let snapshot = root_path.and_then(|root_path| {
+            self.snapshots
+                .lock()
+                .ok()
+                .and_then(|snapshots| snapshots.get(root_path).cloned())
+        });
+        let cx = DiscoveryContext {
+            root_path,
+            settings,
+            snapshot: snapshot.as_ref(),
+            warnings: Mutex::new(Vec::new()),
+            commands: Mutex::new(Vec::new()),
+            dirs_scanned: AtomicUsize::new(0),
+        };
+
+        logging::debug(
+            "discovery",
+            match root_path {
+                Some(root_path) => format!("scanning for {}", root_path.display()),
+                None => "scanning without a worktree".to_string(),
+            },
+        );
+
+        let providers: Vec<_> = self
+            .providers
+            .iter()
+            .filter(|provider| settings.provider_enabled(provider.as_ref()))
+            .collect();
+        // Taken before the providers run, so a change made mid-scan still
+        // invalidates the result.
+        let watched = providers
+            .iter()
+            .flat_map(|provider| provider.watched_paths(&cx))
+            .map(|path| {
+                let modified = modified_time(&path);
+                (path, modified)
+            })
+            .collect();
+
+        // Providers mostly wait on subprocesses and disk, so run them side by
+        // side and merge in priority order afterwards.
+        let mut steps = Vec::new();
+        let results = parallel_map(&providers, |provider| {
+            let started = Instant::now();
+            (provider.discover(&cx), started.elapsed())
+        });
+        for (provider, (result, elapsed)) in providers.iter().zip(results) {
+            let step = ScanStep {
+                name: provider.name(),
+                elapsed,
+                outcome: match &result {
+                    Ok(envs) => format!("{} found", envs.len()),
+                    Err(error) => error.to_string(),
+                },
+            };
+            logging::debug(
+                step.name,
+                format!("{} in {:.2}s", step.outcome, elapsed.as_secs_f64()),
+            );
+            steps.push(step);
+            match result {
+                Ok(envs) => Self::extend_unique(&mut environments, envs),
+                // Missing tools are routine and failed runs were already
+                // reported by `run_tool`; anything else is worth a mention.
+                Err(
+                    PyEnvError::ProviderUnavailable { .. } | PyEnvError::DiscoveryFailed { .. },
+                ) => {}
+                Err(error) => cx.warn(error.to_string()),
+            }
+        }
+
+        let started = Instant::now();
+        let details = parallel_map(&environments, |env| {
+            // The interpreter is a path inside a guest; the provider already
+            // asked it for its version.
+            if env.guest.is_some() {
+                return (None, env.version.clone(), None, None);
+            }
+            let version = env
+                .version
+                .clone()
+                .or_else(|| self.interpreter_version(&env.python_path));
+            let broken = validate_interpreter(&env.python_path)
+                .err()
+                .map(|error| error.to_string());
+            let flavor = broken
+                .is_none()
+                .then(|| self.interpreter_flavor(&env.python_path))
+                .flatten();
+            (
+                PyvenvConfig::for_interpreter(&env.python_path),
+                version,
+                broken,
+                flavor,
+            )
+        });
+        for (env, (pyvenv, version, broken, flavor)) in environments.iter_mut().zip(details) {
+            if let Some(prompt) = pyvenv.as_ref().and_then(|cfg| cfg.prompt.as_ref()) {
+                env.name = prompt.clone();
+            }
+            env.pyvenv = pyvenv;
+            env.version = version;
+            env.broken = broken;
+            env.flavor = flavor;
+            if let Some(reason) = &env.broken {
+                cx.warn(format!("{} is broken: {}", env.name, reason));
+            }
         }
+        steps.push(ScanStep {
+            name: "interpreter checks",
+            elapsed: started.elapsed(),
+            outcome: format!(
+                "{} checked, {} broken",
+                environments.len(),
+                environments
+                    .iter()
+                    .filter(|env| env.broken.is_some())
+                    .count()
+            ),
+        });
 
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        Self::parse_conda_output(&output_str)
+        let declared_conda_env = ["environment.yml", "environment.yaml"]
+            .into_iter()
+            .find_map(|file| Some((file, cx.read_project_file(file)?)))
+            .and_then(|(file, contents)| Some((file, read_environment_yml_name(&contents)?)));
+        if let Some((file, declared)) = declared_conda_env {
+            let mut found = false;
+            for env in environments.iter_mut().filter(|env| {
+                matches!(env.provider.as_str(), "conda" | "mamba") && env.name == declared
+            }) {
+                env.suggested = true;
+                found = true;
+            }
+            if !found {
+                cx.warn(format!(
+                    "{} declares the conda environment \"{}\", but it doesn't exist; create it with `conda env create -f {}`",
+                    file, declared, file
+                ));
+            }
+        }
+        if let (Some(root_path), Some(pin)) = (
+            root_path,
+            EnvironmentPin::read(|file| cx.read_project_file(file)),
+        ) {
+            if !environments.iter().any(|env| pin.matches(root_path, env)) {
+                cx.warn(format!(
+                    "{} pins the environment \"{}\", but no discovered environment matches it; create it or pin another with /pyenvselect --pin",
+                    pin.source, pin.value
+                ));
+            }
+        }
+
+        if let Some(pin) = VersionPin::read(|file| cx.read_project_file(file)) {
+            // Resolve the pin to an interpreter rather than to every
+            // environment running that version: pyenv and asdf name their
+            // installs after it, otherwise take a base interpreter.
+            let pinned = pin.versions.iter().find_map(|pinned| {
+                environments
+                    .iter()
+                    .position(|env| env.name == *pinned)
+                    .or_else(|| {
+                        environments.iter().position(|env| {
+                            env.pyvenv.is_none()
+                                && env.guest.is_none()
+                                && match pinned.as_str() {
+                                    "system" => env.provider == "system",
+                                    _ => env.version.as_deref().is_some_and(|version| {
+                                        version_matches_pin(version, pinned)
+                                    }),
+                                }
+                        })
+                    })
+            });
+            match pinned {
+                Some(index) => environments[index].pinned = true,
+                None => cx.warn(format!(
+                    "{} pins Python {}, but no discovered interpreter matches it",
+                    pin.source,
+                    pin.versions.join(", ")
+                )),
+            }
+        }
+
+        let env_roots: Vec<PathBuf> = environments
+            .iter()
+            .filter_map(|env| environment_root(&env.python_path))
+            .map(Path::to_path_buf)
+            .collect();
+        let kernels = list_kernelspecs(&env_roots);
+        for env in &mut environments {
+            env.kernels = kernels_for(&env.python_path, &kernels);
+            env.project_local = root_path.is_some_and(|root_path| {
+                environment_root(&env.python_path)
+                    .is_some_and(|env_root| env_root.starts_with(root_path))
+            });
+            env.project_env = root_path.is_some_and(|root_path| {
+                settings
+                    .project_env_rank(root_path, &env.python_path)
+                    .is_some()
+            });
+            // Only the project's own environments are expected to track its lockfile.
+            if let Some(root_path) = root_path.filter(|_| {
+                env.project_local || matches!(env.provider.as_str(), "poetry" | "pipenv")
+            }) {
+                env.stale = stale_dependency_file(&env.python_path, root_path);
+            }
+        }
+        // Stable, so providers keep their order within each half.
+        environments.sort_by_key(|env| !env.project_local);
+        assign_identifiers(&mut environments);
+
+        logging::info(
+            "discovery",
+            format!(
+                "found {} environments in {:.2}s",
+                environments.len(),
+                scan_started.elapsed().as_secs_f64()
+            ),
+        );
+        Discovery {
+            environments,
+            requires_python: cx
+                .read_project_file("pyproject.toml")
+                .and_then(|pyproject| read_requires_python(&pyproject)),
+            warnings: cx.warnings.into_inner().unwrap_or_default(),
+            steps,
+            elapsed: scan_started.elapsed(),
+            commands: cx.commands.into_inner().unwrap_or_default(),
+            dirs_scanned: cx.dirs_scanned.into_inner(),
+            watched,
+            from_cache: false,
+        }
     }
 
-    fn parse_conda_output(output: &str) -> Result<Vec<PythonEnvironment>, String> {
-        let mut envs = Vec::new();
-        let mut lines = output.lines();
+    /// Looks up an interpreter's version from `pyvenv.cfg`, falling back to
+    /// running `python --version` once per interpreter.
+    fn interpreter_version(&self, python_path: &Path) -> Option<String> {
+        if let Some(version) = read_pyvenv_version(python_path) {
+            return Some(version);
+        }
 
-        // Skip header lines
-        while let Some(line) = lines.next() {
-            if line.starts_with('#') {
-                continue;
+        if let Ok(versions) = self.versions.lock() {
+            if let Some(version) = versions.get(python_path) {
+                return version.clone();
             }
-            break;
         }
 
-        // Parse environment lines
-        for line in lines {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 2 {
-                let env_path = PathBuf::from(parts[1]);
-                if let Some(python_path) = Self::find_python_executable(&env_path) {
-                    envs.push(PythonEnvironment {
-                        name: parts[0].to_string(),
-                        python_path,
-                    });
-                }
+        let version = query_python_version(python_path);
+        if let Ok(mut versions) = self.versions.lock() {
+            versions.insert(python_path.to_path_buf(), version.clone());
+        }
+        version
+    }
+
+    /// Asks an interpreter which [`Flavor`] it is, once per interpreter.
+    fn interpreter_flavor(&self, python_path: &Path) -> Option<Flavor> {
+        if let Some(flavor) = self
+            .flavors
+            .lock()
+            .ok()
+            .and_then(|flavors| flavors.get(python_path).cloned())
+        {
+            return flavor;
+        }
+
+        let flavor = query_flavor(python_path);
+        if let Ok(mut flavors) = self.flavors.lock() {
+            flavors.insert(python_path.to_path_buf(), flavor.clone());
+        }
+        flavor
+    }
+
+    /// Size on disk of the environment of `python_path`, if it has a root of
+    /// its own (see [`environment_size_root`]).
+    fn environment_size(&self, python_path: &Path, provider: &str) -> Option<u64> {
+        let env_root = environment_size_root(python_path, provider)?;
+        if let Some(size) = self
+            .sizes
+            .lock()
+            .ok()
+            .and_then(|sizes| sizes.get(env_root).copied())
+        {
+            return Some(size);
+        }
+
+        let size = directory_size(env_root);
+        if let Ok(mut sizes) = self.sizes.lock() {
+            sizes.insert(env_root.to_path_buf(), size);
+        }
+        Some(size)
+    }
+
+    fn remember_worktree(&self, worktree: Option<&Worktree>) {
+        let Some(worktree) = worktree else {
+            return;
+        };
+        if let Ok(mut settings) = self.settings.lock() {
+            *settings = ExtensionSettings::for_worktree(worktree);
+        }
+        let root_path = PathBuf::from(worktree.root_path());
+        if let Ok(mut state) = self.state.lock() {
+            if state.last_worktree_root.as_ref() != Some(&root_path) {
+                state.last_worktree_root = Some(root_path);
+                // Best effort: completions still work for this session if saving fails.
+                let _ = state.save();
             }
         }
-        Ok(envs)
     }
 
-    fn get_all_python_environments(&self, worktree: Option<&Worktree>) -> Vec<PythonEnvironment> {
-        let mut environments = Vec::new();
+    /// Returns the path of the npm-installed language server, installing or
+    /// updating it in the extension's working directory when needed.
+    fn npm_server_path(
+        server: &NpmLanguageServer,
+        language_server_id: &LanguageServerId,
+    ) -> Result<String, String> {
+        let server_exists = fs::metadata(server.script).is_ok_and(|stat| stat.is_file());
+        if server_exists {
+            return Ok(server.script.to_string());
+        }
 
-        println!("here1");
+        zed::set_language_server_installation_status(
+            language_server_id,
+            &zed::LanguageServerInstallationStatus::CheckingForUpdate,
+        );
+        let version = zed::npm_package_latest_version(server.package)?;
 
-        // Get virtual environments from worktree
-        if let Some(worktree) = worktree {
-            environments.extend(Self::find_venvs_from_worktree(worktree));
+        zed::set_language_server_installation_status(
+            language_server_id,
+            &zed::LanguageServerInstallationStatus::Downloading,
+        );
+        if let Err(error) = zed::npm_install_package(server.package, &version) {
+            if fs::metadata(server.script).is_err() {
+                return Err(error);
+            }
         }
 
-        // Get Conda environments
-        if let Ok(conda_envs) = Self::find_envs_from_conda() {
-            environments.extend(conda_envs);
+        Ok(server.script.to_string())
+    }
+
+    /// Runs pylsp from the selected environment when it is installed there,
+    /// otherwise a pylsp from `PATH`; either way the workspace configuration
+    /// points Jedi at the selected interpreter.
+    fn pylsp_command(&self, worktree: &Worktree) -> Result<zed::Command, String> {
+        let command = self
+            .effective_selection(worktree)
+            .and_then(|selected| environment_executable(&selected.python_path, "pylsp"))
+            .map(|path| path.to_string_lossy().into_owned())
+            .or_else(|| worktree.which("pylsp"))
+            .ok_or_else(|| {
+                "pylsp not found: install python-lsp-server into the selected environment \
+                 or put pylsp on PATH"
+                    .to_string()
+            })?;
+
+        Ok(zed::Command {
+            command,
+            args: Vec::new(),
+            env: worktree.shell_env(),
+        })
+    }
+
+    /// Builds the `python` settings Pyright reads to locate the interpreter.
+    fn pyright_python_settings(selected: &SelectedEnvironment) -> zed::serde_json::Value {
+        let mut python = json!({
+            "pythonPath": selected.python_path.to_string_lossy(),
+        });
+
+        // `<venvPath>/<venv>/bin/python`: point Pyright at the environment root.
+        if let Some(env_root) = environment_root(&selected.python_path) {
+            if let (Some(venv_path), Some(venv)) = (env_root.parent(), env_root.file_name()) {
+                python["venvPath"] = json!(venv_path.to_string_lossy());
+                python["venv"] = json!(venv.to_string_lossy());
+            }
         }
 
-        environments
+        python
     }
 }
 
 impl zed::Extension for PythonEnvironmentSelectExtension {
     fn new() -> Self {
-        PythonEnvironmentSelectExtension
+        PythonEnvironmentSelectExtension {
+            providers: providers(),
+            state: Mutex::new(SelectionState::load()),
+            settings: Mutex::new(ExtensionSettings::default()),
+            cache: Mutex::new(HashMap::new()),
+            versions: Mutex::new(HashMap::new()),
+            flavors: Mutex::new(HashMap::new()),
+            snapshots: Mutex::new(HashMap::new()),
+            worktree_roots: Mutex::new(BTreeSet::new()),
+            last_listing: Mutex::new(Vec::new()),
+            sizes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn language_server_command(
+        &mut self,
+        language_server_id: &LanguageServerId,
+        worktree: &Worktree,
+    ) -> Result<zed::Command, String> {
+        let settings = ExtensionSettings::for_worktree(worktree);
+        if settings.language_server != language_server_id.as_ref() {
+            return Err(format!(
+                "{} is disabled: the pyenvselect `language_server` setting selects {}",
+                language_server_id, settings.language_server
+            ));
+        }
+
+        if language_server_id.as_ref() == PYLSP_LANGUAGE_SERVER {
+            return self.pylsp_command(worktree);
+        }
+
+        let server = NPM_LANGUAGE_SERVERS
+            .iter()
+            .find(|server| server.id == language_server_id.as_ref())
+            .ok_or_else(|| format!("unknown language server: {}", language_server_id))?;
+
+        if let Some(path) = worktree.which(server.binary) {
+            return Ok(zed::Command {
+                command: path,
+                args: vec!["--stdio".to_string()],
+                env: worktree.shell_env(),
+            });
+        }
+
+        let server_path = Self::npm_server_path(server, language_server_id)?;
+        let server_path = std::env::current_dir()
+            .map_err(|e| format!("Failed to resolve extension directory: {}", e))?
+            .join(server_path);
+
+        Ok(zed::Command {
+            command: zed::node_binary_path()?,
+            args: vec![
+                server_path.to_string_lossy().into_owned(),
+                "--stdio".to_string(),
+            ],
+            env: Default::default(),
+        })
+    }
+
+    fn language_server_workspace_configuration(
+        &mut self,
+        language_server_id: &LanguageServerId,
+        worktree: &Worktree,
+    ) -> Result<Option<zed::serde_json::Value>, String> {
+        // Start from the user's own server settings so we only override the interpreter.
+        // basedpyright reads the interpreter from the same `python.*` keys as Pyright.
+        let mut settings = LspSettings::for_worktree(language_server_id.as_ref(), worktree)
+            .ok()
+            .and_then(|lsp_settings| lsp_settings.settings)
+            .unwrap_or_else(|| json!({}));
+
+        self.export_selection(worktree);
+        if let Some(selected) = self.effective_selection(worktree) {
+            // The server runs on the host and can't start an interpreter
+            // that only exists inside a guest.
+            if let Some(guest) = &selected.guest {
+                logging::info(
+                    "lsp",
+                    format!(
+                        "{} runs in {}; leaving {}'s interpreter unchanged",
+                        selected.name,
+                        guest,
+                        language_server_id.as_ref()
+                    ),
+                );
+                return Ok(Some(settings));
+            }
+            if !settings.is_object() {
+                settings = json!({});
+            }
+            if language_server_id.as_ref() == PYLSP_LANGUAGE_SERVER {
+                set_json_path(
+                    &mut settings,
+                    interpreter_setting_path(PYLSP_LANGUAGE_SERVER),
+                    json!(selected.python_path.to_string_lossy()),
+                );
+                return Ok(Some(settings));
+            }
+
+            let python = Self::pyright_python_settings(&selected);
+            match settings
+                .get_mut("python")
+                .and_then(|value| value.as_object_mut())
+            {
+                Some(existing) => {
+                    if let Some(python) = python.as_object() {
+                        existing.extend(python.clone());
+                    }
+                }
+                None => settings["python"] = python,
+            }
+        }
+
+        Ok(Some(settings))
     }
 
     fn complete_slash_command_argument(
         &self,
         command: SlashCommand,
-        _args: Vec<String>,
-    ) -> Result<Vec<zed_extension_api::SlashCommandArgumentCompletion>, String> {
-        match command.name.as_str() {
-            "pyenvcur" => Ok(vec![]),
-            "pyenvlst" => Ok(vec![]),
-            "pyenvselect" => Ok(vec![]),
-            command => Err(format!("unknown slash command: \"{command}\"")),
-        }
+        args: Vec<String>,
+    ) -> Result<Vec<SlashCommandArgumentCompletion>, String> {
+        self.complete_command_argument(command, args)
     }
 
     fn run_slash_command(
         &self,
         command: SlashCommand,
         args: Vec<String>,
-        _worktree: Option<&Worktree>,
+        worktree: Option<&Worktree>,
     ) -> Result<SlashCommandOutput, String> {
-        match command.name.as_str() {
-            "pyenvcur" => {
-                if args.is_empty() {
-                    return Err("nothing to echo".to_string());
-                }
+        let invocation = format!("/{} {}", command.name, args.join(" "));
+        logging::debug("command", invocation.trim_end());
+        self.execute_command(command, args, worktree)
+            .inspect_err(|error| {
+                logging::error("command", format!("{}: {}", invocation.trim_end(), error))
+            })
+    }
+}
 
-                let text = args.join(" ");
+zed::register_extension!(PythonEnvironmentSelectExtension);
 
-                Ok(SlashCommandOutput {
-                    sections: vec![SlashCommandOutputSection {
-                        range: (0..text.len()).into(),
-                        label: "Echo".to_string(),
-                    }],
-                    text,
-                })
-            }
-            "pyenvlst" => {
-                let all_envs = self.get_all_python_environments(_worktree);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{environment, Fixture};
 
-                // Find the longest environment name for proper alignment
-                let max_name_length = all_envs.iter().map(|env| env.name.len()).max().unwrap_or(0);
+    #[test]
+    fn notices_environments_created_and_deleted_since_the_scan() {
+        let fixture = Fixture::new();
+        let python = fixture.venv(".venv");
+        fixture.touch("services/main.py");
+        let watched = fixture.path().join("services");
+        let discovery = Discovery {
+            environments: vec![environment(".venv", &python.to_string_lossy())],
+            watched: vec![(watched.clone(), modified_time(&watched))],
+            ..Default::default()
+        };
+        assert_eq!(discovery.changed_on_disk(), None);
 
-                // Format each environment with aligned columns
-                let formatted_envs: Vec<String> = all_envs
-                    .iter()
-                    .map(|env| {
-                        format!(
-                            "{:<width$}    {}",
-                            env.name,
-                            env.python_path.display(),
-                            width = max_name_length
-                        )
-                    })
-                    .collect();
-
-                let mut text = formatted_envs.join("\n");
-                text = format!("======\n {} ======", text);
-                text = format!("{}\nlen: {}", text, all_envs.len());
-
-                Ok(SlashCommandOutput {
-                    sections: vec![SlashCommandOutputSection {
-                        range: (0..text.len()).into(),
-                        label: "Python Environments".to_string(),
-                    }],
-                    text,
-                })
-            }
-            "pyenvselect" => {
-                if args.is_empty() {
-                    return Err("nothing to echo".to_string());
-                }
+        fs::remove_file(&python).unwrap();
+        assert!(discovery
+            .changed_on_disk()
+            .is_some_and(|change| change.ends_with("python was deleted")));
 
-                let text = args.join(" ");
+        let discovery = Discovery {
+            watched: vec![(watched.clone(), Some(SystemTime::UNIX_EPOCH))],
+            ..Default::default()
+        };
+        assert_eq!(
+            discovery.changed_on_disk(),
+            Some(format!("{} changed", watched.display()))
+        );
+    }
 
-                Ok(SlashCommandOutput {
-                    sections: vec![SlashCommandOutputSection {
-                        range: (0..text.len()).into(),
-                        label: "Echo".to_string(),
-                    }],
-                    text,
-                })
-            }
-            command => Err(format!("unknown slash command: \"{command}\"")),
-        }
+    #[test]
+    fn flags_environments_outside_requires_python() {
+        let discovery = Discovery {
+            requires_python: Some(">=3.11".to_string()),
+            ..Default::default()
+        };
+        let mut env = environment("legacy", "/work/legacy/bin/python");
+        assert!(!discovery.violates_requires_python(&env));
+
+        env.version = Some("3.8.18".to_string());
+        assert!(discovery.violates_requires_python(&env));
+        env.version = Some("3.12.1".to_string());
+        assert!(!discovery.violates_requires_python(&env));
+        assert!(!Discovery::default().violates_requires_python(&env));
     }
-}
 
-zed::register_extension!(PythonEnvironmentSelectExtension);
+    #[test]
+    fn disabling_a_provider_overrides_enabling_it() {
+        let providers = providers();
+        let provider = |name| {
+            providers
+                .iter()
+                .find(|provider| provider.name() == name)
+                .unwrap()
+                .as_ref()
+        };
+        let mut settings = ExtensionSettings::default();
+        assert!(settings.provider_enabled(provider("conda")));
+        assert!(!settings.provider_enabled(provider("pipx")));
+
+        settings.enabled_providers = vec!["pipx".to_string()];
+        settings.disabled_providers = vec!["conda".to_string(), "pipx".to_string()];
+        assert!(!settings.provider_enabled(provider("conda")));
+        assert!(!settings.provider_enabled(provider("pipx")));
+        assert!(settings.provider_enabled(provider("venv")));
+    }
+}
@@ -0,0 +1,1060 @@
+use crate::environment::{
+    environment_root, query_python_version, validate_interpreter, Guest, PythonEnvironment,
+};
+use crate::error::PyEnvError;
+use crate::logging;
+use crate::project::{parse_pin_file, read_pyproject_pin, VersionPin, PIN_FILE};
+use crate::{worktree_name, ExtensionSettings, PythonEnvironmentSelectExtension, STATE_FILE};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use zed_extension_api::serde_json::{json, Map, Value};
+use zed_extension_api::Worktree;
+
+/// How many previous selections `/pyenvrecent` remembers per worktree.
+const HISTORY_LIMIT: usize = 10;
+
+/// File in the worktree's `.zed` directory that tells other tools which
+/// interpreter is selected, when the `selection_file` setting is on.
+const SELECTION_FILE: &str = "python-environment.json";
+
+/// Version of the [`SELECTION_FILE`] format, bumped on incompatible changes.
+const SELECTION_FILE_SCHEMA: u64 = 1;
+
+/// An environment chosen with `/pyenvselect` for a single worktree.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct SelectedEnvironment {
+    pub(crate) name: String,
+    pub(crate) python_path: PathBuf,
+    pub(crate) version: Option<String>,
+    #[serde(default)]
+    pub(crate) provider: String,
+    /// See [`PythonEnvironment::guest`].
+    #[serde(default)]
+    pub(crate) guest: Option<Guest>,
+}
+
+impl SelectedEnvironment {
+    pub(crate) fn from_environment(env: &PythonEnvironment) -> Self {
+        Self {
+            name: env.name.clone(),
+            python_path: env.python_path.clone(),
+            version: env.version.clone().or_else(|| {
+                env.guest
+                    .is_none()
+                    .then(|| query_python_version(&env.python_path))
+                    .flatten()
+            }),
+            provider: env.provider.clone(),
+            guest: env.guest.clone(),
+        }
+    }
+
+    /// How the [`SELECTION_FILE`] and `--json` output describe the selection.
+    pub(crate) fn to_json(&self) -> Value {
+        let (container, wsl_distro) = match &self.guest {
+            Some(Guest::Container(name)) => (Some(name), None),
+            Some(Guest::Wsl(distro)) => (None, Some(distro)),
+            None => (None, None),
+        };
+        json!({
+            "name": self.name,
+            "python": self.python_path.to_string_lossy(),
+            "python_version": self.version,
+            "provider": self.provider,
+            "environment_root": environment_root(&self.python_path)
+                .map(|env_root| env_root.to_string_lossy()),
+            "container": container,
+            "wsl_distro": wsl_distro,
+        })
+    }
+
+    /// Whether the interpreter is gone, e.g. because the environment was
+    /// deleted after it was selected.
+    /// A guest's interpreters can't be checked from the host.
+    pub(crate) fn is_missing(&self) -> bool {
+        self.guest.is_none() && !self.python_path.exists()
+    }
+}
+
+/// Selections keyed by worktree root path, persisted across Zed restarts.
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct SelectionState {
+    pub(crate) selections: HashMap<String, SelectedEnvironment>,
+    /// Root of the worktree seen by the most recent slash command. Argument
+    /// completion is not given a worktree, so discovery falls back to this.
+    #[serde(default)]
+    pub(crate) last_worktree_root: Option<PathBuf>,
+    /// Selections made for each scope, most recent first, without repeats.
+    #[serde(default)]
+    pub(crate) history: HashMap<String, Vec<SelectedEnvironment>>,
+}
+
+impl SelectionState {
+    pub(crate) fn load() -> Self {
+        fs::read_to_string(STATE_FILE)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Makes `selected` the selection for `scope` and moves it to the front
+    /// of the scope's history.
+    pub(crate) fn record(&mut self, scope: String, selected: SelectedEnvironment) {
+        let history = self.history.entry(scope.clone()).or_default();
+        history.retain(|previous| previous.python_path != selected.python_path);
+        history.insert(0, selected.clone());
+        history.truncate(HISTORY_LIMIT);
+        self.selections.insert(scope, selected);
+    }
+
+    /// Drops the selections and history entries of every scope that point
+    /// into the deleted environment at `env_root`, returning whether any
+    /// selection was dropped.
+    pub(crate) fn forget_environment(&mut self, env_root: &Path) -> bool {
+        let selected = self.selections.len();
+        self.selections
+            .retain(|_, selected| !selected.python_path.starts_with(env_root));
+        for history in self.history.values_mut() {
+            history.retain(|previous| !previous.python_path.starts_with(env_root));
+        }
+        self.history.retain(|_, history| !history.is_empty());
+        self.selections.len() != selected
+    }
+
+    pub(crate) fn save(&self) -> Result<(), PyEnvError> {
+        let contents = serde_json::to_string_pretty(self).map_err(|e| PyEnvError::Io {
+            context: "Failed to serialize selection state".to_string(),
+            source: e.into(),
+        })?;
+        fs::write(STATE_FILE, contents)
+            .map_err(PyEnvError::io(format!("Failed to write {}", STATE_FILE)))
+    }
+}
+
+/// The project directory `path` belongs to: the nearest ancestor (within the
+/// worktree) containing a `pyproject.toml` or `requirements.txt`, or the
+/// worktree root itself. Selections made with `--path` are keyed by it.
+pub(crate) fn project_scope(root_path: &Path, path: &str) -> Result<PathBuf, PyEnvError> {
+    let start = root_path.join(path);
+    if !start.exists() {
+        return Err(PyEnvError::InvalidSelection(format!(
+            "{} does not exist in the worktree",
+            path
+        )));
+    }
+    let scope = start
+        .ancestors()
+        .take_while(|dir| dir.starts_with(root_path) && *dir != root_path)
+        .find(|dir| dir.join("pyproject.toml").is_file() || dir.join("requirements.txt").is_file())
+        .unwrap_or(root_path);
+    Ok(scope.to_path_buf())
+}
+
+/// The environment a worktree's committed files pin, so everyone opening the
+/// repository gets the same default selection.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct EnvironmentPin {
+    /// An environment name or identifier, or a path relative to the worktree
+    /// root (an environment directory or interpreter).
+    pub(crate) value: String,
+    /// The file the pin was read from.
+    pub(crate) source: &'static str,
+}
+
+impl EnvironmentPin {
+    /// Reads the pin from [`PIN_FILE`], falling back to `[tool.zed-pyenv]` in
+    /// `pyproject.toml`, with `read` returning a worktree file's contents.
+    pub(crate) fn read(read: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        if let Some(value) = read(PIN_FILE).and_then(|contents| parse_pin_file(&contents)) {
+            return Some(Self {
+                value,
+                source: PIN_FILE,
+            });
+        }
+        let value = read("pyproject.toml").and_then(|contents| read_pyproject_pin(&contents))?;
+        Some(Self {
+            value,
+            source: "pyproject.toml",
+        })
+    }
+
+    /// Whether `env` is the pinned environment of the worktree at `root_path`.
+    pub(crate) fn matches(&self, root_path: &Path, env: &PythonEnvironment) -> bool {
+        if self.value.contains(['/', '\\']) || self.value.starts_with('.') {
+            let pinned = root_path.join(&self.value);
+            env.python_path == pinned || environment_root(&env.python_path) == Some(&pinned)
+        } else {
+            env.id == self.value || env.name == self.value
+        }
+    }
+
+    /// The pin `/pyenvselect --pin` writes for `selected`: its environment
+    /// directory relative to the worktree when it lives inside it, its name
+    /// otherwise.
+    pub(crate) fn for_selection(root_path: &Path, selected: &SelectedEnvironment) -> String {
+        environment_root(&selected.python_path)
+            .and_then(|env_root| env_root.strip_prefix(root_path).ok())
+            .filter(|relative| !relative.as_os_str().is_empty())
+            .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_else(|| selected.name.clone())
+    }
+}
+
+/// Writes the pin for `selected` to the worktree's [`PIN_FILE`], returning
+/// the path written.
+pub(crate) fn write_pin_file(
+    root_path: &Path,
+    selected: &SelectedEnvironment,
+) -> Result<PathBuf, PyEnvError> {
+    let path = root_path.join(PIN_FILE);
+    let contents = format!(
+        "# The Python environment for this project, read by the pyenvselect Zed extension.\n{}\n",
+        EnvironmentPin::for_selection(root_path, selected)
+    );
+    fs::write(&path, contents).map_err(PyEnvError::io(format!(
+        "Failed to write {}",
+        path.display()
+    )))?;
+    Ok(path)
+}
+
+/// The worktree's own environments, other than `selected`, known to run on
+/// another CPU architecture than it.
+fn mismatched_architectures<'a>(
+    selected: &PythonEnvironment,
+    environments: &'a [PythonEnvironment],
+) -> Vec<&'a PythonEnvironment> {
+    let machine = |env: &PythonEnvironment| env.flavor.as_ref()?.machine.clone();
+    let Some(selected_machine) = machine(selected) else {
+        return Vec::new();
+    };
+    environments
+        .iter()
+        .filter(|env| env.project_local && env.python_path != selected.python_path)
+        .filter(|env| machine(env).is_some_and(|other| other != selected_machine))
+        .collect()
+}
+
+/// Writes the [`SELECTION_FILE`] of the worktree at `root_path`: the
+/// worktree's effective selection and those of its project directories,
+/// keyed by worktree-relative path. Returns the path when the file changed.
+pub(crate) fn write_selection_file(
+    root_path: &Path,
+    selected: Option<&SelectedEnvironment>,
+    scoped: &[(PathBuf, SelectedEnvironment)],
+) -> Result<Option<PathBuf>, PyEnvError> {
+    let projects: Map<String, Value> = scoped
+        .iter()
+        .map(|(relative, selected)| {
+            (
+                relative.to_string_lossy().replace('\\', "/"),
+                selected.to_json(),
+            )
+        })
+        .collect();
+    let contents = serde_json::to_string_pretty(&json!({
+        "schema_version": SELECTION_FILE_SCHEMA,
+        "worktree": root_path.to_string_lossy(),
+        "selected": selected.map(SelectedEnvironment::to_json),
+        "projects": projects,
+    }))
+    .map_err(|e| PyEnvError::Io {
+        context: format!("Failed to serialize {}", SELECTION_FILE),
+        source: e.into(),
+    })? + "\n";
+
+    let zed_dir = root_path.join(".zed");
+    let path = zed_dir.join(SELECTION_FILE);
+    // Watchers of the file shouldn't see a write for every language server
+    // configuration request.
+    if fs::read_to_string(&path).is_ok_and(|existing| existing == contents) {
+        return Ok(None);
+    }
+    fs::create_dir_all(&zed_dir).map_err(PyEnvError::io("Failed to create .zed directory"))?;
+    fs::write(&path, contents).map_err(PyEnvError::io(format!(
+        "Failed to write {}",
+        path.display()
+    )))?;
+    Ok(Some(path))
+}
+
+type EnvironmentFilter<'a> = dyn Fn(&PythonEnvironment) -> bool + 'a;
+
+/// Picks the default environment of the worktree at `root_path` from its
+/// discovered environments, along with a description of the heuristic that
+/// picked it. In order: the environment pinned in `.zed-python-env` or
+/// `[tool.zed-pyenv]`, the one direnv activates, the worktree's project
+/// environment (the first `project_env_dirs` match), a Poetry/uv project
+/// environment, the interpreter `.python-version` or `.tool-versions` pins,
+/// the conda
+/// environment `environment.yml` declares, any environment inside the
+/// worktree, an environment named after the worktree, then the environment
+/// activated in the shell Zed was launched from. Discovery lists
+/// project-local environments first, so they also win ties within each step.
+fn default_environment<'a>(
+    all_envs: &'a [PythonEnvironment],
+    root_path: &Path,
+    settings: &ExtensionSettings,
+    pin: Option<&EnvironmentPin>,
+) -> Option<(&'a PythonEnvironment, &'static str)> {
+    let project_name = root_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase());
+    let project_env = all_envs
+        .iter()
+        .filter_map(|env| Some((settings.project_env_rank(root_path, &env.python_path)?, env)))
+        .min_by_key(|(rank, _)| *rank)
+        .map(|(_, env)| env.python_path.clone());
+    let pinned_by = |source: &str, env: &PythonEnvironment| {
+        pin.is_some_and(|pin| pin.source == source && pin.matches(root_path, env))
+    };
+
+    let heuristics: [(&'static str, &EnvironmentFilter); 10] = [
+        ("the environment pinned in .zed-python-env", &|env| {
+            pinned_by(PIN_FILE, env)
+        }),
+        ("the environment pinned in pyproject.toml", &|env| {
+            pinned_by("pyproject.toml", env)
+        }),
+        ("the environment .envrc activates", &|env| {
+            env.provider == "direnv"
+                || env
+                    .also_found_by
+                    .iter()
+                    .any(|provider| provider == "direnv")
+        }),
+        ("the worktree's project environment", &|env| {
+            project_env.as_ref() == Some(&env.python_path)
+        }),
+        ("the Poetry/uv project environment", &|env| {
+            matches!(env.provider.as_str(), "poetry" | "uv") && env.project.is_some()
+        }),
+        (
+            "the interpreter .python-version/.tool-versions pins",
+            &|env| env.pinned,
+        ),
+        ("the conda environment environment.yml declares", &|env| {
+            env.suggested && matches!(env.provider.as_str(), "conda" | "mamba")
+        }),
+        ("an environment inside the worktree", &|env| {
+            env.project_local
+        }),
+        ("the environment named after the project", &|env| {
+            project_name.as_deref() == Some(env.name.to_lowercase().as_str())
+        }),
+        ("the environment active in the launching shell", &|env| {
+            env.active
+        }),
+    ];
+
+    heuristics.iter().find_map(|(reason, matches)| {
+        all_envs
+            .iter()
+            .find(|env| matches(env))
+            .map(|env| (env, *reason))
+    })
+}
+
+impl PythonEnvironmentSelectExtension {
+    /// Resolves `query` against the discovered environments: by identifier
+    /// (`conda:ml`), by exact name, by its 1-based position in the
+    /// `/pyenvlst` output, then fuzzily. A name several environments share
+    /// is ambiguous rather than resolved to the first of them.
+    pub(crate) fn resolve_environment<'a>(
+        &self,
+        environments: &'a [PythonEnvironment],
+        query: &str,
+    ) -> Result<&'a PythonEnvironment, PyEnvError> {
+        if let Some(env) = environments.iter().find(|env| env.id == query) {
+            return Ok(env);
+        }
+        let named: Vec<&PythonEnvironment> = environments
+            .iter()
+            .filter(|env| env.name == query)
+            .collect();
+        match named.as_slice() {
+            [env] => return Ok(env),
+            [] => {}
+            _ => return Err(Self::ambiguous(query, &named)),
+        }
+
+        if let Ok(index) = query.parse::<usize>() {
+            let listed = index.checked_sub(1).and_then(|index| {
+                self.last_listing
+                    .lock()
+                    .ok()
+                    .and_then(|listing| listing.get(index).cloned())
+            });
+            if let Some(python_path) = listed {
+                return environments
+                    .iter()
+                    .find(|env| env.python_path == python_path)
+                    .ok_or_else(|| {
+                        PyEnvError::InvalidSelection(format!(
+                            "environment {} from the last listing ({}) no longer exists; run /pyenvlst again",
+                            index,
+                            python_path.display()
+                        ))
+                    });
+            }
+
+            return index
+                .checked_sub(1)
+                .and_then(|index| environments.get(index))
+                .ok_or_else(|| {
+                    PyEnvError::InvalidSelection(format!(
+                        "environment index {} is out of range (found {} environments)",
+                        index,
+                        environments.len()
+                    ))
+                });
+        }
+
+        let matches = Self::fuzzy_matches(environments, query);
+        match matches.as_slice() {
+            [env] => Ok(env),
+            [] => Err(PyEnvError::InvalidSelection(format!(
+                "no environment matching \"{}\" found; run /pyenvlst to see available environments",
+                query
+            ))),
+            _ => Err(Self::ambiguous(query, &matches)),
+        }
+    }
+
+    /// The error for a query matching several environments, listing their
+    /// identifiers so the user can pick one.
+    fn ambiguous(query: &str, matches: &[&PythonEnvironment]) -> PyEnvError {
+        PyEnvError::InvalidSelection(format!(
+            "\"{}\" matches {} environments, be more specific or use an identifier:\n{}",
+            query,
+            matches.len(),
+            matches
+                .iter()
+                .map(|env| format!("  {} ({})", env.id, env.python_path.display()))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ))
+    }
+
+    /// Environments matching `query`, trying progressively looser matches:
+    /// a substring of the name, a substring of the path, then the query's
+    /// characters appearing in order in the name (so "dj311" finds
+    /// "django-3.11-venv").
+    fn fuzzy_matches<'a>(
+        environments: &'a [PythonEnvironment],
+        query: &str,
+    ) -> Vec<&'a PythonEnvironment> {
+        let query = query.to_lowercase();
+        let is_subsequence = |text: &str| {
+            let mut chars = text.chars();
+            query.chars().all(|q| chars.any(|c| c == q))
+        };
+        let tiers: [&EnvironmentFilter; 3] = [
+            &|env| env.name.to_lowercase().contains(&query),
+            &|env| {
+                env.python_path
+                    .to_string_lossy()
+                    .to_lowercase()
+                    .contains(&query)
+            },
+            &|env| is_subsequence(&env.name.to_lowercase()),
+        ];
+
+        tiers
+            .iter()
+            .map(|matches| environments.iter().filter(|env| matches(env)).collect())
+            .find(|found: &Vec<_>| !found.is_empty())
+            .unwrap_or_default()
+    }
+
+    /// Selects the environment matching `query` for `scope`, a project
+    /// directory inside the worktree (see [`project_scope`]), or for the whole
+    /// worktree when `scope` is `None`. The environments of every open
+    /// worktree are candidates; `in_worktree` names the one to pick from
+    /// when their names collide.
+    pub(crate) fn select_environment(
+        &self,
+        worktree: Option<&Worktree>,
+        query: &str,
+        scope: Option<&Path>,
+        in_worktree: Option<&str>,
+    ) -> Result<SelectedEnvironment, PyEnvError> {
+        let worktree = worktree.ok_or_else(|| {
+            PyEnvError::InvalidSelection("selecting an environment requires a worktree".to_string())
+        })?;
+
+        let mut all_envs = self.workspace_discovery(Some(worktree)).environments;
+        if let Some(name) = in_worktree {
+            let current = worktree_name(Path::new(&worktree.root_path()));
+            all_envs.retain(|env| match &env.worktree {
+                Some(other) => other == name,
+                None => current == name,
+            });
+            if all_envs.is_empty() {
+                return Err(PyEnvError::InvalidSelection(format!(
+                    "no open worktree named \"{}\" (this one is \"{}\")",
+                    name, current
+                )));
+            }
+        }
+        let env = self.resolve_environment(&all_envs, query)?;
+        // Check again rather than trusting the cache: the user may have just
+        // repaired (or broken) the environment. A guest's interpreter can't
+        // be run from here.
+        if let Some(Err(reason)) = env
+            .guest
+            .is_none()
+            .then(|| validate_interpreter(&env.python_path))
+        {
+            return Err(PyEnvError::InvalidSelection(format!(
+                "{} is broken and can't be selected: {}\nInterpreter: {}",
+                env.name,
+                reason,
+                env.python_path.display()
+            )));
+        }
+        let selected = SelectedEnvironment::from_environment(env);
+        let root_path = PathBuf::from(worktree.root_path());
+        self.store_selection(scope.unwrap_or(&root_path), selected.clone())?;
+        self.export_selection(worktree);
+
+        Ok(selected)
+    }
+
+    pub(crate) fn store_selection(
+        &self,
+        scope: &Path,
+        selected: SelectedEnvironment,
+    ) -> Result<(), PyEnvError> {
+        let mut state = self.state.lock().map_err(|_| PyEnvError::Io {
+            context: "Failed to update the selection".to_string(),
+            source: io::Error::other("selection state is poisoned"),
+        })?;
+        logging::info(
+            "selection",
+            format!(
+                "selected {} ({}) for {}",
+                selected.name,
+                selected.python_path.display(),
+                scope.display()
+            ),
+        );
+        state.record(scope.to_string_lossy().into_owned(), selected);
+        state.save()
+    }
+
+    /// Environments previously selected for the whole worktree, most recent
+    /// (the current selection) first.
+    pub(crate) fn selection_history(&self, worktree: &Worktree) -> Vec<SelectedEnvironment> {
+        self.state
+            .lock()
+            .ok()
+            .and_then(|state| state.history.get(&worktree.root_path()).cloned())
+            .unwrap_or_default()
+    }
+
+    /// The selection for the whole worktree, or with `dir` the one for the
+    /// nearest enclosing project directory that has its own selection.
+    pub(crate) fn current_selection(
+        &self,
+        worktree: &Worktree,
+        dir: Option<&Path>,
+    ) -> Option<SelectedEnvironment> {
+        let root_path = PathBuf::from(worktree.root_path());
+        let state = self.state.lock().ok()?;
+        dir.unwrap_or(&root_path)
+            .ancestors()
+            .take_while(|dir| dir.starts_with(&root_path))
+            .find_map(|dir| state.selections.get(dir.to_string_lossy().as_ref()))
+            .cloned()
+    }
+
+    /// Selections made for project directories inside the worktree, keyed by
+    /// their path relative to the worktree root.
+    pub(crate) fn scoped_selections(
+        &self,
+        worktree: &Worktree,
+    ) -> Vec<(PathBuf, SelectedEnvironment)> {
+        let root_path = PathBuf::from(worktree.root_path());
+        let Ok(state) = self.state.lock() else {
+            return Vec::new();
+        };
+        let mut scoped: Vec<_> = state
+            .selections
+            .iter()
+            .filter_map(|(scope, selected)| {
+                let relative = Path::new(scope).strip_prefix(&root_path).ok()?;
+                (!relative.as_os_str().is_empty())
+                    .then(|| (relative.to_path_buf(), selected.clone()))
+            })
+            .collect();
+        scoped.sort_by(|a, b| a.0.cmp(&b.0));
+        scoped
+    }
+
+    /// The environment used when nothing has been selected for the worktree,
+    /// along with a description of the heuristic that picked it; see
+    /// [`default_environment`].
+    pub(crate) fn default_selection(
+        &self,
+        worktree: &Worktree,
+    ) -> Option<(SelectedEnvironment, &'static str)> {
+        let root_path = PathBuf::from(worktree.root_path());
+        let all_envs = self.get_all_python_environments(Some(worktree));
+        let settings = self
+            .settings
+            .lock()
+            .map(|settings| settings.clone())
+            .unwrap_or_default();
+        let pin = EnvironmentPin::read(|file| worktree.read_text_file(file).ok());
+        default_environment(&all_envs, &root_path, &settings, pin.as_ref())
+            .map(|(env, reason)| (SelectedEnvironment::from_environment(env), reason))
+    }
+
+    /// Describes how `selected` disagrees with the version the worktree pins
+    /// in `.python-version` or `.tool-versions`, if it does.
+    pub(crate) fn version_pin_mismatch(
+        worktree: &Worktree,
+        selected: &SelectedEnvironment,
+    ) -> Option<String> {
+        let pin = VersionPin::read(|file| worktree.read_text_file(file).ok())?;
+        let version = selected.version.as_deref()?;
+        (!pin.allows(&selected.name, version)).then(|| {
+            format!(
+                "Python {} doesn't match {} from {}",
+                version,
+                pin.versions.join(", "),
+                pin.source
+            )
+        })
+    }
+
+    /// Warns when `selected` was built for another CPU architecture than the
+    /// worktree's other environments, e.g. an x86_64 interpreter running
+    /// under Rosetta next to arm64 virtual environments: packages installed
+    /// in one don't load in the other, and pip picks wheels for the wrong
+    /// platform.
+    pub(crate) fn architecture_mismatch(
+        &self,
+        worktree: &Worktree,
+        selected: &SelectedEnvironment,
+    ) -> Option<String> {
+        let all_envs = self.get_all_python_environments(Some(worktree));
+        let selected = all_envs
+            .iter()
+            .find(|env| env.python_path == selected.python_path)?;
+        let machine = selected.flavor.as_ref()?.machine.as_deref()?;
+        let others = mismatched_architectures(selected, &all_envs);
+        let other_machine = others.first()?.flavor.as_ref()?.machine.as_deref()?;
+        let names: Vec<&str> = others.iter().map(|env| env.name.as_str()).collect();
+        Some(format!(
+            "{} is {}, but the project's {} {} {}; packages installed for one architecture won't load in the other",
+            selected.name,
+            machine,
+            names.join(", "),
+            if names.len() == 1 { "is" } else { "are" },
+            other_machine
+        ))
+    }
+
+    /// The closest stand-in for a selection whose interpreter is gone: an
+    /// environment recreated under the same name (by the same provider if
+    /// there are several), otherwise the worktree's default.
+    pub(crate) fn replacement_selection(
+        &self,
+        worktree: &Worktree,
+        missing: &SelectedEnvironment,
+    ) -> Option<(SelectedEnvironment, &'static str)> {
+        let all_envs = self.get_all_python_environments(Some(worktree));
+        let recreated = all_envs
+            .iter()
+            .filter(|env| env.name == missing.name && env.broken.is_none())
+            .min_by_key(|env| env.provider != missing.provider);
+        match recreated {
+            Some(env) => Some((
+                SelectedEnvironment::from_environment(env),
+                "the environment recreated under the same name",
+            )),
+            None => self.default_selection(worktree),
+        }
+    }
+
+    /// The explicit selection for the worktree, falling back to the default.
+    /// A selection whose interpreter was deleted is replaced (see
+    /// [`Self::replacement_selection`]) rather than handed to the language
+    /// server as a dead path.
+    pub(crate) fn effective_selection(&self, worktree: &Worktree) -> Option<SelectedEnvironment> {
+        match self.current_selection(worktree, None) {
+            Some(selected) if selected.is_missing() => {
+                logging::warn(
+                    "selection",
+                    format!(
+                        "selected environment {} no longer exists ({})",
+                        selected.name,
+                        selected.python_path.display()
+                    ),
+                );
+                self.replacement_selection(worktree, &selected)
+                    .map(|(selected, _)| selected)
+            }
+            Some(selected) => Some(selected),
+            None => self
+                .default_selection(worktree)
+                .map(|(selected, _)| selected),
+        }
+    }
+
+    /// Brings the worktree's [`SELECTION_FILE`] up to date when the
+    /// `selection_file` setting asks for one. Failing to write it is logged
+    /// rather than failing whatever changed the selection.
+    pub(crate) fn export_selection(&self, worktree: &Worktree) {
+        if !ExtensionSettings::for_worktree(worktree).selection_file {
+            return;
+        }
+        let root_path = PathBuf::from(worktree.root_path());
+        let selected = self.effective_selection(worktree);
+        match write_selection_file(
+            &root_path,
+            selected.as_ref(),
+            &self.scoped_selections(worktree),
+        ) {
+            Ok(Some(path)) => logging::debug("selection", format!("wrote {}", path.display())),
+            Ok(None) => {}
+            Err(error) => logging::warn("selection", error.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::Flavor;
+    use crate::test_support::{environment, Fixture};
+    use zed_extension_api::Extension;
+
+    fn environments() -> Vec<PythonEnvironment> {
+        vec![
+            environment("api", "/work/api/.venv/bin/python"),
+            environment("api-legacy", "/work/api-legacy/.venv/bin/python"),
+            environment(
+                "data-science",
+                "/home/dev/miniconda3/envs/data-science/bin/python",
+            ),
+            environment("3.12.1", "/home/dev/.pyenv/versions/3.12.1/bin/python"),
+        ]
+    }
+
+    #[test]
+    fn defaults_to_the_pinned_interpreter() {
+        let mut envs = vec![
+            PythonEnvironment {
+                provider: "pyenv".to_string(),
+                ..environment("3.11.9", "/home/dev/.pyenv/versions/3.11.9/bin/python")
+            },
+            PythonEnvironment {
+                provider: "pyenv".to_string(),
+                ..environment("3.12.1", "/home/dev/.pyenv/versions/3.12.1/bin/python")
+            },
+            PythonEnvironment {
+                provider: "system".to_string(),
+                active: true,
+                ..environment("python3", "/usr/bin/python3")
+            },
+        ];
+        // Discovery marks the interpreter the pin resolves to.
+        envs[1].pinned = true;
+
+        let (env, reason) = default_environment(
+            &envs,
+            Path::new("/work/app"),
+            &ExtensionSettings::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(env.name, "3.12.1");
+        assert_eq!(
+            reason,
+            "the interpreter .python-version/.tool-versions pins"
+        );
+
+        // An asdf install pinned in `.tool-versions` instead.
+        let envs = vec![
+            PythonEnvironment {
+                provider: "asdf".to_string(),
+                pinned: true,
+                ..environment(
+                    "3.11.9",
+                    "/home/dev/.asdf/installs/python/3.11.9/bin/python",
+                )
+            },
+            PythonEnvironment {
+                provider: "system".to_string(),
+                active: true,
+                ..environment("python3", "/usr/bin/python3")
+            },
+        ];
+        let (env, _) = default_environment(
+            &envs,
+            Path::new("/work/app"),
+            &ExtensionSettings::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(env.name, "3.11.9");
+    }
+
+    #[test]
+    fn writes_the_selection_file_only_on_change() {
+        let fixture = Fixture::new();
+        let python_path = fixture.venv(".venv");
+        let selected = SelectedEnvironment {
+            name: ".venv".to_string(),
+            python_path: python_path.clone(),
+            version: Some("3.12.1".to_string()),
+            provider: "venv".to_string(),
+            guest: None,
+        };
+        let scoped = [(
+            PathBuf::from("services").join("api"),
+            SelectedEnvironment {
+                guest: Some(Guest::Container("api-1".to_string())),
+                ..selected.clone()
+            },
+        )];
+
+        let path = write_selection_file(fixture.path(), Some(&selected), &scoped)
+            .unwrap()
+            .unwrap();
+        let written: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["schema_version"], 1);
+        assert_eq!(
+            written["selected"]["python"],
+            python_path.to_string_lossy().as_ref()
+        );
+        assert_eq!(
+            written["selected"]["environment_root"],
+            fixture.path().join(".venv").to_string_lossy().as_ref()
+        );
+        assert_eq!(written["projects"]["services/api"]["container"], "api-1");
+
+        assert_eq!(
+            write_selection_file(fixture.path(), Some(&selected), &scoped).unwrap(),
+            None
+        );
+        let cleared = write_selection_file(fixture.path(), None, &[]).unwrap();
+        assert_eq!(cleared, Some(path));
+    }
+
+    #[test]
+    fn finds_environments_of_another_architecture() {
+        let with_machine = |name: &str, path: &str, machine: &str| PythonEnvironment {
+            project_local: true,
+            flavor: Some(Flavor {
+                implementation: "CPython".to_string(),
+                distribution: None,
+                machine: Some(machine.to_string()),
+            }),
+            ..environment(name, path)
+        };
+        let selected = PythonEnvironment {
+            project_local: false,
+            ..with_machine("x86", "/usr/local/bin/python3", "x86_64")
+        };
+        let envs = [
+            selected.clone(),
+            with_machine(".venv", "/work/app/.venv/bin/python", "arm64"),
+            with_machine("tools", "/work/app/tools/.venv/bin/python", "x86_64"),
+            environment("unknown", "/work/app/old/bin/python"),
+        ];
+        let names: Vec<&str> = mismatched_architectures(&selected, &envs)
+            .iter()
+            .map(|env| env.name.as_str())
+            .collect();
+        assert_eq!(names, [".venv"]);
+    }
+
+    fn resolve<'a>(envs: &'a [PythonEnvironment], query: &str) -> Result<&'a str, String> {
+        PythonEnvironmentSelectExtension::new()
+            .resolve_environment(envs, query)
+            .map(|env| env.name.as_str())
+            .map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn exact_name_beats_substring() {
+        assert_eq!(resolve(&environments(), "api"), Ok("api"));
+    }
+
+    #[test]
+    fn shared_names_need_an_identifier() {
+        let mut envs = environments();
+        let mut conda_api = environment("api", "/opt/conda/envs/api/bin/python");
+        conda_api.id = "conda:api".to_string();
+        envs.push(conda_api);
+
+        let error = resolve(&envs, "api").unwrap_err();
+        assert!(
+            error.starts_with("\"api\" matches 2 environments"),
+            "{}",
+            error
+        );
+        assert!(error.contains("conda:api (/opt/conda/envs/api/bin/python)"));
+        let resolved = PythonEnvironmentSelectExtension::new()
+            .resolve_environment(&envs, "conda:api")
+            .unwrap();
+        assert_eq!(
+            resolved.python_path,
+            Path::new("/opt/conda/envs/api/bin/python")
+        );
+    }
+
+    #[test]
+    fn matches_by_position() {
+        assert_eq!(resolve(&environments(), "3"), Ok("data-science"));
+        let error = resolve(&environments(), "9").unwrap_err();
+        assert!(
+            error.contains("out of range (found 4 environments)"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn positions_follow_the_last_listing() {
+        let envs = environments();
+        let extension = PythonEnvironmentSelectExtension::new();
+        *extension.last_listing.lock().unwrap() = envs
+            .iter()
+            .rev()
+            .map(|env| env.python_path.clone())
+            .collect();
+        let resolved = extension.resolve_environment(&envs, "1").unwrap();
+        assert_eq!(resolved.name, "3.12.1");
+
+        *extension.last_listing.lock().unwrap() = vec![PathBuf::from("/gone/bin/python")];
+        let error = extension.resolve_environment(&envs, "1").unwrap_err();
+        assert!(error.to_string().contains("no longer exists"));
+    }
+
+    #[test]
+    fn falls_back_to_path_and_subsequence_matches() {
+        assert_eq!(resolve(&environments(), "legacy"), Ok("api-legacy"));
+        assert_eq!(resolve(&environments(), "miniconda"), Ok("data-science"));
+        assert_eq!(resolve(&environments(), "dsci"), Ok("data-science"));
+    }
+
+    #[test]
+    fn reports_ambiguous_and_missing_queries() {
+        let error = resolve(&environments(), "ap").unwrap_err();
+        assert!(
+            error.starts_with("\"ap\" matches 2 environments"),
+            "{}",
+            error
+        );
+        assert!(error.contains("venv:api-legacy (/work/api-legacy/.venv/bin/python)"));
+
+        let error = resolve(&environments(), "django").unwrap_err();
+        assert!(
+            error.starts_with("no environment matching \"django\""),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn scopes_paths_to_the_nearest_project() {
+        let fixture = Fixture::new();
+        let root = fixture.path();
+        fixture.touch("services/api/pyproject.toml");
+        fixture.touch("services/api/src/app/main.py");
+        fixture.touch("scripts/release.py");
+
+        assert_eq!(
+            project_scope(root, "services/api/src/app").unwrap(),
+            root.join("services/api")
+        );
+        assert_eq!(project_scope(root, "scripts").unwrap(), root);
+        assert!(matches!(
+            project_scope(root, "missing"),
+            Err(PyEnvError::InvalidSelection(_))
+        ));
+    }
+
+    #[test]
+    fn history_keeps_recent_distinct_selections() {
+        let selected = |name: &str| SelectedEnvironment {
+            name: name.to_string(),
+            python_path: PathBuf::from(format!("/envs/{}/bin/python", name)),
+            version: None,
+            provider: "venv".to_string(),
+            guest: None,
+        };
+        let mut state = SelectionState::default();
+        for name in ["py310", "py312", "py310"] {
+            state.record("/work/app".to_string(), selected(name));
+        }
+        let names: Vec<&str> = state.history["/work/app"]
+            .iter()
+            .map(|env| env.name.as_str())
+            .collect();
+        assert_eq!(names, ["py310", "py312"]);
+        assert_eq!(state.selections["/work/app"].name, "py310");
+
+        for index in 0..HISTORY_LIMIT + 5 {
+            state.record("/work/app".to_string(), selected(&index.to_string()));
+        }
+        assert_eq!(state.history["/work/app"].len(), HISTORY_LIMIT);
+        assert_eq!(state.history["/work/app"][0].name, "14");
+    }
+
+    #[test]
+    fn forgets_every_scope_using_a_deleted_environment() {
+        let selected = |path: &str| SelectedEnvironment {
+            name: path.to_string(),
+            python_path: PathBuf::from(format!("{}/bin/python", path)),
+            version: None,
+            provider: "venv".to_string(),
+            guest: None,
+        };
+        let mut state = SelectionState::default();
+        state.record("/work/app".to_string(), selected("/work/app/.venv"));
+        state.record("/work/app".to_string(), selected("/work/app/.venv-old"));
+        state.record("/work/app/api".to_string(), selected("/work/app/.venv"));
+        state.record("/work/app/web".to_string(), selected("/work/app/web/.venv"));
+
+        assert!(state.forget_environment(Path::new("/work/app/.venv")));
+        let mut scopes: Vec<&str> = state.selections.keys().map(String::as_str).collect();
+        scopes.sort();
+        assert_eq!(scopes, ["/work/app", "/work/app/web"]);
+        assert_eq!(state.history["/work/app"].len(), 1);
+        assert!(!state.history.contains_key("/work/app/api"));
+
+        assert!(!state.forget_environment(Path::new("/work/app/.venv")));
+    }
+
+    #[test]
+    fn pins_match_by_path_or_name() {
+        let root = Path::new("/work/api");
+        let envs = environments();
+        let pin = |value: &str| EnvironmentPin {
+            value: value.to_string(),
+            source: PIN_FILE,
+        };
+        let pinned = |value: &str| {
+            envs.iter()
+                .find(|env| pin(value).matches(root, env))
+                .map(|env| env.name.as_str())
+        };
+        assert_eq!(pinned(".venv"), Some("api"));
+        assert_eq!(pinned("./.venv/bin/python"), Some("api"));
+        assert_eq!(pinned("data-science"), Some("data-science"));
+        assert_eq!(pinned("venv:api-legacy"), Some("api-legacy"));
+        assert_eq!(pinned("env"), None);
+
+        let selected = SelectedEnvironment::from_environment(&envs[0]);
+        assert_eq!(EnvironmentPin::for_selection(root, &selected), ".venv");
+    }
+}
@@ -0,0 +1,180 @@
+use crate::environment::{environment_root, find_on_path, is_venv, Installer};
+use crate::error::PyEnvError;
+use crate::process::{output_with_timeout, NETWORK_COMMAND_TIMEOUT};
+use std::path::Path;
+use std::process::Command;
+
+/// The tool that brings a project's environment in line with its manifest.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum SyncManager {
+    /// `uv sync` from `uv.lock`.
+    Uv,
+    /// `poetry install` from `poetry.lock`.
+    Poetry,
+    /// `conda env update` from `environment.yml` (or `.yaml`).
+    Conda(&'static str),
+    /// `pip install -r requirements.txt`.
+    Requirements,
+}
+
+impl SyncManager {
+    /// Picks the manager from the files at the worktree root, lockfiles first
+    /// since they pin exactly what the project was tested with.
+    pub(crate) fn detect(root_path: &Path) -> Option<Self> {
+        let has = |file: &str| root_path.join(file).is_file();
+        if has("uv.lock") {
+            Some(Self::Uv)
+        } else if has("poetry.lock") {
+            Some(Self::Poetry)
+        } else if let Some(file) = ["environment.yml", "environment.yaml"]
+            .into_iter()
+            .find(|file| has(file))
+        {
+            Some(Self::Conda(file))
+        } else if has("requirements.txt") {
+            Some(Self::Requirements)
+        } else {
+            None
+        }
+    }
+
+    /// The file the environment is synced from.
+    pub(crate) fn manifest(&self) -> &'static str {
+        match self {
+            Self::Uv => "uv.lock",
+            Self::Poetry => "poetry.lock",
+            Self::Conda(file) => file,
+            Self::Requirements => "requirements.txt",
+        }
+    }
+
+    /// Whether the manager creates the project environment when there is none.
+    pub(crate) fn creates_environment(&self) -> bool {
+        !matches!(self, Self::Requirements)
+    }
+
+    /// Runs the sync from `root_path` into the environment of `python_path`,
+    /// or into the one the manager creates when `None`, returning the
+    /// command line it ran.
+    pub(crate) fn sync(
+        &self,
+        root_path: &Path,
+        python_path: Option<&Path>,
+    ) -> Result<String, PyEnvError> {
+        let env_root = python_path.and_then(environment_root);
+        if let Self::Requirements = self {
+            let python_path = python_path.ok_or_else(|| {
+                PyEnvError::InvalidSelection(
+                    "no environment to install requirements.txt into; run /pyenvcreate first"
+                        .to_string(),
+                )
+            })?;
+            let installer = Installer::for_environment(python_path, false);
+            installer.install(python_path, Some(root_path), &["-r", "requirements.txt"])?;
+            return Ok(format!("{} install -r requirements.txt", installer.name()));
+        }
+
+        // uv and Poetry manage virtual environments and conda its own
+        // prefixes; pointing one at the other's environment would make it
+        // replace or refuse it.
+        if let Some(env_root) = env_root {
+            let (compatible, kind) = match self {
+                Self::Conda(_) => (env_root.join("conda-meta").is_dir(), "conda environment"),
+                _ => (is_venv(env_root), "virtual environment"),
+            };
+            if !compatible {
+                return Err(PyEnvError::InvalidSelection(format!(
+                    "{} is not a {}, so {} can't sync it; pick another with --env",
+                    env_root.display(),
+                    kind,
+                    self.tool()
+                )));
+            }
+        }
+
+        let tool = find_on_path(self.tool())
+            .ok_or_else(|| PyEnvError::unavailable(self.tool(), "it is not installed"))?;
+        let mut command = Command::new(tool);
+        command.current_dir(root_path);
+        let mut invocation = match self {
+            Self::Uv => {
+                command.arg("sync");
+                if let Some(env_root) = env_root {
+                    command.env("UV_PROJECT_ENVIRONMENT", env_root);
+                }
+                "uv sync".to_string()
+            }
+            Self::Poetry => {
+                command.args(["install", "--no-interaction"]);
+                // Poetry installs into the active virtual environment.
+                if let Some(env_root) = env_root {
+                    command.env("VIRTUAL_ENV", env_root);
+                }
+                "poetry install".to_string()
+            }
+            Self::Conda(file) => {
+                command.args(["env", "update", "--file", file, "--prune"]);
+                if let Some(env_root) = env_root {
+                    command.arg("--prefix").arg(env_root);
+                }
+                format!("conda env update --file {} --prune", file)
+            }
+            Self::Requirements => unreachable!("installed with pip above"),
+        };
+        if let Some(env_root) = env_root {
+            invocation.push_str(&format!(" (into {})", env_root.display()));
+        }
+
+        let output = output_with_timeout(&mut command, NETWORK_COMMAND_TIMEOUT)?;
+        if !output.status.success() {
+            return Err(PyEnvError::DiscoveryFailed {
+                context: invocation,
+                message: format!(
+                    "exited with {}: {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            });
+        }
+        Ok(invocation)
+    }
+
+    fn tool(&self) -> &'static str {
+        match self {
+            Self::Uv => "uv",
+            Self::Poetry => "poetry",
+            Self::Conda(_) => "conda",
+            Self::Requirements => "pip",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Fixture;
+
+    #[test]
+    fn prefers_lockfiles() {
+        let fixture = Fixture::new();
+        assert_eq!(SyncManager::detect(fixture.path()), None);
+
+        fixture.touch("requirements.txt");
+        assert_eq!(
+            SyncManager::detect(fixture.path()),
+            Some(SyncManager::Requirements)
+        );
+        fixture.touch("environment.yaml");
+        assert_eq!(
+            SyncManager::detect(fixture.path()),
+            Some(SyncManager::Conda("environment.yaml"))
+        );
+        fixture.touch("poetry.lock");
+        assert_eq!(
+            SyncManager::detect(fixture.path()),
+            Some(SyncManager::Poetry)
+        );
+        fixture.touch("uv.lock");
+        assert_eq!(SyncManager::detect(fixture.path()), Some(SyncManager::Uv));
+    }
+}
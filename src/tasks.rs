@@ -0,0 +1,211 @@
+use std::path::Path;
+use zed_extension_api::serde_json::{json, Value};
+
+/// Prefix of the labels of the tasks and debug configurations this extension
+/// writes, so they can be told apart from (and replaced without touching) the
+/// user's own.
+pub(crate) const TASK_LABEL_PREFIX: &str = "pyenvselect: ";
+
+/// Refers to an interpreter inside the worktree through `$ZED_WORKTREE_ROOT`,
+/// so the generated file works on every checkout.
+fn interpreter_reference(python_path: &Path, root_path: Option<&Path>) -> String {
+    match root_path.and_then(|root| python_path.strip_prefix(root).ok()) {
+        Some(relative) => format!("$ZED_WORKTREE_ROOT/{}", relative.to_string_lossy()),
+        None => python_path.to_string_lossy().into_owned(),
+    }
+}
+
+/// Task templates running `python_path`, in Zed's `tasks.json` format. With
+/// a guest `launcher` (see [`crate::environment::Guest::launcher`]),
+/// `python_path` is a path inside the guest and the tasks go through the
+/// launcher, naming files relative to the worktree.
+pub(crate) fn python_tasks(
+    python_path: &Path,
+    root_path: Option<&Path>,
+    launcher: Option<&[String]>,
+) -> Vec<Value> {
+    let (command, prefix, file) = match launcher {
+        Some([command, args @ ..]) => (
+            command.clone(),
+            args.iter()
+                .cloned()
+                .chain([python_path.to_string_lossy().into_owned()])
+                .collect(),
+            "$ZED_RELATIVE_FILE",
+        ),
+        _ => (
+            interpreter_reference(python_path, root_path),
+            Vec::new(),
+            "$ZED_FILE",
+        ),
+    };
+    let task = |label: &str, args: &[&str]| {
+        let args: Vec<String> = prefix
+            .iter()
+            .cloned()
+            .chain(
+                args.iter()
+                    .map(|arg| if *arg == "$ZED_FILE" { file } else { arg }.to_string()),
+            )
+            .collect();
+        json!({
+            "label": format!("{}{}", TASK_LABEL_PREFIX, label),
+            "command": command,
+            "args": args,
+            "cwd": "$ZED_WORKTREE_ROOT",
+            "use_new_terminal": false,
+            "reveal": "always",
+        })
+    };
+    vec![
+        task("run current file", &["$ZED_FILE"]),
+        task("run current module", &["-m", "$ZED_STEM"]),
+        task("pytest", &["-m", "pytest"]),
+        task("pytest current file", &["-m", "pytest", "$ZED_FILE"]),
+        task(
+            "pytest current test",
+            &["-m", "pytest", "$ZED_FILE", "-k", "$ZED_SYMBOL"],
+        ),
+    ]
+}
+
+/// debugpy launch configurations using `python_path`, in Zed's `debug.json`
+/// format.
+pub(crate) fn debug_configurations(python_path: &Path, root_path: Option<&Path>) -> Vec<Value> {
+    let python = interpreter_reference(python_path, root_path);
+    let configuration = |label: &str, target: Value| {
+        let mut configuration = json!({
+            "label": format!("{}{}", TASK_LABEL_PREFIX, label),
+            "adapter": "Debugpy",
+            "request": "launch",
+            "python": python,
+            "cwd": "$ZED_WORKTREE_ROOT",
+            "justMyCode": false,
+        });
+        if let (Value::Object(configuration), Value::Object(target)) = (&mut configuration, target)
+        {
+            configuration.extend(target);
+        }
+        configuration
+    };
+    vec![
+        configuration("debug current file", json!({ "program": "$ZED_FILE" })),
+        configuration(
+            "debug pytest current file",
+            json!({ "module": "pytest", "args": ["$ZED_FILE"] }),
+        ),
+    ]
+}
+
+/// Replaces the extension's entries in the `tasks.json` or `debug.json`
+/// array `existing` with `tasks`, keeping every other entry in place.
+pub(crate) fn merge_generated(existing: Value, tasks: Vec<Value>) -> Value {
+    let mut merged: Vec<Value> = match existing {
+        Value::Array(existing) => existing
+            .into_iter()
+            .filter(|task| {
+                !task["label"]
+                    .as_str()
+                    .is_some_and(|label| label.starts_with(TASK_LABEL_PREFIX))
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    merged.extend(tasks);
+    Value::Array(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn references_worktree_interpreters_relatively() {
+        let tasks = python_tasks(
+            Path::new("/work/app/.venv/bin/python"),
+            Some(Path::new("/work/app")),
+            None,
+        );
+        assert_eq!(tasks[0]["label"], "pyenvselect: run current file");
+        assert_eq!(tasks[0]["command"], "$ZED_WORKTREE_ROOT/.venv/bin/python");
+        assert_eq!(tasks[0]["args"], json!(["$ZED_FILE"]));
+
+        let tasks = python_tasks(
+            Path::new("/opt/conda/bin/python"),
+            Some(Path::new("/work/app")),
+            None,
+        );
+        assert_eq!(tasks[0]["command"], "/opt/conda/bin/python");
+    }
+
+    #[test]
+    fn runs_containerized_interpreters_with_docker_exec() {
+        let launcher = [
+            "docker",
+            "exec",
+            "-it",
+            "-w",
+            "/workspaces/app",
+            "app-web-1",
+        ]
+        .map(str::to_string);
+        let tasks = python_tasks(
+            Path::new("/usr/local/bin/python3"),
+            Some(Path::new("/work/app")),
+            Some(&launcher),
+        );
+        assert_eq!(tasks[0]["command"], "docker");
+        assert_eq!(
+            tasks[0]["args"],
+            json!([
+                "exec",
+                "-it",
+                "-w",
+                "/workspaces/app",
+                "app-web-1",
+                "/usr/local/bin/python3",
+                "$ZED_RELATIVE_FILE"
+            ])
+        );
+    }
+
+    #[test]
+    fn launches_debugpy_with_the_interpreter() {
+        let configurations = debug_configurations(
+            Path::new("/work/app/.venv/bin/python"),
+            Some(Path::new("/work/app")),
+        );
+        assert_eq!(configurations[0]["adapter"], "Debugpy");
+        assert_eq!(
+            configurations[0]["python"],
+            "$ZED_WORKTREE_ROOT/.venv/bin/python"
+        );
+        assert_eq!(configurations[0]["program"], "$ZED_FILE");
+        assert_eq!(configurations[1]["module"], "pytest");
+    }
+
+    #[test]
+    fn replaces_only_own_tasks() {
+        let existing = json!([
+            { "label": "cargo check", "command": "cargo" },
+            { "label": "pyenvselect: pytest", "command": "/old/python" },
+        ]);
+        let merged = merge_generated(
+            existing,
+            python_tasks(Path::new("/new/bin/python"), None, None),
+        );
+        let labels: Vec<&str> = merged
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|task| task["label"].as_str().unwrap())
+            .collect();
+        assert_eq!(labels[0], "cargo check");
+        assert_eq!(labels.len(), 6);
+        assert!(merged
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|task| task["command"] != "/old/python"));
+    }
+}
@@ -0,0 +1,61 @@
+use crate::environment::PythonEnvironment;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A scratch directory under the system temp dir, removed when dropped.
+pub(crate) struct Fixture {
+    root: PathBuf,
+}
+
+impl Fixture {
+    pub(crate) fn new() -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        let root = std::env::temp_dir().join(format!(
+            "pyenvselect-test-{}-{}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&root).unwrap();
+        Self { root }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// Writes `contents` to `relative`, creating its parent directories.
+    pub(crate) fn write(&self, relative: &str, contents: &str) -> PathBuf {
+        let path = self.root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    pub(crate) fn touch(&self, relative: &str) -> PathBuf {
+        self.write(relative, "")
+    }
+
+    /// Lays out a POSIX virtual environment at `relative` and returns its
+    /// interpreter.
+    pub(crate) fn venv(&self, relative: &str) -> PathBuf {
+        self.write(&format!("{}/pyvenv.cfg", relative), "version = 3.12.1\n");
+        self.touch(&format!("{}/bin/python", relative))
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+pub(crate) fn environment(name: &str, python_path: &str) -> PythonEnvironment {
+    PythonEnvironment {
+        name: name.to_string(),
+        id: format!("venv:{}", name),
+        python_path: PathBuf::from(python_path),
+        provider: "venv".to_string(),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,153 @@
+use crate::environment::python_command;
+use crate::error::PyEnvError;
+use crate::process::command_stdout;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Locates each module named on the command line twice: as code run from the
+/// working directory imports it (the directory first on `sys.path`), and
+/// with that directory left out, which is what is installed. Also reports
+/// the distribution providing the top-level package, and whether the
+/// installed module is part of the standard library.
+const LOCATE_SCRIPT: &str = r#"
+import sys
+# Keep the script's own imports clear of the worktree it inspects.
+worktree_path = sys.path[:]
+sys.path[:] = [p for p in sys.path if p != ""]
+import importlib, importlib.util, json, os, sysconfig
+from importlib import metadata
+
+cwd = os.getcwd()
+installed_path = [p for p in worktree_path if os.path.abspath(p or ".") != cwd]
+stdlib_dirs = {os.path.realpath(sysconfig.get_path(k)) for k in ("stdlib", "platstdlib")}
+
+def locate(name, search_path):
+    sys.path[:] = search_path
+    forget(name.split(".")[0])
+    importlib.invalidate_caches()
+    try:
+        spec = importlib.util.find_spec(name)
+    except (ImportError, ValueError):
+        return None
+    if spec is None:
+        return None
+    if spec.origin and spec.origin != "namespace":
+        return spec.origin
+    return next(iter(spec.submodule_search_locations or []), None)
+
+def forget(top):
+    for module in [m for m in sys.modules if m == top or m.startswith(top + ".")]:
+        del sys.modules[module]
+
+def distribution(top):
+    try:
+        names = metadata.packages_distributions().get(top, [])
+    except AttributeError:
+        names = []
+    for name in names or [top]:
+        try:
+            return name, metadata.version(name)
+        except metadata.PackageNotFoundError:
+            pass
+    return None, None
+
+def standard_library(top, installed):
+    if top in getattr(sys, "stdlib_module_names", ()) or installed in ("built-in", "frozen"):
+        return True
+    if not installed:
+        return False
+    path = os.path.realpath(installed)
+    return not {"site-packages", "dist-packages"} & set(path.split(os.sep)) and any(
+        path.startswith(d + os.sep) for d in stdlib_dirs
+    )
+
+results = []
+for name in sys.argv[1:]:
+    resolved = locate(name, worktree_path)
+    installed = locate(name, installed_path)
+    top = name.split(".")[0]
+    dist, version = distribution(top)
+    results.append({
+        "name": name,
+        "resolved": resolved,
+        "installed": installed,
+        "distribution": dist,
+        "version": version,
+        "standard_library": standard_library(top, installed),
+    })
+print(json.dumps(results))
+"#;
+
+/// Where a module is imported from in an environment.
+#[derive(Debug, Deserialize)]
+pub(crate) struct ModuleLocation {
+    pub(crate) name: String,
+    /// What `import <name>` loads in code run from the worktree root: a
+    /// file, a namespace package's directory, or `built-in`/`frozen`.
+    pub(crate) resolved: Option<String>,
+    /// What it loads when the worktree root isn't on `sys.path`.
+    pub(crate) installed: Option<String>,
+    /// The distribution providing the top-level package, and its version.
+    pub(crate) distribution: Option<String>,
+    pub(crate) version: Option<String>,
+    /// Whether the installed module comes with Python itself.
+    #[serde(default)]
+    pub(crate) standard_library: bool,
+}
+
+impl ModuleLocation {
+    /// Whether a file or directory in the worktree at `root_path` is
+    /// imported in place of the installed module of the same name.
+    pub(crate) fn shadows_installed(&self, root_path: &Path) -> bool {
+        match (&self.resolved, &self.installed) {
+            (Some(resolved), Some(installed)) => {
+                resolved != installed && Path::new(resolved).starts_with(root_path)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Locates `names` in the environment of `python_path`, resolving imports
+/// from `cwd` (normally the worktree root) like code run there would.
+pub(crate) fn locate_modules(
+    python_path: &Path,
+    cwd: &Path,
+    names: &[&str],
+) -> Result<Vec<ModuleLocation>, PyEnvError> {
+    let output = command_stdout(
+        python_command(python_path)
+            .args(["-c", LOCATE_SCRIPT])
+            .args(names)
+            .current_dir(cwd),
+    )?;
+    serde_json::from_str(output.trim()).map_err(PyEnvError::parse("module locations"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_worktree_modules_shadowing_installed_ones() {
+        let locations: Vec<ModuleLocation> = serde_json::from_str(
+            r#"[
+                {"name": "requests", "resolved": "/work/app/requests/__init__.py",
+                 "installed": "/work/app/.venv/lib/python3.12/site-packages/requests/__init__.py",
+                 "distribution": "requests", "version": "2.32.3"},
+                {"name": "numpy", "resolved": "/work/app/.venv/lib/python3.12/site-packages/numpy/__init__.py",
+                 "installed": "/work/app/.venv/lib/python3.12/site-packages/numpy/__init__.py",
+                 "distribution": "numpy", "version": "2.1.0"},
+                {"name": "app", "resolved": "/work/app/app/__init__.py", "installed": null,
+                 "distribution": null, "version": null}
+            ]"#,
+        )
+        .unwrap();
+        let shadowing: Vec<&str> = locations
+            .iter()
+            .filter(|location| location.shadows_installed(Path::new("/work/app")))
+            .map(|location| location.name.as_str())
+            .collect();
+        assert_eq!(shadowing, ["requests"]);
+    }
+}